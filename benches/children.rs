@@ -0,0 +1,71 @@
+//! Measures the allocator traffic of short-lived arenas, with and without `DynamicArena::child`.
+//!
+//! This doesn't need a benchmarking framework, so it's run with `cargo bench --bench children`.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use dynamic_arena::DynamicArena;
+
+const CYCLES: usize = 10_000;
+const VALUES: usize = 1000;
+
+/// Counts the calls to the global allocator
+struct Counting;
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+struct Droppable(u64);
+impl Drop for Droppable {
+    fn drop(&mut self) {
+        black_box(self.0);
+    }
+}
+
+/// Fill an arena like a short-lived sub-arena would be
+fn fill(arena: &DynamicArena) {
+    for index in 0..VALUES {
+        black_box(arena.alloc(Droppable(index as u64)));
+        black_box(arena.alloc_slice_fill_copy(16, index as u8));
+    }
+}
+
+/// Run the create/fill/drop cycles, returning the allocations and nanoseconds per cycle
+fn measure(mut cycle: impl FnMut()) -> (f64, f64) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..CYCLES {
+        cycle();
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    (
+        allocations as f64 / CYCLES as f64,
+        elapsed.as_nanos() as f64 / CYCLES as f64,
+    )
+}
+
+fn main() {
+    let (fresh_allocations, fresh) = measure(|| fill(&DynamicArena::new()));
+    let parent = DynamicArena::new();
+    let (child_allocations, child) = measure(|| fill(&parent.child()));
+    println!(
+        "fresh arenas: {:.2} allocations/cycle, {:.0} ns/cycle",
+        fresh_allocations, fresh
+    );
+    println!(
+        "children:     {:.2} allocations/cycle, {:.0} ns/cycle",
+        child_allocations, child
+    );
+}
@@ -0,0 +1,83 @@
+//! Measures the cost of registering values to be dropped.
+//!
+//! This doesn't need a benchmarking framework, so it's run with `cargo bench --bench registration`.
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use dynamic_arena::DynamicArena;
+
+const VALUES: usize = 1_000_000;
+const ROUNDS: u32 = 10;
+
+struct Droppable(u64);
+impl Drop for Droppable {
+    fn drop(&mut self) {
+        black_box(self.0);
+    }
+}
+/// Another type, so consecutive values can't share a drop entry
+struct OtherDroppable(u64);
+impl Drop for OtherDroppable {
+    fn drop(&mut self) {
+        black_box(self.0);
+    }
+}
+
+/// The fastest of several rounds, in nanoseconds per value
+fn measure(mut round: impl FnMut() -> Duration) -> f64 {
+    let best = (0..ROUNDS).map(|_| round()).min().unwrap();
+    best.as_nanos() as f64 / VALUES as f64
+}
+
+fn main() {
+    let fresh = measure(|| {
+        let arena = DynamicArena::new();
+        let start = Instant::now();
+        for index in 0..VALUES {
+            black_box(arena.alloc(Droppable(index as u64)));
+        }
+        let elapsed = start.elapsed();
+        drop(arena);
+        elapsed
+    });
+    let mut arena = DynamicArena::new();
+    let warm = measure(|| {
+        arena.reset();
+        let start = Instant::now();
+        for index in 0..VALUES {
+            black_box(arena.alloc(Droppable(index as u64)));
+        }
+        start.elapsed()
+    });
+    let exclusive = measure(|| {
+        arena.reset();
+        let view = arena.as_exclusive();
+        let start = Instant::now();
+        for index in 0..VALUES {
+            black_box(view.alloc(Droppable(index as u64)));
+        }
+        start.elapsed()
+    });
+    let interleaved = measure(|| {
+        arena.reset();
+        let start = Instant::now();
+        for index in 0..VALUES / 2 {
+            black_box(arena.alloc(Droppable(index as u64)));
+            black_box(arena.alloc(OtherDroppable(index as u64)));
+        }
+        start.elapsed()
+    });
+    let copy = measure(|| {
+        arena.reset();
+        let start = Instant::now();
+        for index in 0..VALUES {
+            black_box(arena.alloc_copy(index as u64));
+        }
+        start.elapsed()
+    });
+    println!("alloc (fresh arena): {:.2} ns/value", fresh);
+    println!("alloc (reset arena): {:.2} ns/value", warm);
+    println!("alloc (exclusive):   {:.2} ns/value", exclusive);
+    println!("alloc (interleaved): {:.2} ns/value", interleaved);
+    println!("alloc_copy:          {:.2} ns/value", copy);
+}
@@ -0,0 +1,49 @@
+//! A small service which uses a per-request arena for its scratch data.
+//!
+//! Run with `cargo run --example http_pool --features http-pool`.
+use std::convert::Infallible;
+use std::future::{self, Future, Ready};
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+use dynamic_arena::{ArenaLayer, ArenaPool, RequestArena};
+use http::{Request, Response};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Responds with the words of the request body in reverse order
+struct ReverseWords;
+impl Service<Request<String>> for ReverseWords {
+    type Response = Response<String>;
+    type Error = Infallible;
+    type Future = Ready<Result<Response<String>, Infallible>>;
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, request: Request<String>) -> Self::Future {
+        let arena = request.extensions().get::<RequestArena>().unwrap();
+        let words = request
+            .body()
+            .split_whitespace()
+            .map(|word| &*arena.alloc_str(word))
+            .collect::<Vec<&str>>();
+        let reversed = arena.alloc_slice_copy(&words);
+        reversed.reverse();
+        future::ready(Ok(Response::new(reversed.join(" "))))
+    }
+}
+
+fn main() {
+    let pool = Arc::new(ArenaPool::new(16, 1 << 20));
+    let mut service = ArenaLayer::new(Arc::clone(&pool)).layer(ReverseWords);
+    for body in ["hello arena world", "one two three four"] {
+        let future = pin!(service.call(Request::new(String::from(body))));
+        match future.poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(Ok(response)) => println!("{:?} -> {:?}", body, response.body()),
+            Poll::Ready(Err(never)) => match never {},
+            Poll::Pending => unreachable!(),
+        }
+    }
+    println!("{} arena(s) retained by the pool", pool.retained_arenas());
+}
@@ -0,0 +1,73 @@
+//! Merging another arena into this one, so a single drop tears everything down.
+use std::mem;
+
+use crate::DynamicArena;
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Take ownership of everything in the other arena, consuming it.
+    ///
+    /// The other arena's chunks are kept as they are (just like a committed fork),
+    /// so references into it stay valid without copying anything,
+    /// and they're freed when this arena is reset or dropped.
+    /// Since both arenas share the bound `'a`, their values can already refer to each other.
+    ///
+    /// The absorbed values are registered after all of this arena's existing values,
+    /// so within each phase of teardown (see `alloc_in_phase`) they're dropped first,
+    /// in the same order they would've been dropped by the other arena.
+    /// Rolling back to a checkpoint taken before absorbing drops them as well.
+    /// Any contextual finalizers are moved over too,
+    /// while the other arena's settings (like its allocation limit) are discarded.
+    pub fn absorb(&mut self, mut other: DynamicArena<'a, S>) {
+        // The entries stay where they are, in the chunks that are adopted below
+        self.items.append(&other.items);
+        let len = self.items.len();
+        self.record_peak_items(len);
+        #[cfg(feature = "registry")]
+        self.publish_items(len);
+        let finalizers = mem::take(other.finalizers.get_mut());
+        self.finalizers.get_mut().extend(finalizers);
+        self.allocations.set(self.allocations.get() + other.len());
+        self.adopted.get_mut().append(other.adopted.get_mut());
+        self.adopted.get_mut().push(other.into_handle());
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+
+    struct DropOrder<'a>(&'a RefCell<Vec<&'static str>>, &'static str);
+    impl<'a> Drop for DropOrder<'a> {
+        fn drop(&mut self) {
+            self.0.borrow_mut().push(self.1);
+        }
+    }
+
+    #[test]
+    fn merged_teardown() {
+        let order = RefCell::new(Vec::new());
+        let mut arena = DynamicArena::new_bounded();
+        arena.alloc(DropOrder(&order, "own"));
+        let other = DynamicArena::new_bounded();
+        let name: *const str = other.alloc_str("absorbed");
+        other.alloc(DropOrder(&order, "absorbed-1"));
+        other.alloc_in_phase(0, DropOrder(&order, "absorbed-early"));
+        other.alloc(DropOrder(&order, "absorbed-2"));
+        let allocated = other.as_bumpalo().allocated_bytes();
+        arena.absorb(other);
+        assert_eq!(arena.pending_drop_count(), 4);
+        assert_eq!(arena.len(), 5);
+        assert!(arena.stats().capacity_bytes >= allocated);
+        // The absorbed memory wasn't moved
+        assert_eq!(unsafe { &*name }, "absorbed");
+        assert!(arena.contains(unsafe { &*name }));
+        arena.verify().unwrap();
+        assert!(order.borrow().is_empty());
+        drop(arena);
+        assert_eq!(
+            *order.borrow(),
+            vec!["absorbed-early", "absorbed-2", "absorbed-1", "own"]
+        );
+    }
+}
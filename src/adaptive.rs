@@ -0,0 +1,135 @@
+//! Adaptive capacity, learned from the usage of previous cycles.
+use bumpalo::Bump;
+
+use crate::DynamicArena;
+
+/// The capacity learned from previous cycles
+///
+/// Each value is a decaying maximum, so a single unusually large cycle
+/// is gradually forgotten.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct LearnedCapacity {
+    items: usize,
+    bytes: usize,
+}
+impl LearnedCapacity {
+    #[inline]
+    fn update(&mut self, items: usize, bytes: usize) {
+        self.items = decay(self.items).max(items);
+        self.bytes = decay(self.bytes).max(bytes);
+    }
+}
+/// Forget a quarter of the previous maximum
+#[inline]
+fn decay(value: usize) -> usize {
+    value - value / 4
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Enable or disable adaptive capacity, where the arena learns how much it needs from previous cycles.
+    ///
+    /// While this is enabled, each `reset` records the number of items and bytes actually used,
+    /// and pre-sizes the drop list and memory chunk for the next cycle.
+    /// This means steady-state workloads stop needing to allocate new chunks.
+    /// The learned values are a decaying maximum of the previous cycles,
+    /// so the capacity shrinks again if usage drops.
+    ///
+    /// Disabling this forgets the learned values.
+    pub fn set_adaptive_capacity(&mut self, enabled: bool) {
+        self.learned = if enabled {
+            Some(self.learned.unwrap_or_default())
+        } else {
+            None
+        };
+    }
+    /// The capacity learned from previous cycles, as `(item_capacity, byte_capacity)`
+    ///
+    /// This is `None` unless adaptive capacity is enabled.
+    /// These can be persisted and restored with `set_learned_capacity`.
+    #[inline]
+    pub fn learned_capacity(&self) -> Option<(usize, usize)> {
+        self.learned.map(|learned| (learned.items, learned.bytes))
+    }
+    /// Enable adaptive capacity, starting from a previously learned capacity
+    ///
+    /// This immediately ensures the arena has the specified capacity (if it's empty).
+    pub fn set_learned_capacity(&mut self, item_capacity: usize, byte_capacity: usize) {
+        self.learned = Some(LearnedCapacity {
+            items: item_capacity,
+            bytes: byte_capacity,
+        });
+        if self.items.is_empty() && self.used_bytes() == 0 {
+            self.apply_learned_capacity();
+        }
+    }
+    /// Record the usage of the cycle that's about to be reset
+    #[inline]
+    pub(crate) fn learn_capacity(&mut self) {
+        if self.learned.is_some() {
+            let items = self.items.len();
+            let bytes = self.used_bytes();
+            if let Some(ref mut learned) = self.learned {
+                learned.update(items, bytes);
+            }
+        }
+    }
+    /// Pre-size the (empty) arena with the learned capacity
+    pub(crate) fn apply_learned_capacity(&mut self) {
+        if let Some(learned) = self.learned {
+            // The learned bytes already include the drop entries, which live in the arena
+            let limit = self.handle.allocation_limit();
+            if self.handle.chunk_capacity() < learned.bytes
+                && limit.is_none_or(|limit| learned.bytes <= limit)
+            {
+                // Nothing is allocated, so we can just replace the whole allocator
+                if let Ok(handle) = Bump::try_with_capacity(learned.bytes) {
+                    handle.set_allocation_limit(limit);
+                    self.handle = handle;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use crate::test_util::chunk_count;
+
+    #[test]
+    fn converges() {
+        let mut arena = DynamicArena::new();
+        arena.set_adaptive_capacity(true);
+        let mut counts = Vec::new();
+        for _ in 0..5 {
+            for size in 1..=40 {
+                arena.alloc_slice_fill_copy(size * 64, 0u8);
+                arena.alloc(vec![size]);
+            }
+            counts.push(chunk_count(&arena));
+            arena.reset();
+        }
+        assert!(counts[0] > 1);
+        assert_eq!(&counts[1..], &[1, 1, 1, 1]);
+        let (items, bytes) = arena.learned_capacity().unwrap();
+        assert_eq!(items, 40);
+        assert!(bytes >= 40 * 41 / 2 * 64);
+        // The learned values can be restored into a fresh arena
+        let mut restored = DynamicArena::new();
+        restored.set_learned_capacity(items, bytes);
+        assert!(restored.as_bumpalo().chunk_capacity() >= bytes);
+        arena.set_adaptive_capacity(false);
+        assert_eq!(arena.learned_capacity(), None);
+    }
+    #[test]
+    fn decays() {
+        let mut arena = DynamicArena::new();
+        arena.set_learned_capacity(100, 0);
+        for _ in 0..4 {
+            arena.alloc(String::new());
+            arena.reset();
+        }
+        let (items, _) = arena.learned_capacity().unwrap();
+        assert_eq!(items, 33);
+    }
+}
@@ -0,0 +1,168 @@
+//! Values that must be dropped on the thread that allocated them.
+use std::fmt::{self, Debug};
+use std::mem::{self, ManuallyDrop};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, ThreadId};
+
+use crate::{DynamicArena, Sendable};
+
+/// A value whose drop is deferred to its queue,
+/// if the arena is dropped on a different thread.
+struct Affine<'a, T: Send + 'a> {
+    value: ManuallyDrop<T>,
+    owner: ThreadId,
+    queue: FinalizerQueue<'a>,
+}
+impl<'a, T: Send + 'a> Drop for Affine<'a, T> {
+    fn drop(&mut self) {
+        if thread::current().id() == self.owner {
+            unsafe { ManuallyDrop::drop(&mut self.value) }
+        } else {
+            let value = unsafe { ManuallyDrop::take(&mut self.value) };
+            self.queue.push(self.owner, Box::new(value));
+        }
+    }
+}
+
+type PendingDrop<'a> = (ThreadId, Box<dyn Send + 'a>);
+
+/// A queue of values that must be dropped on the thread that allocated them,
+/// but whose arena was dropped on another thread.
+///
+/// The values are moved out of the arena (into a box) before its memory is freed,
+/// and wait in the queue until their owning thread calls `run_finalizers`.
+/// The queue is a shared handle, so it can be cloned and sent back to the owning thread.
+/// If the last handle is dropped while values are still pending,
+/// they're dropped on whichever thread dropped it.
+///
+/// Values are added to the queue with `DynamicArena::alloc_with_affinity`.
+#[derive(Clone, Default)]
+pub struct FinalizerQueue<'a> {
+    pending: Arc<Mutex<Vec<PendingDrop<'a>>>>,
+}
+impl<'a> FinalizerQueue<'a> {
+    /// Create a new empty queue
+    #[inline]
+    pub fn new() -> Self {
+        FinalizerQueue::default()
+    }
+    /// Drop all the pending values that are owned by the current thread,
+    /// returning the number that were dropped
+    ///
+    /// Values owned by other threads are left in the queue.
+    pub fn run_finalizers(&self) -> usize {
+        let current = thread::current().id();
+        let owned = {
+            let mut pending = self.lock();
+            let (owned, others): (Vec<_>, Vec<_>) = mem::take(&mut *pending)
+                .into_iter()
+                .partition(|(owner, _)| *owner == current);
+            *pending = others;
+            owned
+        };
+        let count = owned.len();
+        // The lock is released before any of the drops run
+        drop(owned);
+        count
+    }
+    /// The number of values waiting to be dropped by their owning threads
+    pub fn pending_count(&self) -> usize {
+        self.lock().len()
+    }
+    fn push(&self, owner: ThreadId, value: Box<dyn Send + 'a>) {
+        self.lock().push((owner, value));
+    }
+    #[inline]
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<PendingDrop<'a>>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+impl<'a> Debug for FinalizerQueue<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FinalizerQueue")
+            .field("pending_count", &self.pending_count())
+            .finish()
+    }
+}
+
+impl<'a> DynamicArena<'a, Sendable> {
+    /// Allocate the specified value, which must be dropped on the current thread.
+    ///
+    /// If the arena is dropped (or reset) on the current thread, the value is dropped inline as usual.
+    /// Otherwise, the value is moved into the specified queue,
+    /// and isn't dropped until this thread calls `FinalizerQueue::run_finalizers`.
+    /// This is useful for things like FFI handles, which can be sent between threads
+    /// but have to be destroyed on the thread that created them.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_with_affinity<T: Send + 'a>(
+        &self,
+        queue: &FinalizerQueue<'a>,
+        value: T,
+    ) -> &mut T {
+        let affine = self.alloc(Affine {
+            value: ManuallyDrop::new(value),
+            owner: thread::current().id(),
+            queue: queue.clone(),
+        });
+        &mut affine.value
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct OwnedHandle<'a> {
+        owner: ThreadId,
+        drops: &'a AtomicUsize,
+    }
+    impl<'a> OwnedHandle<'a> {
+        fn new(drops: &'a AtomicUsize) -> Self {
+            OwnedHandle {
+                owner: thread::current().id(),
+                drops,
+            }
+        }
+    }
+    impl<'a> Drop for OwnedHandle<'a> {
+        fn drop(&mut self) {
+            assert_eq!(thread::current().id(), self.owner);
+            self.drops.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn deferred_to_owner() {
+        let drops = AtomicUsize::new(0);
+        let queue = FinalizerQueue::new();
+        let arena = DynamicArena::new_send();
+        for _ in 0..3 {
+            arena.alloc_with_affinity(&queue, OwnedHandle::new(&drops));
+        }
+        arena.alloc(String::from("normal"));
+        thread::scope(|scope| {
+            scope.spawn(move || drop(arena));
+        });
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        assert_eq!(queue.pending_count(), 3);
+        // Other threads can't run them
+        let remote = thread::scope(|scope| scope.spawn(|| queue.run_finalizers()).join());
+        assert_eq!(remote.unwrap(), 0);
+        assert_eq!(queue.run_finalizers(), 3);
+        assert_eq!(drops.load(Ordering::SeqCst), 3);
+        assert_eq!(queue.pending_count(), 0);
+    }
+    #[test]
+    fn inline_on_owner() {
+        let drops = AtomicUsize::new(0);
+        let queue = FinalizerQueue::new();
+        let mut arena = DynamicArena::new_send();
+        let handle = arena.alloc_with_affinity(&queue, OwnedHandle::new(&drops));
+        assert_eq!(handle.drops.load(Ordering::SeqCst), 0);
+        arena.reset();
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        assert_eq!(queue.pending_count(), 0);
+    }
+}
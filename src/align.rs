@@ -0,0 +1,213 @@
+//! Arena-wide minimum alignment, so the low bits of every address can be used for tagging,
+//! along with over-aligning individual allocations.
+use std::alloc::Layout;
+use std::mem;
+use std::ptr::{self, NonNull};
+
+use crate::droplist::DropList;
+use crate::{DynamicArena, EntrySlot, ItemBound, DEFAULT_DROP_PHASE};
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// The alignment every allocation is guaranteed to have, at least
+    ///
+    /// This is `1` unless the arena was built with `DynamicArenaBuilder::min_align`.
+    #[inline]
+    pub fn min_align(&self) -> usize {
+        self.min_align
+    }
+    /// Round the layout's alignment up to the arena's minimum
+    ///
+    /// ## Panics
+    /// If rounding up the size to the alignment overflows.
+    #[inline]
+    pub(crate) fn aligned_layout(&self, layout: Layout) -> Layout {
+        if layout.align() >= self.min_align {
+            layout
+        } else {
+            layout
+                .align_to(self.min_align)
+                .expect("Layout overflowed the minimum alignment")
+        }
+    }
+    /// Move a slice that was built in a bumpalo buffer to meet the minimum alignment, if it doesn't
+    ///
+    /// The buffers only have the natural alignment of their elements.
+    /// The elements are moved (rather than copied), so nothing needs to be dropped in the old buffer.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn realigned<'s, T>(&'s self, slice: &'s mut [T]) -> &'s mut [T] {
+        if slice.as_ptr() as usize & (self.min_align - 1) == 0 {
+            return slice;
+        }
+        let len = slice.len();
+        let layout = self.aligned_layout(Layout::for_value(slice));
+        let target = self.handle.alloc_layout(layout).as_ptr().cast::<T>();
+        ptr::copy_nonoverlapping(slice.as_ptr(), target, len);
+        std::slice::from_raw_parts_mut(target, len)
+    }
+    /// Allocate the specified value at (at least) the specified alignment
+    ///
+    /// This is for the occasional value that needs more alignment than its type,
+    /// like a SIMD or DMA buffer, without raising the alignment of everything else.
+    /// The padding skipped to reach the alignment counts as part of the allocation,
+    /// in the cycle budget and the other per-allocation statistics.
+    ///
+    /// ## Panics
+    /// If the alignment isn't a power of two, or it's less than the alignment of the type.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_with_align<T: 'a>(&self, value: T, align: usize) -> &mut T
+    where
+        S: ItemBound<T>,
+    {
+        let layout = over_aligned(mem::size_of::<T>(), mem::align_of::<T>(), align);
+        let type_name = Some(std::any::type_name::<T>());
+        unsafe {
+            if !mem::needs_drop::<T>() {
+                let target = self.alloc_padded(layout, type_name).as_ptr().cast::<T>();
+                target.write(value);
+                return &mut *target;
+            }
+            // The entry goes right after the value, just like `alloc`
+            let (combined, offset) = layout
+                .extend(DropList::NODE_LAYOUT)
+                .expect("Layout overflowed");
+            let block = self.alloc_padded(combined, type_name);
+            let target = block.as_ptr().cast::<T>();
+            target.write(value);
+            let node = NonNull::new_unchecked(block.as_ptr().add(offset));
+            self.link_drop(target, EntrySlot::At(node), None, DEFAULT_DROP_PHASE);
+            &mut *target
+        }
+    }
+    /// Allocate a copy of the specified slice, starting at (at least) the specified alignment
+    ///
+    /// See `alloc_with_align` for details.
+    ///
+    /// ## Panics
+    /// If the alignment isn't a power of two, or it's less than the alignment of the elements.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_slice_copy_aligned<T: Copy>(&self, src: &[T], align: usize) -> &mut [T]
+    where
+        S: ItemBound<T>,
+    {
+        let layout = over_aligned(mem::size_of_val(src), mem::align_of::<T>(), align);
+        unsafe {
+            let target = self
+                .alloc_padded(layout, Some(std::any::type_name::<T>()))
+                .as_ptr()
+                .cast::<T>();
+            ptr::copy_nonoverlapping(src.as_ptr(), target, src.len());
+            std::slice::from_raw_parts_mut(target, src.len())
+        }
+    }
+    /// Allocate memory with the specified layout, counting the padding before it as allocated
+    ///
+    /// Recycled blocks are never used, since they only have their natural alignment.
+    /// The padding can only be measured within the current chunk,
+    /// so a value that starts a new chunk just counts its size.
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    unsafe fn alloc_padded(&self, layout: Layout, type_name: Option<&'static str>) -> NonNull<u8> {
+        let layout = self.aligned_layout(layout);
+        let chunks = self.handle.allocated_bytes();
+        let remaining = self.handle.chunk_capacity();
+        let ptr = self.handle.alloc_layout(layout);
+        let used = if self.handle.allocated_bytes() == chunks {
+            remaining - self.handle.chunk_capacity()
+        } else {
+            layout.size()
+        };
+        self.record_bytes_as(type_name, used);
+        ptr
+    }
+}
+
+/// The layout of a value with the specified size, at the requested alignment
+///
+/// ## Panics
+/// If the alignment is invalid, or less than the natural alignment.
+#[inline]
+#[cfg_attr(feature = "track-callers", track_caller)]
+fn over_aligned(size: usize, natural: usize, align: usize) -> Layout {
+    assert!(
+        align.is_power_of_two() && align >= natural,
+        "Invalid alignment {}, which must be a power of two that's at least {}",
+        align,
+        natural
+    );
+    Layout::from_size_align(size, align).expect("Layout overflowed")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use crate::CollectIn;
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    fn assert_aligned<T: ?Sized>(value: *const T) {
+        assert_eq!(value as *const u8 as usize % 8, 0);
+    }
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn every_path() {
+        let arena: DynamicArena = DynamicArena::builder().min_align(8).build();
+        assert_eq!(arena.min_align(), 8);
+        for index in 0..100u8 {
+            assert_aligned(arena.alloc_copy(index));
+            assert_aligned(arena.alloc(Box::new(index)));
+            assert_aligned(arena.alloc_str("odd"));
+            assert_aligned(arena.alloc_slice_copy(&[index; 3]));
+            assert_aligned(arena.alloc_layout(Layout::new::<u8>()).as_ptr());
+            assert_aligned(arena.try_alloc_copy(index).unwrap());
+            let unknown_len: &[u8] = (0..index % 7).filter(|_| true).collect_in(&arena);
+            assert_aligned(unknown_len);
+            assert_aligned(arena.alloc_str_from_chars("abc".chars()));
+        }
+        let text: &str = ["a", "bc"].iter().copied().collect_in(&arena);
+        assert_aligned(text);
+        assert_eq!(arena.pending_drop_count(), 100);
+        arena.verify().unwrap();
+        // Forks allocate in their own chunks, but with the same alignment
+        let fork = arena.fork();
+        assert_aligned(fork.alloc_copy(1u8));
+        assert_aligned(fork.alloc_str("x"));
+        fork.commit();
+    }
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn over_aligned() {
+        let mut arena: DynamicArena = DynamicArena::with_capacity(0, 16 * 1024);
+        arena.set_cycle_budget(usize::MAX);
+        for index in 0..50u8 {
+            // Leave the bump pointer at an odd address before each over-aligned allocation
+            let previous = arena.alloc_copy(index) as *mut u8 as usize;
+            let before = arena.cycle_bytes();
+            let slice = arena.alloc_slice_copy_aligned(&[index; 5], 32);
+            assert_eq!(slice.as_ptr() as usize % 32, 0);
+            assert_eq!(slice, [index; 5]);
+            // Everything between the allocations counts, since chunks are filled downwards
+            assert_eq!(
+                arena.cycle_bytes() - before,
+                previous - slice.as_ptr() as usize
+            );
+            arena.alloc_copy(index);
+            let value = arena.alloc_with_align(Box::new(index), 64);
+            assert_eq!(value as *mut Box<u8> as usize % 64, 0);
+            assert_eq!(**value, index);
+        }
+        assert_eq!(arena.pending_drop_count(), 50);
+        arena.verify().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid alignment")]
+    fn under_aligned() {
+        let arena: DynamicArena = DynamicArena::new();
+        arena.alloc_with_align(0u64, 4);
+    }
+}
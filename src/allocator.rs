@@ -0,0 +1,189 @@
+//! Using an arena as the allocator of collections, through the unstable `Allocator` trait
+//! (with the `nightly-allocator` feature) or its stable `allocator-api2` polyfill.
+use std::alloc::Layout;
+use std::ptr::{self, NonNull};
+
+use crate::DynamicArena;
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Check if the specified pointer is the start of the most recent allocation
+    #[inline]
+    fn is_last_allocation(&self, ptr: NonNull<u8>) -> bool {
+        // The chunk is only compared against, never accessed
+        unsafe { self.handle.iter_allocated_chunks_raw() }
+            .next()
+            .is_some_and(|(bump, _)| bump == ptr.as_ptr())
+    }
+    /// Give back the bytes of an allocation, if it's the most recent one
+    #[inline]
+    unsafe fn release_bytes(&self, ptr: NonNull<u8>, size: usize) {
+        // Bumpalo only exposes deallocation through its collections (see `dealloc_last`)
+        drop(bumpalo::collections::Vec::from_raw_parts_in(
+            ptr.as_ptr(),
+            0,
+            size,
+            &self.handle,
+        ));
+    }
+    /// Resize an allocation, returning `None` if a new one can't be allocated
+    ///
+    /// The most recent allocation is moved within the current chunk, reusing its own bytes,
+    /// as long as the new layout is guaranteed to fit there.
+    /// Otherwise, shrinking keeps the allocation where it is, and growing copies it.
+    unsafe fn realloc_bytes(
+        &self,
+        ptr: NonNull<u8>,
+        old: Layout,
+        new: Layout,
+    ) -> Option<NonNull<u8>> {
+        let copied = old.size().min(new.size());
+        let aligned = self.aligned_layout(new);
+        let available = self.handle.chunk_capacity().saturating_add(old.size());
+        if self.is_last_allocation(ptr)
+            && available >= aligned.size().saturating_add(aligned.align())
+        {
+            self.release_bytes(ptr, old.size());
+            let target = self.alloc_layout(new);
+            ptr::copy(ptr.as_ptr(), target.as_ptr(), copied);
+            return Some(target);
+        }
+        if new.size() <= old.size() && ptr.as_ptr() as usize & (new.align() - 1) == 0 {
+            return Some(ptr);
+        }
+        let target = self.try_alloc_layout(new).ok()?;
+        ptr::copy_nonoverlapping(ptr.as_ptr(), target.as_ptr(), copied);
+        Some(target)
+    }
+}
+
+/// Implement an `Allocator` trait for `&DynamicArena`, given the path to the trait and its error
+macro_rules! impl_allocator {
+    ($($api:ident)::+) => {
+        /// Allocates collections in the arena, so their storage lives until it's reset or dropped
+        ///
+        /// The collections own their elements, so nothing is registered to be dropped with the arena:
+        /// a `Vec<String, &DynamicArena>` drops its strings when the `Vec` is dropped,
+        /// and leaking the `Vec` leaks them too.
+        /// Deallocating is free, and it reclaims the bytes if they're the most recent allocation.
+        /// Growing or shrinking the most recent allocation moves it within the chunk, reusing its bytes.
+        unsafe impl<'x, 'a, S> $($api)::+::Allocator for &'x DynamicArena<'a, S> {
+            #[inline]
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, $($api)::+::AllocError> {
+                match self.try_alloc_layout(layout) {
+                    Ok(ptr) => Ok(NonNull::slice_from_raw_parts(ptr, layout.size())),
+                    Err(_) => Err($($api)::+::AllocError),
+                }
+            }
+            #[inline]
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                self.release_bytes(ptr, layout.size());
+            }
+            #[inline]
+            unsafe fn grow(
+                &self,
+                ptr: NonNull<u8>,
+                old_layout: Layout,
+                new_layout: Layout,
+            ) -> Result<NonNull<[u8]>, $($api)::+::AllocError> {
+                match self.realloc_bytes(ptr, old_layout, new_layout) {
+                    Some(ptr) => Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size())),
+                    None => Err($($api)::+::AllocError),
+                }
+            }
+            #[inline]
+            unsafe fn shrink(
+                &self,
+                ptr: NonNull<u8>,
+                old_layout: Layout,
+                new_layout: Layout,
+            ) -> Result<NonNull<[u8]>, $($api)::+::AllocError> {
+                self.grow(ptr, old_layout, new_layout)
+            }
+        }
+    };
+}
+#[cfg(feature = "nightly-allocator")]
+impl_allocator!(std::alloc);
+#[cfg(feature = "allocator-api2")]
+impl_allocator!(allocator_api2::alloc);
+
+#[cfg(all(test, feature = "nightly-allocator"))]
+mod test {
+    use super::*;
+    use crate::test_util::DropCounted;
+    use std::cell::Cell;
+
+    #[test]
+    fn collections() {
+        let counter = Cell::new(0);
+        let arena: DynamicArena = DynamicArena::new();
+        {
+            let mut items = Vec::new_in(&arena);
+            for _ in 0..100 {
+                items.push(DropCounted(&counter));
+            }
+            let boxed = Box::new_in(DropCounted(&counter), &arena);
+            drop(boxed);
+            assert_eq!(counter.get(), 1);
+            // The collection owns its elements, not the arena
+            assert_eq!(arena.pending_drop_count(), 0);
+        }
+        assert_eq!(counter.get(), 101);
+    }
+
+    #[test]
+    fn resizes_last_allocation() {
+        let arena: DynamicArena = DynamicArena::with_capacity(0, 64 * 1024);
+        let mut bytes: Vec<u8, _> = Vec::with_capacity_in(1000, &arena);
+        bytes.extend((0..1000).map(|index| index as u8));
+        let used = arena.used_bytes();
+        // Growing the most recent allocation reuses its bytes, instead of copying it elsewhere
+        bytes.reserve_exact(2000);
+        assert!(arena.used_bytes() - used < 2100);
+        assert!(bytes.iter().copied().eq((0..1000).map(|index| index as u8)));
+        bytes.truncate(10);
+        bytes.shrink_to_fit();
+        assert!(arena.used_bytes() < used);
+        assert_eq!(bytes, (0..10).collect::<Vec<u8>>());
+        // Once something else is allocated, growing has to copy
+        let other = arena.alloc_copy(7u64);
+        bytes.reserve_exact(100);
+        assert_eq!(*other, 7);
+        assert_eq!(bytes, (0..10).collect::<Vec<u8>>());
+    }
+}
+
+#[cfg(all(test, feature = "allocator-api2"))]
+mod test_api2 {
+    use super::*;
+    use allocator_api2::boxed::Box;
+    use allocator_api2::vec::Vec;
+
+    #[test]
+    fn hashbrown_map() {
+        let arena: DynamicArena = DynamicArena::new();
+        let mut map = hashbrown::HashMap::new_in(&arena);
+        for index in 0..1000u32 {
+            map.insert(index, index.to_string());
+        }
+        assert_eq!(map[&500], "500");
+        assert_eq!(arena.pending_drop_count(), 0);
+        assert!(arena.stats().allocated_bytes > 1000 * 4);
+        let boxed = Box::new_in([1u8; 16], &arena);
+        assert_eq!(*boxed, [1; 16]);
+    }
+
+    #[test]
+    fn resizes_last_allocation() {
+        let arena: DynamicArena = DynamicArena::with_capacity(0, 64 * 1024);
+        let mut bytes: Vec<u8, _> = Vec::with_capacity_in(1000, &arena);
+        bytes.extend((0..1000).map(|index| index as u8));
+        let used = arena.used_bytes();
+        bytes.reserve_exact(2000);
+        assert!(arena.used_bytes() - used < 2100);
+        assert!(bytes.iter().copied().eq((0..1000).map(|index| index as u8)));
+        arena.alloc_copy(7u64);
+        bytes.reserve_exact(10_000);
+        assert!(bytes.iter().copied().eq((0..1000).map(|index| index as u8)));
+    }
+}
@@ -0,0 +1,139 @@
+//! Attributing allocated bytes to user-defined regions (like "parse" or "codegen").
+use std::cell::{Cell, RefCell};
+use std::fmt::{self, Debug};
+
+use crate::DynamicArena;
+
+/// The bytes and allocations attributed to each region, along with the stack of open regions
+#[derive(Default)]
+pub(crate) struct RegionAccounting {
+    /// The tag, bytes and count of each region, in the order they were first entered
+    totals: RefCell<Vec<(&'static str, usize, usize)>>,
+    /// The indexes of the open regions, innermost last
+    stack: RefCell<Vec<usize>>,
+    /// The innermost open region
+    current: Cell<Option<usize>>,
+}
+impl RegionAccounting {
+    #[inline]
+    pub(crate) fn record(&self, bytes: usize) {
+        if let Some(index) = self.current.get() {
+            let mut totals = self.totals.borrow_mut();
+            let total = &mut totals[index];
+            total.1 += bytes;
+            total.2 += 1;
+        }
+    }
+    /// Clear the totals, while keeping the regions open
+    pub(crate) fn clear(&mut self) {
+        for total in self.totals.get_mut() {
+            total.1 = 0;
+            total.2 = 0;
+        }
+    }
+}
+
+/// A region that's open until the guard is dropped, as returned by `DynamicArena::enter_region`
+#[must_use = "The region is closed as soon as the guard is dropped"]
+pub struct RegionGuard<'r, 'a, S> {
+    arena: &'r DynamicArena<'a, S>,
+    tag: &'static str,
+}
+impl<S> Drop for RegionGuard<'_, '_, S> {
+    #[inline]
+    fn drop(&mut self) {
+        self.arena.pop_region();
+    }
+}
+impl<S> Debug for RegionGuard<'_, '_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegionGuard")
+            .field("tag", &self.tag)
+            .finish()
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Attribute subsequent allocations to the specified region, until it's popped
+    ///
+    /// Regions nest, with allocations attributed to the innermost one.
+    /// These are unrelated to the regions of values dropped by `drop_region`,
+    /// since they only account for bytes.
+    pub fn push_region(&self, tag: &'static str) {
+        let regions = &self.regions;
+        let index = {
+            let mut totals = regions.totals.borrow_mut();
+            match totals.iter().position(|total| total.0 == tag) {
+                Some(index) => index,
+                None => {
+                    totals.push((tag, 0, 0));
+                    totals.len() - 1
+                }
+            }
+        };
+        regions.stack.borrow_mut().push(index);
+        regions.current.set(Some(index));
+    }
+    /// Close the innermost region, returning its tag (or `None` if no regions are open)
+    pub fn pop_region(&self) -> Option<&'static str> {
+        let regions = &self.regions;
+        let mut stack = regions.stack.borrow_mut();
+        let index = stack.pop()?;
+        regions.current.set(stack.last().copied());
+        Some(regions.totals.borrow()[index].0)
+    }
+    /// Open a region that's closed when the returned guard is dropped
+    ///
+    /// See `push_region` for details.
+    #[inline]
+    pub fn enter_region(&self, tag: &'static str) -> RegionGuard<'_, 'a, S> {
+        self.push_region(tag);
+        RegionGuard { arena: self, tag }
+    }
+    /// The tag, bytes and number of allocations attributed to each region,
+    /// in the order they were first entered
+    ///
+    /// This counts the requested sizes, so it excludes any padding,
+    /// and it doesn't include memory reused from `dealloc_last` or the free lists.
+    /// The totals are cleared when the arena is reset, although any open regions stay open.
+    pub fn region_stats(&self) -> Vec<(&'static str, usize, usize)> {
+        self.regions
+            .totals
+            .borrow()
+            .iter()
+            .filter(|total| total.2 > 0)
+            .copied()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nested() {
+        let mut arena = DynamicArena::new();
+        arena.alloc_copy(0u64);
+        {
+            let _parse = arena.enter_region("parse");
+            arena.alloc_copy(1u64);
+            arena.push_region("typecheck");
+            arena.alloc_copy(2u32);
+            arena.alloc_copy(3u64);
+            assert_eq!(arena.pop_region(), Some("typecheck"));
+            arena.alloc_copy(4u32);
+        }
+        assert_eq!(arena.pop_region(), None);
+        arena.alloc_copy(5u64);
+        assert_eq!(
+            arena.region_stats(),
+            vec![("parse", 12, 2), ("typecheck", 12, 2)]
+        );
+        arena.push_region("codegen");
+        arena.reset();
+        assert_eq!(arena.region_stats(), vec![]);
+        arena.alloc_copy(6u16);
+        assert_eq!(arena.region_stats(), vec![("codegen", 2, 1)]);
+    }
+}
@@ -0,0 +1,214 @@
+//! Fixed-size bitsets allocated in a `DynamicArena`.
+use std::fmt::{self, Debug};
+
+use crate::DynamicArena;
+
+const WORD_BITS: usize = 64;
+
+/// A fixed-size set of bits, whose storage is allocated in an arena.
+///
+/// This is intended for analysis scratch space (liveness, dominance, etc),
+/// where thousands of sets with the same size are all freed together.
+/// Since the storage is plain words, nothing is registered to be dropped.
+///
+/// This is created by `DynamicArena::bitset`.
+pub struct ArenaBitSet<'arena> {
+    words: &'arena mut [u64],
+    len: usize,
+}
+impl<'arena> ArenaBitSet<'arena> {
+    /// The number of bits in this set (not the number that are set)
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Check if this set has zero bits
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Check if the specified bit is set
+    ///
+    /// ## Panics
+    /// If the index is out of bounds.
+    #[inline]
+    pub fn contains(&self, index: usize) -> bool {
+        self.check_index(index);
+        self.words[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+    /// Set the specified bit, returning whether it was previously unset
+    ///
+    /// ## Panics
+    /// If the index is out of bounds.
+    #[inline]
+    pub fn set(&mut self, index: usize) -> bool {
+        self.check_index(index);
+        let word = &mut self.words[index / WORD_BITS];
+        let mask = 1 << (index % WORD_BITS);
+        let changed = *word & mask == 0;
+        *word |= mask;
+        changed
+    }
+    /// Clear the specified bit, returning whether it was previously set
+    ///
+    /// ## Panics
+    /// If the index is out of bounds.
+    #[inline]
+    pub fn clear(&mut self, index: usize) -> bool {
+        self.check_index(index);
+        let word = &mut self.words[index / WORD_BITS];
+        let mask = 1 << (index % WORD_BITS);
+        let changed = *word & mask != 0;
+        *word &= !mask;
+        changed
+    }
+    /// Clear every bit in the set
+    #[inline]
+    pub fn clear_all(&mut self) {
+        for word in self.words.iter_mut() {
+            *word = 0;
+        }
+    }
+    /// The number of bits that are set
+    #[inline]
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+    /// Set every bit that is set in `other`, returning whether anything changed
+    ///
+    /// ## Panics
+    /// If the sets have different lengths.
+    pub fn union_with(&mut self, other: &ArenaBitSet<'_>) -> bool {
+        self.check_same_len(other);
+        let mut changed = false;
+        for (word, &other) in self.words.iter_mut().zip(other.words.iter()) {
+            let updated = *word | other;
+            changed |= updated != *word;
+            *word = updated;
+        }
+        changed
+    }
+    /// Clear every bit that isn't set in `other`, returning whether anything changed
+    ///
+    /// ## Panics
+    /// If the sets have different lengths.
+    pub fn intersect_with(&mut self, other: &ArenaBitSet<'_>) -> bool {
+        self.check_same_len(other);
+        let mut changed = false;
+        for (word, &other) in self.words.iter_mut().zip(other.words.iter()) {
+            let updated = *word & other;
+            changed |= updated != *word;
+            *word = updated;
+        }
+        changed
+    }
+    /// Iterate over the indices of the bits that are set, in ascending order
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words
+            .iter()
+            .enumerate()
+            .flat_map(|(word_index, &word)| {
+                let mut remaining = word;
+                std::iter::from_fn(move || {
+                    if remaining == 0 {
+                        None
+                    } else {
+                        let bit = remaining.trailing_zeros() as usize;
+                        remaining &= remaining - 1;
+                        Some(word_index * WORD_BITS + bit)
+                    }
+                })
+            })
+    }
+    #[inline]
+    fn check_index(&self, index: usize) {
+        assert!(
+            index < self.len,
+            "Index {} out of bounds for bitset of length {}",
+            index,
+            self.len
+        );
+    }
+    #[inline]
+    fn check_same_len(&self, other: &ArenaBitSet<'_>) {
+        assert_eq!(
+            self.len, other.len,
+            "Mismatched bitset lengths: {} and {}",
+            self.len, other.len
+        );
+    }
+}
+impl<'arena> Debug for ArenaBitSet<'arena> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter_ones()).finish()
+    }
+}
+impl<'a, S> DynamicArena<'a, S> {
+    /// Allocate an empty bitset with the specified number of bits
+    pub fn bitset(&self, len: usize) -> ArenaBitSet<'_> {
+        let num_words = len.div_ceil(WORD_BITS);
+        ArenaBitSet {
+            words: self.alloc_slice_fill_copy_value(num_words, 0u64),
+            len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn partial_words() {
+        let arena = DynamicArena::new();
+        let mut set = arena.bitset(130);
+        assert_eq!(set.len(), 130);
+        assert!(set.set(0));
+        assert!(set.set(64));
+        assert!(set.set(129));
+        assert!(!set.set(129));
+        assert!(set.contains(129));
+        assert!(!set.contains(128));
+        assert_eq!(set.iter_ones().collect::<Vec<_>>(), vec![0, 64, 129]);
+        assert_eq!(set.count_ones(), 3);
+        assert!(set.clear(64));
+        assert!(!set.clear(64));
+        assert_eq!(format!("{:?}", set), "{0, 129}");
+        set.clear_all();
+        assert_eq!(set.count_ones(), 0);
+        assert!(arena.bitset(0).is_empty());
+    }
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn out_of_bounds() {
+        let arena = DynamicArena::new();
+        arena.bitset(70).set(70);
+    }
+    #[test]
+    fn union_intersect() {
+        let arena = DynamicArena::new();
+        let mut first = arena.bitset(100);
+        let mut second = arena.bitset(100);
+        first.set(3);
+        first.set(99);
+        second.set(3);
+        second.set(50);
+        assert!(first.union_with(&second));
+        assert!(!first.union_with(&second));
+        assert_eq!(first.iter_ones().collect::<Vec<_>>(), vec![3, 50, 99]);
+        second.clear(50);
+        assert!(first.intersect_with(&second));
+        assert_eq!(first.iter_ones().collect::<Vec<_>>(), vec![3]);
+    }
+    #[test]
+    #[should_panic(expected = "Mismatched bitset lengths")]
+    fn mismatched_union() {
+        let arena = DynamicArena::new();
+        let mut first = arena.bitset(64);
+        let second = arena.bitset(65);
+        first.union_with(&second);
+    }
+}
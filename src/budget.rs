@@ -0,0 +1,211 @@
+//! Per-cycle allocation budgets, for detecting when a single cycle allocates too much.
+use std::cell::Cell;
+
+use crate::{DynamicArena, ItemBound};
+
+/// A callback invoked when a cycle first exceeds its budget
+type BudgetCallback<'a> = Box<dyn FnMut(usize) + 'a>;
+
+/// The bookkeeping for a configured cycle budget
+///
+/// This is boxed so that arenas without a budget only pay for a null check.
+pub(crate) struct CycleBudget<'a> {
+    limit: usize,
+    bytes: Cell<usize>,
+    items: Cell<usize>,
+    exceeded: Cell<bool>,
+    callback: Cell<Option<BudgetCallback<'a>>>,
+}
+/// The callback is only `Send` in a `Sendable` arena
+unsafe impl Send for CycleBudget<'_> {}
+impl<'a> CycleBudget<'a> {
+    #[inline]
+    fn record_bytes(&self, bytes: usize) {
+        let total = self.bytes.get().saturating_add(bytes);
+        self.bytes.set(total);
+        if total > self.limit && !self.exceeded.get() {
+            self.exceeded.set(true);
+            // Take the callback while it runs, in case it somehow reenters the arena
+            if let Some(mut callback) = self.callback.take() {
+                callback(total);
+                self.callback.set(Some(callback));
+            }
+        }
+    }
+    #[inline]
+    fn reset(&self) {
+        self.bytes.set(0);
+        self.items.set(0);
+        self.exceeded.set(false);
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Set the maximum number of bytes a single cycle is expected to allocate,
+    /// where a cycle is the time between calls to `reset`.
+    ///
+    /// This enables counting the bytes and drop registrations since the last reset,
+    /// which restart from zero when the budget is set.
+    /// Exceeding the budget isn't an error, but it can be detected with `budget_exceeded`.
+    ///
+    /// Without a configured budget, the only overhead on allocation is a single branch.
+    pub fn set_cycle_budget(&mut self, bytes: usize) {
+        self.budget = Some(Box::new(CycleBudget {
+            limit: bytes,
+            bytes: Cell::new(0),
+            items: Cell::new(0),
+            exceeded: Cell::new(false),
+            callback: Cell::new(None),
+        }));
+    }
+    /// Set the cycle budget, along with a callback that is invoked
+    /// the first time the budget is exceeded in each cycle.
+    ///
+    /// The callback is given the number of bytes allocated by the cycle so far.
+    /// Just like a value, the callback must satisfy the arena's `ItemBound`.
+    /// See `set_cycle_budget` for details.
+    pub fn set_cycle_budget_with<F: FnMut(usize) + 'a>(&mut self, bytes: usize, callback: F)
+    where
+        S: ItemBound<F>,
+    {
+        self.set_cycle_budget(bytes);
+        if let Some(ref budget) = self.budget {
+            budget.callback.set(Some(Box::new(callback)));
+        }
+    }
+    /// Remove the cycle budget, disabling the per-cycle counters
+    #[inline]
+    pub fn clear_cycle_budget(&mut self) {
+        self.budget = None;
+    }
+    /// The configured cycle budget in bytes, if any
+    #[inline]
+    pub fn cycle_budget(&self) -> Option<usize> {
+        self.budget.as_ref().map(|budget| budget.limit)
+    }
+    /// Check if the current cycle has allocated more than its budget
+    ///
+    /// This is always false if there's no configured budget.
+    #[inline]
+    pub fn budget_exceeded(&self) -> bool {
+        self.budget
+            .as_ref()
+            .is_some_and(|budget| budget.exceeded.get())
+    }
+    /// The number of bytes allocated since the last reset (or since the budget was set)
+    ///
+    /// This is only tracked while there's a cycle budget, and is zero otherwise.
+    #[inline]
+    pub fn cycle_bytes(&self) -> usize {
+        self.budget.as_ref().map_or(0, |budget| budget.bytes.get())
+    }
+    /// The number of values registered to be dropped since the last reset
+    /// (or since the budget was set)
+    ///
+    /// This is only tracked while there's a cycle budget, and is zero otherwise.
+    #[inline]
+    pub fn cycle_items(&self) -> usize {
+        self.budget.as_ref().map_or(0, |budget| budget.items.get())
+    }
+    /// Count the specified number of newly allocated bytes against the cycle budget
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub(crate) fn record_bytes(&self, bytes: usize) {
+        self.record_bytes_as(None, bytes);
+    }
+    /// Count the specified number of newly allocated bytes against the cycle budget,
+    /// along with any other accounting that needs to know the type (if it's known)
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub(crate) fn record_bytes_as(&self, type_name: Option<&'static str>, bytes: usize) {
+        self.count_allocation();
+        #[cfg(feature = "registry")]
+        self.publish_bytes(bytes);
+        if let Some(ref budget) = self.budget {
+            budget.record_bytes(bytes);
+        }
+        #[cfg(feature = "type-stats")]
+        self.type_stats.record(type_name, bytes);
+        #[cfg(feature = "size-histogram")]
+        self.size_counters.record(bytes);
+        self.regions.record(bytes);
+        #[cfg(feature = "test-util")]
+        if let Some(ref recorder) = self.recorder {
+            recorder.record(type_name, bytes);
+        }
+        #[cfg(feature = "track-callers")]
+        self.allocation_sites
+            .record(std::panic::Location::caller(), bytes);
+        self.check_chunks();
+        #[cfg(not(feature = "type-stats"))]
+        let _ = type_name;
+    }
+    /// Count a registered drop against the cycle budget
+    #[inline]
+    pub(crate) fn record_item(&self) {
+        if let Some(ref budget) = self.budget {
+            budget.items.set(budget.items.get() + 1);
+        }
+    }
+    /// Restart the cycle counters
+    #[inline]
+    pub(crate) fn reset_cycle(&mut self) {
+        if let Some(ref budget) = self.budget {
+            budget.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::cell::RefCell;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::rc::Rc;
+
+    #[test]
+    fn boundary() {
+        let mut arena = DynamicArena::new();
+        arena.set_cycle_budget(64);
+        assert_eq!(arena.cycle_budget(), Some(64));
+        arena.alloc_slice_fill_copy(60, 0u8);
+        arena.alloc_copy(0u32);
+        assert_eq!(arena.cycle_bytes(), 64);
+        assert!(!arena.budget_exceeded());
+        arena.alloc_copy(0u8);
+        assert_eq!(arena.cycle_bytes(), 65);
+        assert!(arena.budget_exceeded());
+        arena.reset();
+        assert_eq!(arena.cycle_bytes(), 0);
+        assert!(!arena.budget_exceeded());
+        arena.clear_cycle_budget();
+        arena.alloc_copy(0u64);
+        assert_eq!(arena.cycle_bytes(), 0);
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn callback_and_items() {
+        let crossings = Rc::new(RefCell::new(Vec::new()));
+        let mut arena = DynamicArena::new();
+        // The arena isn't `Sendable`, so the callback doesn't need to be `Send` either
+        let recorded = Rc::clone(&crossings);
+        arena.set_cycle_budget_with(16, move |bytes| recorded.borrow_mut().push(bytes));
+        for cycle in 0..3 {
+            arena.alloc_slice_fill_copy(2, 0u64);
+            if cycle < 2 {
+                // Only the first crossing in each cycle invokes the callback
+                arena.alloc_copy(0u32);
+                arena.alloc_copy(0u32);
+            }
+            arena.alloc(vec![1, 2, 3]);
+            arena.alloc(String::new());
+            assert_eq!(arena.cycle_items(), 2);
+            arena.reset();
+            assert_eq!(arena.cycle_items(), 0);
+        }
+        // The last cycle only crossed the budget once the vector was allocated
+        let last = 16 + std::mem::size_of::<Vec<i32>>();
+        assert_eq!(*crossings.borrow(), vec![20, 20, last]);
+    }
+}
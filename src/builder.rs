@@ -0,0 +1,302 @@
+//! Configurable construction of arenas.
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+
+use bumpalo::Bump;
+
+use crate::droplist::DropList;
+use crate::limit::AllocError;
+use crate::{DynamicArena, NonSend, SendAbility, Sendable};
+
+/// A builder for configuring a `DynamicArena` before it's created.
+///
+/// All the configuration methods are `const`,
+/// so a builder can be stored in a `const` or `static` and built later.
+/// The default configuration produces exactly the same arena as `DynamicArena::new`.
+///
+/// This is created by `DynamicArena::builder`.
+pub struct DynamicArenaBuilder<S = NonSend> {
+    item_capacity: usize,
+    byte_capacity: usize,
+    allocation_limit: Option<usize>,
+    contiguous: bool,
+    address_ordered: bool,
+    min_align: usize,
+    #[cfg(feature = "registry")]
+    name: Option<&'static str>,
+    send: PhantomData<S>,
+}
+impl DynamicArenaBuilder<NonSend> {
+    /// Create a builder with the default configuration
+    #[inline]
+    pub const fn new() -> Self {
+        DynamicArenaBuilder {
+            item_capacity: 0,
+            byte_capacity: 0,
+            allocation_limit: None,
+            contiguous: false,
+            address_ordered: false,
+            min_align: 1,
+            #[cfg(feature = "registry")]
+            name: None,
+            send: PhantomData,
+        }
+    }
+}
+impl<S> DynamicArenaBuilder<S> {
+    /// Build a `Sendable` arena, whose items must all be `Send`
+    #[inline]
+    pub const fn send(self) -> DynamicArenaBuilder<Sendable> {
+        self.with_marker()
+    }
+    /// Build a `NonSend` arena, whose items don't need to be `Send` (this is the default)
+    #[inline]
+    pub const fn non_send(self) -> DynamicArenaBuilder<NonSend> {
+        self.with_marker()
+    }
+    /// Build an arena with a custom marker, which determines the bound on its items
+    ///
+    /// See `ItemBound` for details.
+    #[inline]
+    pub const fn marker<T: SendAbility>(self) -> DynamicArenaBuilder<T> {
+        self.with_marker()
+    }
+    /// Pre-allocate capacity for the specified number of items that need to be dropped
+    ///
+    /// Their drop entries live in the arena, so this adds space for them to the first chunk.
+    #[inline]
+    pub const fn item_capacity(mut self, item_capacity: usize) -> Self {
+        self.item_capacity = item_capacity;
+        self
+    }
+    /// Pre-allocate a chunk with the specified number of bytes
+    #[inline]
+    pub const fn byte_capacity(mut self, byte_capacity: usize) -> Self {
+        self.byte_capacity = byte_capacity;
+        self
+    }
+    /// Limit the total number of bytes the arena can allocate
+    ///
+    /// See `DynamicArena::set_allocation_limit` for details.
+    #[inline]
+    pub const fn allocation_limit(mut self, limit: usize) -> Self {
+        self.allocation_limit = Some(limit);
+        self
+    }
+    /// Keep the entire arena in a single chunk of the specified size, which never grows
+    ///
+    /// This pre-allocates `bytes` (just like `byte_capacity`),
+    /// and limits the arena to the size of that chunk (overriding any `allocation_limit`).
+    /// Since every value is in the same chunk, they can be referred to by
+    /// their 32-bit `ArenaOffset` (see `DynamicArena::offset_of`).
+    /// Once the chunk is full, the fallible methods return an `AllocError`
+    /// and the other methods panic.
+    ///
+    /// ## Panics
+    /// If the size doesn't fit in a `u32`.
+    #[inline]
+    pub const fn contiguous(mut self, bytes: usize) -> Self {
+        assert!(
+            bytes <= u32::MAX as usize,
+            "A contiguous arena can't be larger than 4 GiB"
+        );
+        self.byte_capacity = bytes;
+        self.contiguous = true;
+        self
+    }
+    /// Drop the values in address order, instead of reverse registration order
+    ///
+    /// When the arena is dropped, reset or rolled back, the drop entries are sorted by address
+    /// (so each chunk is walked sequentially) before running them,
+    /// which avoids most of the cache misses when tearing down lots of values.
+    /// The phases (see `DynamicArena::alloc_in_phase`) are still respected,
+    /// but the order within each phase is effectively arbitrary.
+    ///
+    /// This is only correct when the destructors don't depend on the order they run in,
+    /// which is why it's disabled by default. Incremental teardown with `drop_some` is unaffected.
+    #[inline]
+    pub const fn address_ordered_teardown(mut self, enabled: bool) -> Self {
+        self.address_ordered = enabled;
+        self
+    }
+    /// Align every allocation to at least the specified number of bytes
+    ///
+    /// This applies to every method that allocates memory (including `alloc_copy`, `alloc_str`,
+    /// and the layout-based methods), so all the addresses the arena returns have
+    /// their low bits clear and can be relied on for pointer tagging.
+    /// Allocating through `as_bumpalo` directly bypasses it.
+    ///
+    /// ## Panics
+    /// If the alignment isn't a power of two.
+    #[inline]
+    pub const fn min_align(mut self, align: usize) -> Self {
+        assert!(
+            align.is_power_of_two(),
+            "The alignment must be a power of two"
+        );
+        self.min_align = align;
+        self
+    }
+    /// Name the arena, adding it to the registry of live arenas (see the `registry` module)
+    #[cfg(feature = "registry")]
+    #[inline]
+    pub const fn name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+    /// Create the arena with this configuration,
+    /// whose items must outlive the lifetime `'a`.
+    pub fn build<'a>(self) -> DynamicArena<'a, S> {
+        let handle = Bump::with_capacity(self.total_capacity());
+        self.apply_limit(&handle);
+        self.finish(DynamicArena::from_parts(handle))
+    }
+    /// Try to create the arena with this configuration,
+    /// returning an error instead of aborting if the initial capacity can't be allocated.
+    pub fn try_build<'a>(self) -> Result<DynamicArena<'a, S>, AllocError> {
+        let failed = |requested| AllocError {
+            requested,
+            usage: 0,
+            limit: None,
+        };
+        let capacity = self.total_capacity();
+        let handle = Bump::try_with_capacity(capacity).map_err(|_| failed(capacity))?;
+        self.apply_limit(&handle);
+        Ok(self.finish(DynamicArena::from_parts(handle)))
+    }
+    /// The size of the first chunk, including space for the drop entries
+    #[inline]
+    fn total_capacity(&self) -> usize {
+        self.byte_capacity
+            .saturating_add(self.item_capacity.saturating_mul(DropList::NODE_SIZE))
+    }
+    /// Limit the new arena, to its first chunk if it's contiguous
+    ///
+    /// A limit that's already exceeded would be ignored by bumpalo,
+    /// so the contiguous limit is the exact size of the chunk.
+    #[inline]
+    fn apply_limit(&self, handle: &Bump) {
+        handle.set_allocation_limit(if self.contiguous {
+            Some(handle.allocated_bytes())
+        } else {
+            self.allocation_limit
+        });
+    }
+    #[inline]
+    fn finish<'a>(&self, mut arena: DynamicArena<'a, S>) -> DynamicArena<'a, S> {
+        arena.address_ordered = self.address_ordered;
+        arena.min_align = self.min_align;
+        #[cfg(feature = "registry")]
+        if let Some(name) = self.name {
+            arena.register(name);
+        }
+        arena
+    }
+    #[inline]
+    pub(crate) const fn with_marker<T>(self) -> DynamicArenaBuilder<T> {
+        DynamicArenaBuilder {
+            item_capacity: self.item_capacity,
+            byte_capacity: self.byte_capacity,
+            allocation_limit: self.allocation_limit,
+            contiguous: self.contiguous,
+            address_ordered: self.address_ordered,
+            min_align: self.min_align,
+            #[cfg(feature = "registry")]
+            name: self.name,
+            send: PhantomData,
+        }
+    }
+}
+impl<S> Debug for DynamicArenaBuilder<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("DynamicArenaBuilder");
+        debug
+            .field("item_capacity", &self.item_capacity)
+            .field("byte_capacity", &self.byte_capacity)
+            .field("allocation_limit", &self.allocation_limit)
+            .field("contiguous", &self.contiguous)
+            .field("address_ordered", &self.address_ordered)
+            .field("min_align", &self.min_align);
+        #[cfg(feature = "registry")]
+        debug.field("name", &self.name);
+        debug.finish()
+    }
+}
+impl Default for DynamicArenaBuilder<NonSend> {
+    #[inline]
+    fn default() -> Self {
+        DynamicArenaBuilder::new()
+    }
+}
+impl DynamicArena<'static, NonSend> {
+    /// Create a builder for configuring a new arena
+    ///
+    /// See `DynamicArenaBuilder` for details.
+    #[inline]
+    pub const fn builder() -> DynamicArenaBuilder<NonSend> {
+        DynamicArenaBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIGURED: DynamicArenaBuilder<Sendable> = DynamicArena::builder()
+        .send()
+        .item_capacity(16)
+        .byte_capacity(4096);
+
+    fn shape<S>(arena: &DynamicArena<'_, S>) -> (usize, usize, usize, Option<usize>) {
+        (
+            arena.as_bumpalo().allocated_bytes(),
+            arena.as_bumpalo().chunk_capacity(),
+            arena.item_capacity(),
+            arena.allocation_limit(),
+        )
+    }
+
+    #[test]
+    fn default_matches_constructors() {
+        let built: DynamicArena = DynamicArena::builder().build();
+        assert_eq!(shape(&built), shape(&DynamicArena::new()));
+        let built_send = DynamicArena::builder().send().build();
+        assert_eq!(shape(&built_send), shape(&DynamicArena::new_send()));
+        let built_capacity: DynamicArena = DynamicArena::builder()
+            .item_capacity(10)
+            .byte_capacity(1000)
+            .build();
+        let expected: DynamicArena = DynamicArena::with_capacity(10, 1000);
+        assert_eq!(shape(&built_capacity), shape(&expected));
+    }
+    #[test]
+    fn fallible() {
+        let arena: DynamicArena = DynamicArena::try_with_capacity(10, 1000).unwrap();
+        let expected: DynamicArena = DynamicArena::with_capacity(10, 1000);
+        assert_eq!(shape(&arena), shape(&expected));
+        let err = DynamicArena::<NonSend>::try_with_capacity(0, usize::MAX / 2)
+            .err()
+            .unwrap();
+        assert_eq!(err.requested_size(), usize::MAX / 2);
+        assert_eq!(err.current_usage(), 0);
+        let err = CONFIGURED
+            .item_capacity(usize::MAX)
+            .try_build()
+            .err()
+            .unwrap();
+        assert_eq!(err.requested_size(), usize::MAX);
+        let _: &dyn std::error::Error = &err;
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn configured() {
+        let arena = CONFIGURED.allocation_limit(1 << 20).build();
+        let (_, chunk, items, limit) = shape(&arena);
+        assert!(chunk >= 4096);
+        assert!(items >= 16);
+        assert_eq!(limit, Some(1 << 20));
+        arena.alloc(String::from("sendable")).push('!');
+        let arena = CONFIGURED.non_send().build::<'static>();
+        arena.alloc(std::rc::Rc::new(()));
+    }
+}
@@ -0,0 +1,75 @@
+//! Converting between arenas and the bump allocators they're built on.
+use bumpalo::Bump;
+
+use crate::DynamicArena;
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Wrap an existing bump allocator, keeping its chunks (and its allocation limit)
+    ///
+    /// Anything already allocated in the bump is left alone, and is never dropped by the arena.
+    #[inline]
+    pub fn from_bump(bump: Bump) -> Self {
+        DynamicArena::from_parts(bump)
+    }
+    /// Drop everything in the arena, then return the bump allocator with its chunks intact
+    ///
+    /// The chunks of committed forks (see `ArenaFork::commit`) aren't part of the bump,
+    /// so they're freed instead.
+    pub fn into_bump(mut self) -> Bump {
+        self.release_contents();
+        self.into_handle()
+    }
+    /// Drop everything in the arena, then retrieve the underlying bump allocator mutably
+    ///
+    /// This is for operations that need exclusive access to the bump, like `Bump::reset`.
+    /// The drop list and the values themselves live in the bump's chunks,
+    /// so they're dropped first, and the bump can be reset without leaving them dangling.
+    /// Since this requires `&mut self`, no references into the arena can be alive either.
+    /// Anything allocated through the returned reference is never dropped by the arena.
+    pub fn as_bumpalo_mut(&mut self) -> &mut Bump {
+        self.release_contents();
+        &mut self.handle
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use crate::test_util::DropCounted;
+    use std::cell::Cell;
+
+    #[test]
+    fn round_trip() {
+        let counter = Cell::new(0);
+        let warm = Bump::with_capacity(64 * 1024);
+        let capacity = warm.allocated_bytes();
+        let arena: DynamicArena<'_> = DynamicArena::from_bump(warm);
+        let first = arena.alloc(DropCounted(&counter)) as *mut _ as *const u8;
+        for _ in 0..99 {
+            arena.alloc(DropCounted(&counter));
+        }
+        let mut bump = arena.into_bump();
+        assert_eq!(counter.get(), 100);
+        // The same chunk comes back, and it can be reused by another arena
+        assert_eq!(bump.allocated_bytes(), capacity);
+        bump.reset();
+        let arena: DynamicArena<'_> = DynamicArena::from_bump(bump);
+        let reused = arena.alloc(DropCounted(&counter)) as *mut _ as *const u8;
+        assert_eq!(reused, first);
+        drop(arena);
+        assert_eq!(counter.get(), 101);
+    }
+
+    #[test]
+    fn reset_mutably() {
+        let counter = Cell::new(0);
+        let mut arena = DynamicArena::new_bounded();
+        arena.alloc(DropCounted(&counter));
+        arena.as_bumpalo_mut().reset();
+        assert_eq!(counter.get(), 1);
+        assert_eq!(arena.pending_drop_count(), 0);
+        arena.alloc(DropCounted(&counter));
+        drop(arena);
+        assert_eq!(counter.get(), 2);
+    }
+}
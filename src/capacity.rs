@@ -0,0 +1,170 @@
+//! Inspecting and reserving capacity after an arena has been created.
+use bumpalo::Bump;
+
+use crate::droplist::DropList;
+use crate::{DynamicArena, ItemBound};
+
+/// Restores the allocation limit that was lowered by `within_current_chunk`, even if it panics
+struct RestoreLimit<'h>(&'h Bump, Option<usize>);
+impl Drop for RestoreLimit<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.set_allocation_limit(self.1);
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// The number of items that can be registered to be dropped
+    /// without requesting a new chunk
+    ///
+    /// The drop entries live in the arena's chunks, so they share this space with the values,
+    /// which aren't included.
+    #[inline]
+    pub fn item_capacity(&self) -> usize {
+        self.handle.chunk_capacity() / DropList::NODE_SIZE
+    }
+    /// The number of bytes remaining in the current chunk,
+    /// which can be allocated without requesting a new chunk
+    #[inline]
+    pub fn byte_capacity(&self) -> usize {
+        self.handle.chunk_capacity()
+    }
+    /// Reserve capacity for at least `additional` more items to be registered to be dropped.
+    ///
+    /// This reserves space for their drop entries in the current chunk (but not the values),
+    /// and does nothing if it already has enough capacity.
+    ///
+    /// ## Panics
+    /// If the allocation limit would be exceeded.
+    #[inline]
+    pub fn reserve_items(&self, additional: usize) {
+        self.reserve_bytes(additional.saturating_mul(DropList::NODE_SIZE));
+    }
+    /// Reserve at least `additional` bytes in the current chunk,
+    /// so the next allocations don't need to request a new one.
+    ///
+    /// This does nothing if the current chunk already has enough capacity.
+    /// Otherwise, a new chunk is allocated (the old one is kept until the arena is reset).
+    /// Since allocations may need padding for alignment,
+    /// slightly more space may be required for values that aren't bytes.
+    ///
+    /// ## Panics
+    /// If the allocation limit would be exceeded.
+    pub fn reserve_bytes(&self, additional: usize) {
+        if self.handle.chunk_capacity() < additional {
+            /*
+             * Bumpalo doesn't have a way to directly request a new chunk,
+             * so we allocate a buffer of the right size and immediately free it.
+             * Since it's the last allocation, the space is given back to the new chunk.
+             */
+            drop(bumpalo::collections::Vec::<u8>::with_capacity_in(
+                additional,
+                &self.handle,
+            ));
+        }
+    }
+    /// Allocate the specified copyable value only if it fits in the current chunk,
+    /// otherwise returning it back without requesting a new chunk.
+    ///
+    /// See `alloc_within_capacity` for details.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_copy_within_capacity<T: Copy>(&self, value: T) -> Result<&mut T, T>
+    where
+        S: ItemBound<T>,
+    {
+        self.within_current_chunk(|| unsafe {
+            self.try_alloc_registered(value).map(|target| &mut *target)
+        })
+    }
+    /// Run the allocation with the limit lowered to the existing chunks,
+    /// so bumpalo fails instead of asking the global allocator for a new one
+    #[inline]
+    fn within_current_chunk<R>(&self, alloc: impl FnOnce() -> R) -> R {
+        let _restore = RestoreLimit(&self.handle, self.handle.allocation_limit());
+        self.handle
+            .set_allocation_limit(Some(self.handle.allocated_bytes()));
+        alloc()
+    }
+}
+impl<'a, S> DynamicArena<'a, S> {
+    /// Allocate the specified value only if it fits in the current chunk (along with its drop entry),
+    /// otherwise returning it back without requesting a new chunk.
+    ///
+    /// This never asks the global allocator for the arena's memory,
+    /// so it's suitable for real-time code paths that capacity was reserved for up front
+    /// (with `with_capacity` or `reserve_bytes`).
+    /// Debug builds and the instrumentation features may still use the heap for their own bookkeeping.
+    /// Otherwise, this is the same as `alloc`.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_within_capacity<T: 'a>(&self, value: T) -> Result<&mut T, T>
+    where
+        S: ItemBound<T>,
+    {
+        self.within_current_chunk(|| unsafe {
+            self.try_alloc_registered(value).map(|target| &mut *target)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::chunk_count;
+
+    #[test]
+    fn reserve() {
+        let arena = DynamicArena::new();
+        assert_eq!(arena.item_capacity(), 0);
+        arena.reserve_items(100);
+        let capacity = arena.item_capacity();
+        assert!(capacity >= 100);
+        arena.reserve_items(10);
+        assert_eq!(arena.item_capacity(), capacity);
+        arena.reserve_bytes(1 << 16);
+        assert!(arena.byte_capacity() >= 1 << 16);
+        let chunks = chunk_count(&arena);
+        for _ in 0..64 {
+            arena.alloc_slice_fill_copy(1000, 0u8);
+        }
+        assert_eq!(chunk_count(&arena), chunks);
+        let remaining = arena.byte_capacity();
+        let allocated = arena.as_bumpalo().allocated_bytes();
+        arena.reserve_bytes(remaining);
+        assert_eq!(arena.as_bumpalo().allocated_bytes(), allocated);
+        assert_eq!(arena.byte_capacity(), remaining);
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn within_capacity() {
+        let arena = DynamicArena::with_capacity(0, 256);
+        let chunks = chunk_count(&arena);
+        let allocated = arena.as_bumpalo().allocated_bytes();
+        let expected = arena.byte_capacity() / 8;
+        let mut count = 0;
+        while arena.alloc_copy_within_capacity(count as u64).is_ok() {
+            count += 1;
+        }
+        assert_eq!(count, expected);
+        assert_eq!(arena.alloc_copy_within_capacity(7u64), Err(7));
+        let mut arena = arena;
+        arena.reset();
+        // Each value takes up its own space and its drop entry's
+        let expected = arena.byte_capacity() / (8 + DropList::NODE_SIZE);
+        let mut boxes = 0;
+        while let Ok(value) = arena.alloc_within_capacity(Box::new(boxes)) {
+            assert_eq!(**value, boxes);
+            boxes += 1;
+        }
+        assert_eq!(boxes, expected);
+        assert_eq!(arena.pending_drop_count(), expected);
+        assert_eq!(*arena.alloc_within_capacity(Box::new(1)).unwrap_err(), 1);
+        assert_eq!(chunk_count(&arena), chunks);
+        assert_eq!(arena.as_bumpalo().allocated_bytes(), allocated);
+        assert_eq!(arena.allocation_limit(), None);
+        arena.verify().unwrap();
+    }
+}
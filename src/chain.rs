@@ -0,0 +1,230 @@
+//! Chaining arenas together, so allocations past a limit spill into an overflow arena.
+use std::alloc::Layout;
+use std::cell::Cell;
+use std::fmt::{self, Debug};
+use std::mem;
+use std::ptr::{self, NonNull};
+
+use crate::{DynamicArena, ItemBound, NonSend};
+
+/// A pair of arenas, where allocations past the primary arena's limit
+/// transparently go to the overflow arena.
+///
+/// Each value is registered to be dropped with whichever arena actually holds it.
+/// Since both arenas have the same lifetime bound `'a`,
+/// values in either arena can safely reference data in the other.
+///
+/// This is created by `DynamicArena::with_overflow`.
+pub struct ChainedArena<'o, 'a, S = NonSend> {
+    primary: &'o DynamicArena<'a, S>,
+    overflow: &'o DynamicArena<'a, S>,
+    spilled_bytes: Cell<usize>,
+    spilled_allocations: Cell<usize>,
+}
+impl<'o, 'a, S> Debug for ChainedArena<'o, 'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChainedArena")
+            .field("primary", self.primary)
+            .field("overflow", self.overflow)
+            .field("spilled_bytes", &self.spilled_bytes.get())
+            .field("spilled_allocations", &self.spilled_allocations.get())
+            .finish()
+    }
+}
+impl<'o, 'a, S> ChainedArena<'o, 'a, S> {
+    /// The primary arena, which is tried first
+    #[inline]
+    pub fn primary(&self) -> &'o DynamicArena<'a, S> {
+        self.primary
+    }
+    /// The overflow arena, which is used once the primary arena's limit is reached
+    #[inline]
+    pub fn overflow(&self) -> &'o DynamicArena<'a, S> {
+        self.overflow
+    }
+    /// The total number of bytes that spilled into the overflow arena
+    #[inline]
+    pub fn spilled_bytes(&self) -> usize {
+        self.spilled_bytes.get()
+    }
+    /// The number of allocations that spilled into the overflow arena
+    #[inline]
+    pub fn spilled_allocations(&self) -> usize {
+        self.spilled_allocations.get()
+    }
+    /// Allocate the specified copyable value in whichever arena has room
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_copy<T: Copy>(&self, value: T) -> &'o mut T
+    where
+        S: ItemBound<T>,
+    {
+        unsafe {
+            let (_, ptr) = self.alloc_layout(Layout::new::<T>());
+            let ptr = ptr.as_ptr().cast::<T>();
+            ptr.write(value);
+            &mut *ptr
+        }
+    }
+    /// Allocate a copy of the specified string in whichever arena has room
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_str(&self, s: &str) -> &'o mut str {
+        unsafe {
+            let (_, ptr) = self.alloc_layout(Layout::for_value(s));
+            let ptr = ptr.as_ptr();
+            ptr::copy_nonoverlapping(s.as_ptr(), ptr, s.len());
+            std::str::from_utf8_unchecked_mut(std::slice::from_raw_parts_mut(ptr, s.len()))
+        }
+    }
+    /// Allocate the specified layout, falling back to the overflow arena
+    /// if the primary arena's limit was reached.
+    ///
+    /// The arena that holds the allocation is returned along with it.
+    #[inline]
+    unsafe fn alloc_layout(&self, layout: Layout) -> (&'o DynamicArena<'a, S>, NonNull<u8>) {
+        match self.primary.try_alloc_layout(layout) {
+            Ok(ptr) => (self.primary, ptr),
+            Err(_) => {
+                self.record_spill(layout.size());
+                (self.overflow, self.overflow.alloc_layout(layout))
+            }
+        }
+    }
+    #[inline]
+    fn record_spill(&self, size: usize) {
+        self.spilled_bytes.set(self.spilled_bytes.get() + size);
+        self.spilled_allocations
+            .set(self.spilled_allocations.get() + 1);
+    }
+}
+impl<'o, 'a, S> ChainedArena<'o, 'a, S> {
+    /// Allocate the specified value in whichever arena has room,
+    /// returning a reference which will be valid for the lifetime of both arenas.
+    ///
+    /// The value and its drop entry are allocated together,
+    /// so it spills unless both fit in the primary arena.
+    /// This has the same bounds as `DynamicArena::alloc`.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T: 'a>(&self, value: T) -> &'o mut T
+    where
+        S: ItemBound<T>,
+    {
+        match self.primary.try_alloc_or_return(value) {
+            Ok(value) => value,
+            Err((_, value)) => {
+                self.record_spill(mem::size_of::<T>());
+                self.overflow.alloc(value)
+            }
+        }
+    }
+}
+impl<'a, S> DynamicArena<'a, S> {
+    /// Chain this arena with an overflow arena,
+    /// which is used for allocations once this arena's limit is reached.
+    ///
+    /// See `set_allocation_limit` and `ChainedArena` for details.
+    #[inline]
+    pub fn with_overflow<'o>(
+        &'o self,
+        overflow: &'o DynamicArena<'a, S>,
+    ) -> ChainedArena<'o, 'a, S> {
+        ChainedArena {
+            primary: self,
+            overflow,
+            spilled_bytes: Cell::new(0),
+            spilled_allocations: Cell::new(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use crate::test_util::DropCounted;
+
+    #[derive(Copy, Clone)]
+    struct Node<'n> {
+        name: &'n str,
+        next: Option<&'n Node<'n>>,
+    }
+
+    /// Limit the arena to its current chunk
+    fn limit_to_current<S: ItemBound<u8>>(arena: &DynamicArena<'_, S>) {
+        arena.alloc_copy(0u8);
+        arena.set_allocation_limit(Some(arena.as_bumpalo().allocated_bytes()));
+    }
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn spillover() {
+        let counter = Cell::new(0);
+        let primary = DynamicArena::new_bounded();
+        let overflow = DynamicArena::new_bounded();
+        limit_to_current(&primary);
+        {
+            let chain = primary.with_overflow(&overflow);
+            chain.alloc(DropCounted(&counter));
+            assert_eq!(chain.spilled_allocations(), 0);
+            let big = chain.alloc((DropCounted(&counter), [7u8; 1 << 16]));
+            assert_eq!(big.1[1000], 7);
+            assert_eq!(chain.spilled_allocations(), 1);
+            assert!(chain.spilled_bytes() >= 1 << 16);
+            // Small allocations still fit in the primary arena
+            chain.alloc_copy(0u32);
+            assert_eq!(chain.spilled_allocations(), 1);
+            assert!(std::ptr::eq(chain.primary(), &primary));
+            assert!(std::ptr::eq(chain.overflow(), &overflow));
+        }
+        // Each value was registered with the arena that holds it
+        drop(overflow);
+        assert_eq!(counter.get(), 1);
+        drop(primary);
+        assert_eq!(counter.get(), 2);
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn drop_entry_at_limit() {
+        let counter = Cell::new(0);
+        let primary = DynamicArena::new_bounded();
+        let overflow = DynamicArena::new_bounded();
+        limit_to_current(&primary);
+        // Leave room for the value, but not for its drop entry
+        let remaining = primary.as_bumpalo().chunk_capacity();
+        let fill = (remaining - 16) & !7;
+        primary.alloc_layout(Layout::from_size_align(fill, 8).unwrap());
+        assert!(primary.as_bumpalo().chunk_capacity() < 24);
+        let chain = primary.with_overflow(&overflow);
+        chain.alloc(DropCounted(&counter));
+        assert_eq!(chain.spilled_allocations(), 1);
+        assert_eq!(primary.pending_drop_count(), 0);
+        assert_eq!(overflow.pending_drop_count(), 1);
+        drop(overflow);
+        assert_eq!(counter.get(), 1);
+    }
+    #[test]
+    fn cross_references() {
+        let primary = DynamicArena::new();
+        let overflow = DynamicArena::new();
+        limit_to_current(&primary);
+        let chain = primary.with_overflow(&overflow);
+        let first = chain.alloc_copy(Node {
+            name: chain.alloc_str("primary"),
+            next: None,
+        });
+        // Spill a long name, referenced from the primary arena
+        let long_name = chain.alloc_str(&"x".repeat(1 << 16));
+        let second = chain.alloc_copy(Node {
+            name: long_name,
+            next: Some(first),
+        });
+        let third = chain.alloc_copy(Node {
+            name: "third",
+            next: Some(second),
+        });
+        assert_eq!(chain.spilled_allocations(), 1);
+        assert_eq!(third.next.unwrap().name.len(), 1 << 16);
+        assert_eq!(third.next.unwrap().next.unwrap().name, "primary");
+    }
+}
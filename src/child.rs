@@ -0,0 +1,189 @@
+//! Short-lived child arenas, which recycle their memory through the parent.
+use std::fmt::{self, Debug};
+use std::mem::{self, ManuallyDrop};
+use std::ops::{Deref, DerefMut};
+
+use bumpalo::Bump;
+
+use crate::DynamicArena;
+
+/// The number of spare bump allocators an arena keeps by default
+pub(crate) const DEFAULT_SPARE_LIMIT: usize = 4;
+
+/// A child of a `DynamicArena`, whose memory is recycled when it's dropped.
+///
+/// This dereferences to an ordinary `DynamicArena` with its own drop list,
+/// so destruction works exactly the same as with any other arena.
+/// However, when the child is dropped its (reset) memory chunk is given back to the parent,
+/// and the next child reuses it instead of requesting a new one from the allocator.
+/// The parent only keeps a few of them (see `DynamicArena::set_spare_children_limit`),
+/// so a burst of children doesn't keep its peak memory forever.
+/// This is useful for many short-lived sub-arenas, like one per function being compiled.
+///
+/// This is created by `DynamicArena::child`.
+pub struct ChildArena<'p, 'a, S> {
+    parent: &'p DynamicArena<'a, S>,
+    arena: ManuallyDrop<DynamicArena<'a, S>>,
+}
+impl<'p, 'a, S> Deref for ChildArena<'p, 'a, S> {
+    type Target = DynamicArena<'a, S>;
+    #[inline]
+    fn deref(&self) -> &DynamicArena<'a, S> {
+        &self.arena
+    }
+}
+impl<'p, 'a, S> DerefMut for ChildArena<'p, 'a, S> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut DynamicArena<'a, S> {
+        &mut self.arena
+    }
+}
+impl<'p, 'a, S> Debug for ChildArena<'p, 'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ChildArena").field(&*self.arena).finish()
+    }
+}
+impl<'p, 'a, S> Drop for ChildArena<'p, 'a, S> {
+    fn drop(&mut self) {
+        let recycle = RecycleChunk(self);
+        // Items must be dropped before the memory is recycled
+        recycle.0.arena.release_contents();
+    }
+}
+/// Gives the child's chunk back to the parent, even if one of the child's destructors panics
+struct RecycleChunk<'c, 'p, 'a, S>(&'c mut ChildArena<'p, 'a, S>);
+impl<S> Drop for RecycleChunk<'_, '_, '_, S> {
+    fn drop(&mut self) {
+        let mut handle = unsafe { ManuallyDrop::take(&mut self.0.arena) }.into_handle();
+        let parent = self.0.parent;
+        let mut spares = parent.spare_bumps.borrow_mut();
+        // Otherwise the chunks are freed
+        if spares.len() < parent.spare_limit {
+            handle.reset();
+            spares.push(handle);
+        }
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Create a child arena, which gives its memory back to this arena when it's dropped.
+    ///
+    /// The child has the same lifetime bound `'a` as this arena,
+    /// but its own drop list (which runs when the child is dropped).
+    /// If a previous child has been dropped, its memory is reused.
+    /// See `ChildArena` for more details.
+    pub fn child(&self) -> ChildArena<'_, 'a, S> {
+        let handle = self.spare_bumps.borrow_mut().pop().unwrap_or_default();
+        ChildArena {
+            parent: self,
+            arena: ManuallyDrop::new(DynamicArena::from_parts(handle)),
+        }
+    }
+    /// The number of bump allocators returned by dropped children,
+    /// which are available for reuse.
+    #[inline]
+    pub fn spare_children(&self) -> usize {
+        self.spare_bumps.borrow().len()
+    }
+    /// Limit the number of bump allocators kept for reuse by later children (four by default)
+    ///
+    /// The chunks of children dropped beyond the limit are freed instead,
+    /// along with any existing spares beyond it.
+    pub fn set_spare_children_limit(&mut self, limit: usize) {
+        self.spare_limit = limit;
+        self.spare_bumps.get_mut().truncate(limit);
+    }
+    /// Consume this arena, taking its underlying bump allocator
+    ///
+    /// The rest of the arena is dropped as usual,
+    /// so anything that's still registered is dropped before the chunks are given back.
+    pub(crate) fn into_handle(mut self) -> Bump {
+        mem::take(&mut self.handle)
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use crate::test_util::{current_chunk, DropCounted};
+    use std::cell::Cell;
+
+    #[test]
+    fn recycled_chunks() {
+        let counter = Cell::new(0);
+        let parent = DynamicArena::new_bounded();
+        let original = {
+            let child = parent.child();
+            child.alloc(DropCounted(&counter));
+            child.alloc_slice_fill_copy(100, 0u64);
+            current_chunk(&child)
+        };
+        assert_eq!(counter.get(), 1);
+        assert_eq!(parent.spare_children(), 1);
+        for _ in 0..10 {
+            let child = parent.child();
+            assert_eq!(parent.spare_children(), 0);
+            child.alloc(DropCounted(&counter));
+            assert_eq!(current_chunk(&child), original);
+        }
+        assert_eq!(counter.get(), 11);
+        // Two children at once need two chunks
+        let first = parent.child();
+        let second = parent.child();
+        first.alloc_copy(1u8);
+        second.alloc_copy(2u8);
+        drop((first, second));
+        assert_eq!(parent.spare_children(), 2);
+    }
+    #[test]
+    fn spare_limit() {
+        let mut parent: DynamicArena = DynamicArena::new();
+        let children: Vec<_> = (0..10).map(|_| parent.child()).collect();
+        for child in &children {
+            child.alloc_copy(0u64);
+        }
+        drop(children);
+        assert_eq!(parent.spare_children(), 4);
+        parent.set_spare_children_limit(1);
+        assert_eq!(parent.spare_children(), 1);
+        drop((parent.child(), parent.child()));
+        assert_eq!(parent.spare_children(), 1);
+    }
+    #[test]
+    fn borrow_from_parent() {
+        let counter = Cell::new(0);
+        let parent = DynamicArena::new_bounded();
+        parent.alloc(DropCounted(&counter));
+        let shared = parent.alloc_copy(7u32);
+        {
+            let child = parent.child();
+            // Copyable values in the child can borrow from the parent
+            let borrowed = child.alloc_copy(&*shared);
+            assert_eq!(**borrowed, 7);
+        }
+        assert_eq!(*shared, 7);
+        assert_eq!(counter.get(), 0);
+        drop(parent);
+        assert_eq!(counter.get(), 1);
+    }
+    #[test]
+    fn panicking_destructor() {
+        struct Panics;
+        impl Drop for Panics {
+            fn drop(&mut self) {
+                panic!("Failed to drop");
+            }
+        }
+        let counter = Cell::new(0);
+        let parent = DynamicArena::new_bounded();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let child = parent.child();
+            child.alloc(DropCounted(&counter));
+            child.alloc(Panics);
+        }));
+        assert!(result.is_err());
+        assert_eq!(counter.get(), 1);
+        // The chunk still went back to the parent
+        assert_eq!(parent.spare_children(), 1);
+    }
+}
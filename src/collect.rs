@@ -0,0 +1,147 @@
+//! Collecting iterators into a `DynamicArena`.
+use crate::{DynamicArena, ItemBound};
+
+/// Conversion from an iterator into a value allocated in an arena.
+///
+/// This is the arena equivalent of `FromIterator`,
+/// and is normally used through `CollectIn::collect_in`.
+pub trait FromIteratorIn<'arena, A: ?Sized, T>: Sized {
+    /// Allocate a value in the specified arena, built from the elements of the iterator
+    fn from_iter_in<I: IntoIterator<Item = T>>(iter: I, arena: &'arena A) -> Self;
+}
+
+/// An extension trait for collecting iterators into an arena.
+///
+/// This is the arena equivalent of `Iterator::collect`,
+/// and is implemented for every iterator.
+///
+/// ## Example
+/// ````
+/// # #[cfg(not(feature = "no-drop-tracking"))] {
+/// use dynamic_arena::{CollectIn, DynamicArena};
+/// let arena = DynamicArena::new();
+/// let names: &[String] = (0..3).map(|i| format!("node{}", i)).collect_in(&arena);
+/// assert_eq!(names, &["node0", "node1", "node2"]);
+/// let text: &str = "a-b-c".chars().filter(|&c| c != '-').collect_in(&arena);
+/// assert_eq!(text, "abc");
+/// let joined: &str = names.iter().map(String::as_str).collect_in(&arena);
+/// assert_eq!(joined, "node0node1node2");
+/// # }
+/// ````
+pub trait CollectIn: Iterator + Sized {
+    /// Collect the elements of this iterator into a value allocated in the specified arena
+    ///
+    /// Elements that need to be dropped are registered with the arena,
+    /// just like with `DynamicArena::alloc`.
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn collect_in<'arena, A: ?Sized, C>(self, arena: &'arena A) -> C
+    where
+        C: FromIteratorIn<'arena, A, Self::Item>,
+    {
+        C::from_iter_in(self, arena)
+    }
+}
+impl<I: Iterator> CollectIn for I {}
+
+impl<'arena, 'a, S: ItemBound<T>, T: 'a> FromIteratorIn<'arena, DynamicArena<'a, S>, T>
+    for &'arena mut [T]
+{
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn from_iter_in<I: IntoIterator<Item = T>>(
+        iter: I,
+        arena: &'arena DynamicArena<'a, S>,
+    ) -> Self {
+        unsafe { arena.alloc_iter_unchecked(iter) }
+    }
+}
+impl<'arena, 'a, S, T> FromIteratorIn<'arena, DynamicArena<'a, S>, T> for &'arena [T]
+where
+    &'arena mut [T]: FromIteratorIn<'arena, DynamicArena<'a, S>, T>,
+{
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn from_iter_in<I: IntoIterator<Item = T>>(
+        iter: I,
+        arena: &'arena DynamicArena<'a, S>,
+    ) -> Self {
+        <&'arena mut [T]>::from_iter_in(iter, arena)
+    }
+}
+impl<'arena, 'a, S> FromIteratorIn<'arena, DynamicArena<'a, S>, char> for &'arena mut str {
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn from_iter_in<I: IntoIterator<Item = char>>(
+        iter: I,
+        arena: &'arena DynamicArena<'a, S>,
+    ) -> Self {
+        arena.alloc_str_from_chars(iter)
+    }
+}
+impl<'arena, 'a, 's, S> FromIteratorIn<'arena, DynamicArena<'a, S>, &'s str> for &'arena mut str {
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn from_iter_in<I: IntoIterator<Item = &'s str>>(
+        iter: I,
+        arena: &'arena DynamicArena<'a, S>,
+    ) -> Self {
+        let mut buffer = bumpalo::collections::Vec::new_in(&arena.handle);
+        for s in iter {
+            buffer.extend_from_slice_copy(s.as_bytes());
+        }
+        arena.record_bytes(buffer.len());
+        // The buffer is a concatenation of valid strings
+        unsafe { std::str::from_utf8_unchecked_mut(arena.realigned(buffer.into_bump_slice_mut())) }
+    }
+}
+impl<'arena, 'a, S, T> FromIteratorIn<'arena, DynamicArena<'a, S>, T> for &'arena str
+where
+    &'arena mut str: FromIteratorIn<'arena, DynamicArena<'a, S>, T>,
+{
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn from_iter_in<I: IntoIterator<Item = T>>(
+        iter: I,
+        arena: &'arena DynamicArena<'a, S>,
+    ) -> Self {
+        <&'arena mut str>::from_iter_in(iter, arena)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use crate::test_util::DropCounted;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::cell::Cell;
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn collect_drops() {
+        let counter = Cell::new(0);
+        let arena = DynamicArena::new_bounded();
+        let exact: &[DropCounted<_>] = (0..10).map(|_| DropCounted(&counter)).collect_in(&arena);
+        assert_eq!(exact.len(), 10);
+        let unknown: &mut [DropCounted<_>] = (0..100)
+            .filter(|i| i % 3 == 0)
+            .map(|_| DropCounted(&counter))
+            .collect_in(&arena);
+        assert_eq!(unknown.len(), 34);
+        assert_eq!(counter.get(), 0);
+        drop(arena);
+        assert_eq!(counter.get(), 44);
+    }
+    #[test]
+    fn collect_copy() {
+        let arena = DynamicArena::new_send();
+        let squares: &[u32] = (0..5u32).map(|i| i * i).collect_in(&arena);
+        assert_eq!(squares, &[0, 1, 4, 9, 16]);
+        // Copyable elements don't need to be registered
+        assert!(arena.items.is_empty());
+        let empty: &[u32] = std::iter::empty().collect_in(&arena);
+        assert!(empty.is_empty());
+        let text: &mut str = vec!["foo", "", "bar"].into_iter().collect_in(&arena);
+        assert_eq!(text, "foobar");
+    }
+}
@@ -0,0 +1,75 @@
+//! Checking whether a value lives in an arena.
+use bumpalo::Bump;
+
+use crate::DynamicArena;
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Check whether the specified value was allocated in this arena
+    ///
+    /// This checks whether its address falls within the allocated part of one of the chunks
+    /// (including the chunks of committed forks),
+    /// so it takes time proportional to the number of chunks.
+    /// It's meant for cheap debug assertions like `debug_assert!(arena.contains(node))`.
+    ///
+    /// Values on the stack or the heap are never contained,
+    /// while zero-sized values may not be (since they don't need any memory).
+    pub fn contains<T: ?Sized>(&self, value: &T) -> bool {
+        self.contains_address(value as *const T as *const u8 as usize)
+    }
+    /// Check whether the specified address falls within the allocated part of one of the chunks
+    pub(crate) fn contains_address(&self, address: usize) -> bool {
+        chunks_contain(&self.handle, address)
+            || self
+                .adopted
+                .borrow()
+                .iter()
+                .any(|adopted| chunks_contain(adopted, address))
+    }
+}
+
+fn chunks_contain(handle: &Bump, address: usize) -> bool {
+    // The raw chunks are only compared against, never accessed
+    unsafe { handle.iter_allocated_chunks_raw() }.any(|(start, len)| {
+        let start = start as usize;
+        address >= start && address - start < len
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chunk_bounds() {
+        let arena = DynamicArena::new();
+        let first = arena.alloc_str("first");
+        assert!(arena.contains(first));
+        assert!(arena.contains(&first.as_bytes()[4]));
+        let large = arena.alloc_slice_fill_copy(100_000, 0u8);
+        assert!(arena.contains(&large[0]));
+        assert!(arena.contains(&large[99_999]));
+        let (start, len) = unsafe { arena.as_bumpalo().iter_allocated_chunks_raw() }
+            .next()
+            .unwrap();
+        let past_end = unsafe { &*start.add(len) };
+        assert!(!arena.contains(past_end));
+        let other = DynamicArena::new();
+        assert!(!other.contains(first));
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn foreign_values() {
+        let arena = DynamicArena::new();
+        arena.alloc(String::from("owned"));
+        let local = 5u32;
+        let boxed = Box::new(5u32);
+        assert!(!arena.contains(&local));
+        assert!(!arena.contains(&*boxed));
+        assert!(!arena.contains("static"));
+        let fork = arena.fork();
+        let forked: *const u64 = fork.alloc_copy(7u64);
+        fork.commit();
+        // Committed chunks are retained until the next reset
+        assert!(arena.contains(unsafe { &*forked }));
+    }
+}
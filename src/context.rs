@@ -0,0 +1,154 @@
+//! Finalizers that receive a caller-supplied context when the arena is torn down.
+use std::any::TypeId;
+use std::mem::{self, ManuallyDrop};
+use std::os::raw::c_void;
+use std::ptr;
+
+use crate::{drop_glue, DynamicArena, ItemBound};
+
+/// A type-erased closure which expects a context of a specific type
+pub(crate) struct ContextualFinalizer {
+    context: TypeId,
+    run: unsafe fn(*mut c_void, *mut c_void),
+    discard: unsafe fn(*mut c_void),
+    closure: *mut c_void,
+}
+impl Drop for ContextualFinalizer {
+    /// Drop the closure without running it
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { (self.discard)(self.closure) }
+    }
+}
+/// The closures are only `Send` in a `Sendable` arena
+unsafe impl Send for ContextualFinalizer {}
+
+unsafe fn run_finalizer<C, F: FnOnce(&mut C)>(closure: *mut c_void, context: *mut c_void) {
+    let func = ptr::read(closure.cast::<F>());
+    func(&mut *context.cast::<C>())
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Drop the arena, first running all the contextual finalizers with the specified context.
+    ///
+    /// The finalizers run in reverse registration order (just like values),
+    /// before anything else in the arena is dropped.
+    /// Finalizers that were registered for a different type of context are skipped.
+    /// Afterwards, the arena is dropped normally.
+    pub fn drop_with_context<C: 'static>(mut self, context: &mut C) {
+        let finalizers = mem::take(self.finalizers.get_mut());
+        for finalizer in finalizers.into_iter().rev() {
+            if finalizer.context == TypeId::of::<C>() {
+                // Running the closure consumes it, so it must not be discarded
+                let finalizer = ManuallyDrop::new(finalizer);
+                unsafe { (finalizer.run)(finalizer.closure, (context as *mut C).cast()) }
+            }
+        }
+    }
+    /// The number of contextual finalizers waiting to be run
+    #[inline]
+    pub fn contextual_finalizer_count(&self) -> usize {
+        self.finalizers.borrow().len()
+    }
+    /// Drop all the contextual finalizers without running them,
+    /// since the arena's memory is about to be freed
+    #[inline]
+    pub(crate) fn discard_finalizers(&mut self) {
+        self.finalizers.get_mut().clear();
+    }
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    unsafe fn register_finalizer_unchecked<C: 'static, F: FnOnce(&mut C)>(&self, func: F) {
+        let closure = self.alloc_value(func) as *mut F;
+        self.finalizers.borrow_mut().push(ContextualFinalizer {
+            context: TypeId::of::<C>(),
+            run: run_finalizer::<C, F>,
+            discard: drop_glue::<F>,
+            closure: closure.cast(),
+        });
+    }
+}
+impl<'a, S> DynamicArena<'a, S> {
+    /// Register a finalizer, which receives a context supplied by `drop_with_context`.
+    ///
+    /// This allows teardown to borrow resources that can't be captured for the lifetime `'a`
+    /// (like a `&mut Interpreter` that needs to unregister objects).
+    /// Just like a value, the closure must satisfy the arena's `ItemBound`.
+    ///
+    /// If the arena is dropped (or reset) normally, or with a different type of context,
+    /// the finalizer is skipped and the closure is dropped without running.
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn register_finalizer<C: 'static, F: FnOnce(&mut C) + 'a>(&self, func: F)
+    where
+        S: ItemBound<F>,
+    {
+        unsafe { self.register_finalizer_unchecked(func) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct Interpreter {
+        #[cfg(not(feature = "no-drop-tracking"))]
+        objects: Vec<u32>,
+        #[cfg(not(feature = "no-drop-tracking"))]
+        log: Vec<&'static str>,
+    }
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    struct DropOrder<'a>(&'a RefCell<Vec<&'static str>>, &'static str);
+    #[cfg(not(feature = "no-drop-tracking"))]
+    impl<'a> Drop for DropOrder<'a> {
+        fn drop(&mut self) {
+            self.0.borrow_mut().push(self.1);
+        }
+    }
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn with_context() {
+        let order = RefCell::new(Vec::new());
+        let mut interpreter = Interpreter {
+            objects: vec![1, 2, 3],
+            log: Vec::new(),
+        };
+        let arena = DynamicArena::new_bounded();
+        arena.alloc(DropOrder(&order, "value"));
+        arena.register_finalizer(|interp: &mut Interpreter| {
+            interp.objects.retain(|&id| id != 2);
+            interp.log.push("unregistered");
+        });
+        arena.register_finalizer(|interp: &mut Interpreter| interp.log.push("second"));
+        let skipped = Rc::new(());
+        let captured = Rc::clone(&skipped);
+        arena.register_finalizer(move |_: &mut String| drop(captured));
+        assert_eq!(arena.contextual_finalizer_count(), 3);
+        arena.drop_with_context(&mut interpreter);
+        assert_eq!(interpreter.objects, vec![1, 3]);
+        assert_eq!(interpreter.log, vec!["second", "unregistered"]);
+        assert_eq!(*order.borrow(), vec!["value"]);
+        // The finalizer for the wrong context was dropped without running
+        assert_eq!(Rc::strong_count(&skipped), 1);
+    }
+    #[test]
+    fn skipped_without_context() {
+        let tracker = Rc::new(());
+        let mut arena = DynamicArena::new_bounded();
+        let captured = Rc::clone(&tracker);
+        arena.register_finalizer(move |_: &mut Interpreter| {
+            let _ = captured;
+            unreachable!()
+        });
+        arena.reset();
+        assert_eq!(arena.contextual_finalizer_count(), 0);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+        let arena = DynamicArena::new_send();
+        arena.register_finalizer(|_: &mut Interpreter| unreachable!());
+        std::thread::spawn(move || drop(arena)).join().unwrap();
+    }
+}
@@ -0,0 +1,94 @@
+//! Conversions between `Sendable` and `NonSend` arenas.
+use std::mem::ManuallyDrop;
+use std::ptr;
+
+use crate::{DynamicArena, NonSend, Sendable};
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Move the whole arena into one with a different marker
+    ///
+    /// This keeps the chunks and the drop list exactly as they are.
+    /// The arena is `repr(C)`, and the marker is only used by a `PhantomData`,
+    /// so the layout is the same for every marker.
+    ///
+    /// ## Safety
+    /// Everything in the arena must satisfy the requirements of the new marker.
+    unsafe fn with_marker<T>(self) -> DynamicArena<'a, T> {
+        let this = ManuallyDrop::new(self);
+        ptr::read((&*this as *const DynamicArena<'a, S>).cast::<DynamicArena<'a, T>>())
+    }
+}
+impl<'a> DynamicArena<'a, Sendable> {
+    /// Convert this into a `NonSend` arena, so values that aren't `Send` can be allocated into it.
+    ///
+    /// This is always safe, since the arena only loses the ability to be sent between threads.
+    /// The chunks and the registered values are kept as they are.
+    #[inline]
+    pub fn into_non_send(self) -> DynamicArena<'a, NonSend> {
+        // Everything that's `Send` is also fine for a `NonSend` arena
+        unsafe { self.with_marker() }
+    }
+}
+impl<'a> DynamicArena<'a, NonSend> {
+    /// Convert this into a `Sendable` arena, without checking its contents.
+    ///
+    /// The chunks and the registered values are kept as they are.
+    ///
+    /// ## Safety
+    /// Every value registered to be dropped must be `Send`,
+    /// along with any closures registered in the arena (like `on_drop` and `set_alloc_hook`).
+    /// Values allocated by `alloc_debuggable` must be `Send` as well, since they can be printed.
+    #[inline]
+    pub unsafe fn into_send(self) -> DynamicArena<'a, Sendable> {
+        self.with_marker()
+    }
+    /// Convert this into a `Sendable` arena, if nothing in it could be tied to the current thread.
+    ///
+    /// This succeeds if there aren't any registered drop functions, finalizers or allocation hooks,
+    /// since nothing else in the arena is ever accessed once it's been moved.
+    /// Otherwise the arena is returned unchanged.
+    #[allow(clippy::result_large_err)]
+    pub fn try_into_send(mut self) -> Result<DynamicArena<'a, Sendable>, Self> {
+        #[cfg(feature = "debug-dump")]
+        let printable = self.dump.has_printable();
+        #[cfg(not(feature = "debug-dump"))]
+        let printable = false;
+        if self.items.is_empty()
+            && self.finalizers.get_mut().is_empty()
+            && self.alloc_hook.is_none()
+            && !printable
+        {
+            // Nothing in the arena depends on the current thread
+            Ok(unsafe { self.with_marker() })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn round_trip() {
+        let arena = DynamicArena::new_send();
+        let name: *const str = arena.alloc_str("kept");
+        arena.alloc(String::from("dropped later"));
+        let arena = arena.into_non_send();
+        let shared = Rc::new(5);
+        arena.alloc(Rc::clone(&shared));
+        assert_eq!(arena.pending_drop_count(), 2);
+        assert_eq!(unsafe { &*name }, "kept");
+        // The `Rc` isn't `Send`, so the arena can't be sent
+        let mut arena = arena.try_into_send().unwrap_err();
+        arena.verify().unwrap();
+        arena.reset();
+        assert_eq!(Rc::strong_count(&shared), 1);
+        let arena = arena.try_into_send().unwrap();
+        std::thread::spawn(move || arena.alloc(String::from("moved")).len())
+            .join()
+            .unwrap();
+    }
+}
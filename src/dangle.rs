@@ -0,0 +1,65 @@
+//! Allocating values whose destructors never use their borrowed data, enabled by the `nightly` feature.
+use crate::{DynamicArena, ItemBound, DEFAULT_DROP_PHASE};
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Allocate the specified value, which may borrow data that's dropped before the arena.
+    ///
+    /// Unlike `alloc`, this doesn't require `T: 'a`.
+    /// The value only needs to outlive the borrow of the arena,
+    /// and the returned reference can't be used after that.
+    /// The value is still dropped along with the arena (or when it's reset).
+    ///
+    /// ## Safety
+    /// This is the same promise that `#[may_dangle]` makes for a `Drop` implementation:
+    /// dropping the value must never access anything it borrows
+    /// (including through the destructors of its fields), since that data may already be gone.
+    /// Dropping owned fields (like a `String` or `Vec`) is fine,
+    /// as long as they don't borrow anything either.
+    ///
+    /// The arena only records a type-erased drop function for each value,
+    /// so the compiler can't check this the way it does for a `Vec<T>` with `#[may_dangle] T`.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub unsafe fn alloc_may_dangle<'b, T: 'b>(&'b self, value: T) -> &'b mut T
+    where
+        S: ItemBound<T>,
+    {
+        let target = self.alloc_registered(value, None, DEFAULT_DROP_PHASE);
+        #[cfg(feature = "debug-dump")]
+        self.dump.record_opaque::<T>(target);
+        &mut *target
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::rc::Rc;
+
+    /// Borrows a name, but only drops its owned fields
+    struct Labeled<'n> {
+        name: &'n str,
+        owned: Vec<Rc<()>>,
+    }
+
+    #[test]
+    fn shorter_borrow() {
+        let tracker = Rc::new(());
+        let arena = DynamicArena::new();
+        {
+            let name = String::from("temporary");
+            let value = unsafe {
+                arena.alloc_may_dangle(Labeled {
+                    name: &name,
+                    owned: vec![Rc::clone(&tracker); 3],
+                })
+            };
+            assert_eq!((value.name, value.owned.len()), ("temporary", 3));
+        }
+        // The name is gone, but dropping the value never reads it
+        assert_eq!(Rc::strong_count(&tracker), 4);
+        drop(arena);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+}
@@ -0,0 +1,152 @@
+//! Reclaiming the most recent allocation, for cheap speculative allocation.
+use std::alloc::Layout;
+use std::mem;
+
+use crate::droplist::DropList;
+use crate::DynamicArena;
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Drop the value at the specified pointer, reclaiming its bytes if it's the most recent allocation.
+    ///
+    /// The value's drop function is run immediately and unregistered,
+    /// so it won't run again when the arena is reset or dropped.
+    /// If the value is the most recent allocation in the arena,
+    /// the bump pointer is rolled back so the next allocation reuses its bytes.
+    /// Otherwise the bytes are left in place until the arena is reset
+    /// (unless recycling is enabled, see `set_recycling`).
+    /// Returns whether the bytes were reclaimed.
+    ///
+    /// This is intended for parsers that allocate a node and immediately
+    /// discard it when a production fails.
+    /// Searching for the drop entry starts from the most recent one,
+    /// so it's cheapest for recently allocated values.
+    ///
+    /// Checkpoints are unaffected, and still roll back everything registered after them.
+    ///
+    /// ## Safety
+    /// The pointer must have been returned by allocating a single `T` in this arena,
+    /// and the value must not have already been dropped or deallocated.
+    /// The value must never be accessed again.
+    pub unsafe fn dealloc_last<T>(&self, value: *mut T) -> bool {
+        self.sample_peaks();
+        let mut node = None;
+        // The caller's pointer may not be usable after the drop, so the registered one is used instead
+        let mut value = value;
+        if mem::needs_drop::<T>() {
+            // Splitting a run needs another entry
+            let spare = || {
+                self.recycled_layout(DropList::NODE_LAYOUT)
+                    .unwrap_or_else(|| self.handle.alloc_layout(DropList::NODE_LAYOUT))
+            };
+            if let Some((item, entry)) = self.items.remove_value(value as usize, spare) {
+                self.handle_epoch.invalidate();
+                value = item.value.cast();
+                // The drop function could use the arena, so it runs before any memory is reclaimed
+                drop(item);
+                node = entry;
+            }
+        }
+        #[cfg(feature = "debug-dump")]
+        self.dump.forget(value.cast());
+        self.metadata.forget(value as usize);
+        /*
+         * Bumpalo only exposes deallocation through its collections,
+         * which roll back the bump pointer if they were the last allocation.
+         * The drop entry is normally allocated along with the value (see `alloc_registered`),
+         * or right after it (see `dynamic_drop`), so they're reclaimed together as a single block.
+         */
+        let (block, layout) = match node {
+            Some(node) => {
+                let combined = Layout::new::<T>().extend(DropList::NODE_LAYOUT).ok();
+                if let Some((combined, _)) = combined.filter(|&(_, offset)| {
+                    node.as_ptr() as usize == (value as usize).wrapping_add(offset)
+                }) {
+                    (value.cast(), combined)
+                } else {
+                    // The entry is somewhere else, so it can only be recycled
+                    self.recycle_layout(node.as_ptr(), DropList::NODE_LAYOUT);
+                    (value.cast(), Layout::new::<T>())
+                }
+            }
+            None => (value.cast(), Layout::new::<T>()),
+        };
+        let capacity = self.handle.chunk_capacity();
+        drop(bumpalo::collections::Vec::from_raw_parts_in(
+            block,
+            0,
+            layout.size(),
+            &self.handle,
+        ));
+        let reclaimed = self.handle.chunk_capacity() > capacity;
+        if !reclaimed {
+            self.recycle_layout(block, layout);
+        }
+        reclaimed
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use crate::test_util::DropCounted;
+    use std::cell::Cell;
+
+    #[test]
+    fn reuses_bytes() {
+        let counter = Cell::new(0);
+        let arena = DynamicArena::new_bounded();
+        arena.alloc_copy(0u64);
+        let speculative: *mut _ = arena.alloc(DropCounted(&counter));
+        let capacity = arena.byte_capacity();
+        assert!(unsafe { arena.dealloc_last(speculative) });
+        assert_eq!(counter.get(), 1);
+        assert_eq!(arena.pending_drop_count(), 0);
+        assert!(arena.byte_capacity() > capacity);
+        let next: *mut _ = arena.alloc(DropCounted(&counter));
+        assert_eq!(next, speculative);
+        let copied: *mut u32 = arena.alloc_copy(7u32);
+        assert!(unsafe { arena.dealloc_last(copied) });
+        assert_eq!(arena.alloc_copy(8u32) as *mut u32, copied);
+        arena.verify().unwrap();
+        drop(arena);
+        assert_eq!(counter.get(), 2);
+    }
+    #[test]
+    fn not_last() {
+        let counter = Cell::new(0);
+        let mut arena = DynamicArena::new_bounded();
+        let checkpoint = arena.checkpoint();
+        let first: *mut _ = arena.alloc(DropCounted(&counter));
+        arena.alloc(DropCounted(&counter));
+        let capacity = arena.byte_capacity();
+        assert!(!unsafe { arena.dealloc_last(first) });
+        assert_eq!(counter.get(), 1);
+        assert_eq!(arena.byte_capacity(), capacity);
+        assert_eq!(arena.pending_drop_count(), 1);
+        arena.verify().unwrap();
+        arena.rollback_to(checkpoint);
+        assert_eq!(counter.get(), 2);
+        drop(arena);
+        assert_eq!(counter.get(), 2);
+    }
+    #[test]
+    fn registered_separately() {
+        #[repr(align(16))]
+        struct Big(#[allow(dead_code)] String);
+        let arena = DynamicArena::new_bounded();
+        let big = arena
+            .alloc_layout(Layout::new::<Big>())
+            .cast::<Big>()
+            .as_ptr();
+        unsafe { big.write(Big("big".into())) };
+        // Allocated between the value and its drop entry, so it can't be reclaimed with them
+        let bytes = arena.alloc_slice_copy(&[0xAAu8; 8]);
+        unsafe {
+            arena.dynamic_drop(big);
+            assert!(!arena.dealloc_last(big));
+        }
+        arena.alloc_slice_fill_copy(64, 0x55u8);
+        assert_eq!(*bytes, [0xAA; 8]);
+        assert_eq!(arena.pending_drop_count(), 0);
+    }
+}
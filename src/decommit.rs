@@ -0,0 +1,168 @@
+//! Resets which give memory back to the operating system.
+use bumpalo::Bump;
+
+use crate::DynamicArena;
+
+/// The total amount of memory given back by `DynamicArena::reset_and_decommit`
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct ReleasedMemory {
+    /// Bytes of chunks that were freed back to the allocator
+    pub(crate) freed: usize,
+    /// Bytes of the retained chunk whose pages were decommitted
+    pub(crate) decommitted: usize,
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Reset the arena (just like `reset`),
+    /// then give all but `resident_bytes` of its memory back to the operating system.
+    ///
+    /// Every chunk except the largest one is freed back to the allocator.
+    /// The number of retained chunks isn't configurable, since bumpalo's reset only ever keeps one,
+    /// so `resident_bytes` controls how much memory is kept instead.
+    /// Where it's supported (currently Linux and Android), the unused pages of the retained chunk
+    /// are decommitted with `madvise(MADV_DONTNEED)`, so the resident memory actually drops
+    /// while the address space stays reserved (and warm) for the next cycle.
+    /// Bump allocation starts at the end of the chunk,
+    /// so the last `resident_bytes` of it are kept resident for the next allocations.
+    ///
+    /// On other platforms, a retained chunk that's larger than `resident_bytes`
+    /// is simply freed and replaced by a chunk of `resident_bytes`.
+    ///
+    /// Any learned capacity isn't applied until the next `reset`,
+    /// since that would defeat the point of releasing the memory.
+    /// The total amount released is available from `freed_bytes` and `decommitted_bytes`,
+    /// along with `stats`.
+    pub fn reset_and_decommit(&mut self, resident_bytes: usize) {
+        let usage = self.usage();
+        self.sample_peaks();
+        self.learn_capacity();
+        self.release_contents();
+        self.released.freed += self
+            .adopted
+            .get_mut()
+            .drain(..)
+            .map(|bump| bump.allocated_bytes())
+            .sum::<usize>();
+        let allocated = self.handle.allocated_bytes();
+        self.handle.reset();
+        self.allocations.set(0);
+        self.reset_cycle();
+        self.released.freed += allocated - self.handle.allocated_bytes();
+        self.notify_reset(usage);
+        let capacity = self.handle.chunk_capacity();
+        if capacity <= resident_bytes {
+            return;
+        }
+        match decommit_chunk(&self.handle, resident_bytes) {
+            Some(decommitted) => self.released.decommitted += decommitted,
+            None => {
+                // Fallback to freeing the chunk
+                let limit = self.handle.allocation_limit();
+                if let Ok(handle) = Bump::try_with_capacity(resident_bytes) {
+                    handle.set_allocation_limit(limit);
+                    self.released.freed += capacity;
+                    self.handle = handle;
+                    self.sync_chunks();
+                }
+            }
+        }
+    }
+    /// The total number of bytes freed back to the allocator by `reset_and_decommit`
+    #[inline]
+    pub fn freed_bytes(&self) -> usize {
+        self.released.freed
+    }
+    /// The total number of bytes decommitted by `reset_and_decommit`,
+    /// without giving up their address space
+    ///
+    /// This is always zero on platforms that don't support decommitting memory.
+    #[inline]
+    pub fn decommitted_bytes(&self) -> usize {
+        self.released.decommitted
+    }
+}
+
+/// Decommit the pages of the (empty) current chunk,
+/// except for the last `resident_bytes`,
+/// returning the number of bytes decommitted.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn decommit_chunk(handle: &Bump, resident_bytes: usize) -> Option<usize> {
+    let page_size = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as usize,
+        _ => return None,
+    };
+    // After a reset, the bump pointer is at the end of the chunk's data (just before the footer)
+    let (end, _) = unsafe { handle.iter_allocated_chunks_raw() }.next()?;
+    let end = end as usize;
+    let start = end - handle.chunk_capacity();
+    /*
+     * Only whole pages strictly inside the data can be decommitted,
+     * since the footer and the allocator's own bookkeeping share the boundary pages.
+     * The contents of decommitted pages are lost (they're zeroed on next use),
+     * which doesn't matter since nothing is allocated there.
+     */
+    let first_page = start.checked_add(page_size - 1)? & !(page_size - 1);
+    let last_page = end.saturating_sub(resident_bytes) & !(page_size - 1);
+    if last_page <= first_page {
+        return Some(0);
+    }
+    let length = last_page - first_page;
+    let result =
+        unsafe { libc::madvise(first_page as *mut libc::c_void, length, libc::MADV_DONTNEED) };
+    if result == 0 {
+        Some(length)
+    } else {
+        None
+    }
+}
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+#[inline]
+fn decommit_chunk(_handle: &Bump, _resident_bytes: usize) -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use crate::test_util::chunk_count;
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn frees_chunks() {
+        let mut arena = DynamicArena::new();
+        for _ in 0..16 {
+            arena.alloc_slice_fill_copy(64 * 1024, 1u8);
+            arena.alloc(vec![1u32]);
+        }
+        assert!(chunk_count(&arena) > 1);
+        let allocated = arena.as_bumpalo().allocated_bytes();
+        arena.reset_and_decommit(usize::MAX);
+        assert_eq!(chunk_count(&arena), 1);
+        assert_eq!(arena.pending_drop_count(), 0);
+        assert_eq!(
+            arena.freed_bytes(),
+            allocated - arena.as_bumpalo().allocated_bytes()
+        );
+        assert_eq!(arena.decommitted_bytes(), 0);
+    }
+    #[test]
+    // Miri doesn't support `madvise`
+    #[cfg_attr(miri, ignore)]
+    fn decommits_retained() {
+        const SIZE: usize = 1 << 20;
+        let mut arena: DynamicArena = DynamicArena::with_capacity(0, SIZE);
+        arena.set_allocation_limit(Some(4 * SIZE));
+        arena.alloc_slice_fill_copy(SIZE / 2, 0xFFu8);
+        arena.reset_and_decommit(4096);
+        assert!(arena.decommitted_bytes() + arena.freed_bytes() >= SIZE / 2);
+        if cfg!(target_os = "linux") {
+            // The chunk is retained, and is still usable
+            assert!(arena.byte_capacity() >= SIZE);
+            assert!(arena.decommitted_bytes() >= SIZE / 2);
+        }
+        assert_eq!(arena.allocation_limit(), Some(4 * SIZE));
+        let slice = arena.alloc_slice_fill_copy(SIZE - 4096, 7u8);
+        assert!(slice.iter().all(|&b| b == 7));
+    }
+}
@@ -0,0 +1,80 @@
+//! Opting types out of the lifetime bound on `alloc`, for destructors that never use borrowed data.
+use crate::{DynamicArena, ItemBound, DEFAULT_DROP_PHASE};
+
+/// A type whose destructor never accesses any of the data it borrows
+///
+/// This allows the type to be allocated by `DynamicArena::alloc_drop_safe`,
+/// even if it borrows data that doesn't outlive the arena (like other values in the same arena).
+/// Usually that's only possible with `alloc_copy`,
+/// which rules out types that own something like a `Vec` or a `String`.
+///
+/// ## Safety
+/// Dropping the value must never dereference anything it borrows,
+/// since that data may already be gone (or dropped by the arena first).
+/// This includes its own `Drop` implementation (if any),
+/// along with the destructors of all its fields.
+/// Owned fields that don't borrow anything (like a `Vec<String>`) are always fine,
+/// but a field with a destructor that reads a borrowed reference (like a logging guard) isn't.
+pub unsafe trait DropSafe {}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Allocate a value whose destructor never uses its borrowed data.
+    ///
+    /// Unlike `alloc`, this doesn't require `T: 'a`.
+    /// The value only needs to outlive the borrow of the arena,
+    /// so it can reference values that were allocated in the arena before it.
+    /// Otherwise, this is the same as `alloc`.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_drop_safe<T: DropSafe>(&self, value: T) -> &mut T
+    where
+        S: ItemBound<T>,
+    {
+        unsafe {
+            // The value's destructor is fine to run after its borrows end (see `DropSafe`)
+            let target = self.alloc_registered(value, None, DEFAULT_DROP_PHASE);
+            #[cfg(feature = "debug-dump")]
+            self.dump.record_opaque::<T>(target);
+            &mut *target
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use std::rc::Rc;
+
+    /// A tree node that owns its labels and borrows its parent
+    struct Node<'n> {
+        parent: Option<&'n Node<'n>>,
+        labels: Vec<Rc<str>>,
+    }
+    unsafe impl DropSafe for Node<'_> {}
+    impl<'n> Node<'n> {
+        fn depth(&self) -> usize {
+            self.parent.map_or(0, |parent| parent.depth() + 1)
+        }
+    }
+
+    #[test]
+    fn self_referential() {
+        let label: Rc<str> = Rc::from("label");
+        let arena = DynamicArena::new_bounded();
+        let mut node: &Node = arena.alloc_drop_safe(Node {
+            parent: None,
+            labels: vec![Rc::clone(&label)],
+        });
+        for _ in 0..10 {
+            node = arena.alloc_drop_safe(Node {
+                parent: Some(node),
+                labels: vec![Rc::clone(&label); 2],
+            });
+        }
+        assert_eq!((node.depth(), node.labels.len()), (10, 2));
+        assert_eq!(Rc::strong_count(&label), 22);
+        drop(arena);
+        assert_eq!(Rc::strong_count(&label), 1);
+    }
+}
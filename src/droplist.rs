@@ -0,0 +1,403 @@
+//! The list of values that need to be dropped, which lives in the arena's own chunks.
+use std::alloc::Layout;
+use std::cell::Cell;
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr::{self, NonNull};
+
+use crate::DynamicArenaItem;
+
+/// An entry in the drop list, allocated in the arena right after the value it drops
+struct DropNode {
+    item: DynamicArenaItem,
+    next: Option<NonNull<DropNode>>,
+}
+
+/// An intrusive linked list of the registered values, starting from the most recent one
+///
+/// The entries are allocated in the arena itself, so registering a value never touches the heap
+/// (or pauses to reallocate a list), and the entries are freed along with the chunks.
+/// Each entry is unlinked before it's dropped, so a panic always leaves the list consistent.
+///
+/// An entry can cover a run of values (see `DynamicArenaItem`),
+/// so the length counts values rather than entries.
+#[derive(Default)]
+pub(crate) struct DropList {
+    head: Cell<Option<NonNull<DropNode>>>,
+    /// The number of values in all the entries
+    len: Cell<usize>,
+    /// Whether the values are being dropped, so no more can be registered
+    dropping: Cell<bool>,
+    /// The serial of the next value to be registered (see `DynamicArenaItem::serial`)
+    next_serial: Cell<u64>,
+}
+/// The values are only `Send` in a `Sendable` arena
+unsafe impl Send for DropList {}
+impl DropList {
+    /// The memory each entry takes up in the arena
+    pub(crate) const NODE_LAYOUT: Layout = Layout::new::<DropNode>();
+    /// The number of bytes each entry takes up in the arena
+    pub(crate) const NODE_SIZE: usize = mem::size_of::<DropNode>();
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.len.get()
+    }
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.head.get().is_none()
+    }
+    /// The serial the next registered value will get, which is after every value in the list
+    #[inline]
+    pub(crate) fn next_serial(&self) -> u64 {
+        self.next_serial.get()
+    }
+    /// The number of values in the list that were registered since the specified serial,
+    /// which are always the most recent ones
+    pub(crate) fn count_since(&self, serial: u64) -> usize {
+        self.iter()
+            .take_while(|item| item.serial_end() > serial)
+            .map(|item| item.len().min((item.serial_end() - serial) as usize))
+            .sum()
+    }
+    /// Link a new entry into the list, which is written to the specified memory
+    ///
+    /// Returns the new length of the list.
+    ///
+    /// ## Safety
+    /// The memory must have been allocated in the arena with `NODE_LAYOUT`,
+    /// and must stay valid until the entry is unlinked (or forgotten).
+    #[inline]
+    pub(crate) unsafe fn push(&self, node: NonNull<u8>, mut item: DynamicArenaItem) -> usize {
+        let node = node.cast::<DropNode>();
+        let len = self.len.get() + item.len();
+        item.serial = self.next_serial.get();
+        self.next_serial.set(item.serial_end());
+        node.as_ptr().write(DropNode {
+            item,
+            next: self.head.get(),
+        });
+        self.head.set(Some(node));
+        self.len.set(len);
+        len
+    }
+    /// Whether a value registered with the specified drop function and size could extend
+    /// the most recent run (if it's right before it)
+    ///
+    /// The run must also end with the most recently registered value,
+    /// which isn't the case if that value was removed (see `remove_value`).
+    #[inline]
+    pub(crate) fn head_matches(
+        &self,
+        drop: unsafe fn(*mut c_void),
+        size: usize,
+        tag: Option<u32>,
+        phase: u8,
+    ) -> bool {
+        match self.head.get() {
+            Some(node) => {
+                let item = unsafe { &(*node.as_ptr()).item };
+                item.drop as usize == drop as usize
+                    && item.stride as usize == size
+                    && item.count < u32::MAX
+                    && item.tagged == tag.is_some()
+                    && item.tag == tag.unwrap_or(0)
+                    && item.phase == phase
+                    && item.serial_end() == self.next_serial.get()
+            }
+            None => false,
+        }
+    }
+    /// Whether the value at the specified address is immediately before the first value
+    /// of the most recent run, so it could extend it
+    #[inline]
+    pub(crate) fn precedes_head(&self, value: *mut c_void) -> bool {
+        match self.head.get() {
+            Some(node) => {
+                let item = unsafe { &(*node.as_ptr()).item };
+                (value as usize).wrapping_add(item.stride as usize) == item.value as usize
+            }
+            None => false,
+        }
+    }
+    /// Extend the most recent run with the value at the specified address,
+    /// returning the new length of the list
+    ///
+    /// ## Safety
+    /// The run must accept the value (see `head_matches` and `precedes_head`).
+    #[inline]
+    pub(crate) unsafe fn extend_head(&self, value: *mut c_void) -> usize {
+        let item = &mut (*self.head.get().unwrap().as_ptr()).item;
+        item.value = value;
+        item.count += 1;
+        self.next_serial.set(item.serial_end());
+        let len = self.len.get() + 1;
+        self.len.set(len);
+        len
+    }
+    /// Unlink the most recent entry
+    #[inline]
+    pub(crate) fn pop(&self) -> Option<DynamicArenaItem> {
+        let node = self.head.get()?.as_ptr();
+        unsafe {
+            self.head.set((*node).next);
+            self.len.set(self.len.get() - (*node).item.len());
+            Some(ptr::read(&(*node).item))
+        }
+    }
+    /// Unlink up to `max` of the most recent values,
+    /// splitting the most recent run if it has more than that
+    #[inline]
+    pub(crate) fn pop_at_most(&self, max: usize) -> Option<DynamicArenaItem> {
+        let node = self.head.get()?.as_ptr();
+        let item = unsafe { &mut (*node).item };
+        if max == 0 {
+            None
+        } else if item.len() > max {
+            self.len.set(self.len.get() - max);
+            Some(item.split_newest(max))
+        } else {
+            self.pop()
+        }
+    }
+    /// Unlink every value after the first `len`, returning their entries in registration order
+    pub(crate) fn split_off(&self, len: usize) -> Vec<DynamicArenaItem> {
+        let mut items = Vec::new();
+        while let Some(item) = self.pop_at_most(self.len().saturating_sub(len)) {
+            items.push(item);
+        }
+        items.reverse();
+        items
+    }
+    /// The entries, starting from the most recent one
+    #[inline]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &DynamicArenaItem> + '_ {
+        let mut next = self.head.get();
+        std::iter::from_fn(move || {
+            let node = unsafe { &*next?.as_ptr() };
+            next = node.next;
+            Some(&node.item)
+        })
+    }
+    /// Unlink every entry that matches the predicate, returning them in registration order
+    pub(crate) fn extract(
+        &self,
+        mut pred: impl FnMut(&DynamicArenaItem) -> bool,
+    ) -> Vec<DynamicArenaItem> {
+        let mut extracted = Vec::new();
+        // The link that points to the current entry, so it can be skipped over
+        let mut link: *mut Option<NonNull<DropNode>> = self.head.as_ptr();
+        unsafe {
+            while let Some(node) = *link {
+                let node = node.as_ptr();
+                if pred(&(*node).item) {
+                    *link = (*node).next;
+                    self.len.set(self.len.get() - (*node).item.len());
+                    extracted.push(ptr::read(&(*node).item));
+                } else {
+                    link = &mut (*node).next;
+                }
+            }
+        }
+        extracted.reverse();
+        extracted
+    }
+    /// Remove the most recently registered value at the specified address,
+    /// returning its own entry, along with the memory of the original entry if it was unlinked
+    ///
+    /// If the value is in the middle of a run, the run is split in two,
+    /// with the newer values moving into an entry written to `spare` (which must have `NODE_LAYOUT`).
+    pub(crate) unsafe fn remove_value(
+        &self,
+        address: usize,
+        spare: impl FnOnce() -> NonNull<u8>,
+    ) -> Option<(DynamicArenaItem, Option<NonNull<u8>>)> {
+        let mut link: *mut Option<NonNull<DropNode>> = self.head.as_ptr();
+        while let Some(node) = *link {
+            let node = node.as_ptr();
+            let item = &mut (*node).item;
+            let index = match item.position(address) {
+                Some(index) => index,
+                None => {
+                    link = &mut (*node).next;
+                    continue;
+                }
+            };
+            if item.len() == 1 {
+                *link = (*node).next;
+                self.len.set(self.len.get() - 1);
+                return Some((ptr::read(item), Some(NonNull::new_unchecked(node).cast())));
+            }
+            if index > 0 && index + 1 < item.len() {
+                // The newer values get their own entry, taking the run's place in the list
+                let newer = item.split_newest(index);
+                let spare = spare().cast::<DropNode>();
+                spare.as_ptr().write(DropNode {
+                    item: newer,
+                    next: Some(NonNull::new_unchecked(node)),
+                });
+                *link = Some(spare);
+            }
+            let item = &mut (*node).item;
+            let removed = if item.position(address) == Some(0) {
+                item.split_newest(1)
+            } else {
+                // The oldest value is at the end of the run
+                item.count -= 1;
+                let oldest = DynamicArenaItem {
+                    value: item.value_at(item.len()),
+                    count: 1,
+                    ..*item
+                };
+                item.serial += 1;
+                oldest
+            };
+            self.len.set(self.len.get() - 1);
+            return Some((removed, None));
+        }
+        None
+    }
+    /// Forget every entry without dropping anything
+    #[inline]
+    pub(crate) fn forget_all(&self) {
+        self.head.set(None);
+        self.len.set(0);
+    }
+    /// Move every entry from the other list to the front of this one,
+    /// so they're newer than all of this list's entries
+    ///
+    /// The entries get new serials, in the same order as they had in the other list.
+    pub(crate) fn append(&self, other: &DropList) {
+        let first = match other.head.take() {
+            Some(first) => first,
+            None => return,
+        };
+        let len = other.len.replace(0);
+        let end = self.next_serial.get() + len as u64;
+        unsafe {
+            let mut last = first.as_ptr();
+            let mut serial = end - (*last).item.len() as u64;
+            (*last).item.serial = serial;
+            while let Some(next) = (*last).next {
+                last = next.as_ptr();
+                serial -= (*last).item.len() as u64;
+                (*last).item.serial = serial;
+            }
+            (*last).next = self.head.get();
+        }
+        self.head.set(Some(first));
+        self.len.set(self.len.get() + len);
+        self.next_serial.set(end);
+    }
+    /// Whether the values are being dropped
+    #[inline]
+    pub(crate) fn is_dropping(&self) -> bool {
+        self.dropping.get()
+    }
+    /// Prevent any more values from being registered until the guard is dropped
+    #[inline]
+    pub(crate) fn lock(&self) -> DroppingGuard<'_> {
+        DroppingGuard {
+            list: self,
+            previous: self.dropping.replace(true),
+        }
+    }
+}
+
+/// Marks the values in a drop list as being dropped, while it's alive
+pub(crate) struct DroppingGuard<'l> {
+    list: &'l DropList,
+    previous: bool,
+}
+impl Drop for DroppingGuard<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.list.dropping.set(self.previous);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bumpalo::Bump;
+
+    unsafe fn ignore(_value: *mut c_void) {}
+    /// A single copy of the function pointer, since copies aren't guaranteed to be equal
+    static IGNORE: unsafe fn(*mut c_void) = ignore;
+
+    /// Register a fake value with the specified tag, at an address derived from the tag
+    fn push(list: &DropList, handle: &Bump, tag: u32) {
+        let value = (0x1000 + tag as usize * 8) as *mut c_void;
+        unsafe {
+            list.push(
+                handle.alloc_layout(DropList::NODE_LAYOUT),
+                DynamicArenaItem::single(IGNORE, value, 8, Some(tag), 0),
+            );
+        }
+    }
+    fn tags<'l>(items: impl IntoIterator<Item = &'l DynamicArenaItem>) -> Vec<u32> {
+        items.into_iter().map(|item| item.tag).collect()
+    }
+
+    #[test]
+    fn unlinking() {
+        let handle = Bump::new();
+        let (list, other) = (DropList::default(), DropList::default());
+        for tag in 0..6 {
+            push(&list, &handle, tag);
+        }
+        assert_eq!(tags(&list.extract(|item| item.tag % 2 == 1)), vec![1, 3, 5]);
+        assert_eq!(tags(list.iter()), vec![4, 2, 0]);
+        push(&other, &handle, 10);
+        list.append(&other);
+        assert_eq!((tags(list.iter()), list.len()), (vec![10, 4, 2, 0], 4));
+        assert!(other.is_empty());
+        let spare = || unreachable!();
+        let (removed, node) = unsafe { list.remove_value(0x1000 + 2 * 8, spare) }.unwrap();
+        assert_eq!((removed.tag, node.is_some()), (2, true));
+        assert_eq!(tags(&list.split_off(1)), vec![4, 10]);
+        assert_eq!((tags(list.iter()), list.len()), (vec![0], 1));
+    }
+    #[test]
+    fn runs() {
+        let handle = Bump::new();
+        let list = DropList::default();
+        push(&list, &handle, 10);
+        // Each value is right before the previous one, like the bump allocator places them
+        for tag in (5..10).rev() {
+            let value = (0x1000 + tag * 8) as *mut c_void;
+            assert!(list.head_matches(IGNORE, 8, Some(10), 0));
+            assert!(list.precedes_head(value));
+            unsafe { list.extend_head(value) };
+        }
+        assert!(!list.head_matches(IGNORE, 8, None, 0));
+        assert!(!list.precedes_head(0x1000 as *mut c_void));
+        assert_eq!((list.len(), list.iter().count()), (6, 1));
+        // Removing a value from the middle splits the run
+        let spare = || handle.alloc_layout(DropList::NODE_LAYOUT);
+        let (removed, node) = unsafe { list.remove_value(0x1000 + 7 * 8, spare) }.unwrap();
+        assert_eq!(
+            (removed.value as usize, removed.len(), node),
+            (0x1000 + 7 * 8, 1, None)
+        );
+        let runs: Vec<_> = list
+            .iter()
+            .map(|item| (item.value as usize, item.len()))
+            .collect();
+        assert_eq!(runs, vec![(0x1000 + 5 * 8, 2), (0x1000 + 8 * 8, 3)]);
+        // Popping part of a run splits off its newest values
+        let popped = list.pop_at_most(1).unwrap();
+        assert_eq!((popped.value as usize, popped.len()), (0x1000 + 5 * 8, 1));
+        let rest: Vec<_> = list
+            .split_off(1)
+            .iter()
+            .map(|item| (item.value as usize, item.len()))
+            .collect();
+        assert_eq!(rest, vec![(0x1000 + 8 * 8, 2), (0x1000 + 6 * 8, 1)]);
+        assert_eq!(
+            list.iter()
+                .flat_map(|item| item.addresses())
+                .collect::<Vec<_>>(),
+            vec![0x1000 + 10 * 8]
+        );
+    }
+}
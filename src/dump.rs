@@ -0,0 +1,168 @@
+//! Printing the contents of an arena, enabled by the `debug-dump` feature.
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::{self, Debug, Write};
+use std::mem;
+use std::os::raw::c_void;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::{DynamicArena, ItemBound, DEFAULT_DROP_PHASE};
+
+/// A recorded value, which can be printed if it was allocated by `alloc_debuggable`
+struct DumpEntry {
+    type_name: &'static str,
+    value: *const c_void,
+    fmt: Option<unsafe fn(*const c_void, &mut fmt::Formatter<'_>) -> fmt::Result>,
+    needs_drop: bool,
+}
+
+/// The values in the arena, in allocation order
+#[derive(Default)]
+pub(crate) struct DebugDump {
+    entries: RefCell<Vec<DumpEntry>>,
+}
+/// The values are only `Send` in a `Sendable` arena
+unsafe impl Send for DebugDump {}
+impl DebugDump {
+    /// Record a value that can't be printed
+    #[inline]
+    pub(crate) fn record_opaque<T>(&self, value: *const T) {
+        self.record::<T>(value, None);
+    }
+    #[inline]
+    fn record<T>(
+        &self,
+        value: *const T,
+        fmt: Option<unsafe fn(*const c_void, &mut fmt::Formatter<'_>) -> fmt::Result>,
+    ) {
+        self.entries.borrow_mut().push(DumpEntry {
+            type_name: std::any::type_name::<T>(),
+            value: value.cast(),
+            fmt,
+            needs_drop: mem::needs_drop::<T>(),
+        });
+    }
+    /// Forget the value at the specified address, since its memory was reclaimed
+    pub(crate) fn forget(&self, value: *const c_void) {
+        let mut entries = self.entries.borrow_mut();
+        if let Some(index) = entries.iter().rposition(|entry| entry.value == value) {
+            entries.remove(index);
+        }
+    }
+    pub(crate) fn clear(&mut self) {
+        self.entries.get_mut().clear();
+    }
+    /// Whether any of the values can be printed, which would access them from the current thread
+    pub(crate) fn has_printable(&mut self) -> bool {
+        self.entries
+            .get_mut()
+            .iter()
+            .any(|entry| entry.fmt.is_some())
+    }
+}
+
+unsafe fn debug_value<T: Debug>(value: *const c_void, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    Debug::fmt(&*value.cast::<T>(), f)
+}
+
+/// A type-erased value, formatted with its recorded `Debug` implementation
+struct Erased<'e>(&'e DumpEntry);
+impl Debug for Erased<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.fmt {
+            // The value is still alive, and was allocated with this type
+            Some(func) => unsafe { func(self.0.value, f) },
+            None => f.write_str("<opaque>"),
+        }
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Print each value in the arena along with its type, in allocation order
+    ///
+    /// Only values allocated by `alloc_debuggable` can be printed,
+    /// while the values from `alloc`, `alloc_copy` and `alloc_unchecked` are listed as `<opaque>`.
+    /// Values that have already been dropped (by rolling back or `drop_region`) are skipped.
+    /// If a `Debug` implementation panics, the panic is caught and the dump continues.
+    pub fn debug_dump(&self, out: &mut impl Write) -> fmt::Result {
+        let live: HashSet<usize> = self
+            .items
+            .iter()
+            .flat_map(|item| item.addresses())
+            .collect();
+        let entries = self.dump.entries.borrow();
+        let mut buffer = String::new();
+        for (index, entry) in entries.iter().enumerate() {
+            if entry.needs_drop && !live.contains(&(entry.value as usize)) {
+                continue;
+            }
+            buffer.clear();
+            let formatted =
+                panic::catch_unwind(AssertUnwindSafe(|| write!(buffer, "{:?}", Erased(entry))));
+            match formatted {
+                Ok(Ok(())) => writeln!(out, "{}: {} = {}", index, entry.type_name, buffer)?,
+                _ => writeln!(out, "{}: {} = <panicked>", index, entry.type_name)?,
+            }
+        }
+        Ok(())
+    }
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    unsafe fn alloc_debuggable_unchecked<T: Debug>(&self, value: T) -> &mut T {
+        let target = self.alloc_registered(value, None, DEFAULT_DROP_PHASE);
+        self.dump.record::<T>(target, Some(debug_value::<T>));
+        &mut *target
+    }
+}
+impl<'a, S> DynamicArena<'a, S> {
+    /// Allocate the specified value, recording its `Debug` implementation for `debug_dump`.
+    ///
+    /// Otherwise, this is the same as `alloc`.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_debuggable<T: Debug + 'a>(&self, value: T) -> &mut T
+    where
+        S: ItemBound<T>,
+    {
+        unsafe { self.alloc_debuggable_unchecked(value) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Unprintable;
+    impl Debug for Unprintable {
+        fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            panic!("unprintable")
+        }
+    }
+
+    #[test]
+    fn dump() {
+        let mut arena = DynamicArena::new();
+        arena.alloc_debuggable(String::from("first"));
+        arena.alloc_copy(5u32);
+        arena.alloc_debuggable(Unprintable);
+        let checkpoint = arena.checkpoint();
+        arena.alloc_debuggable(vec![1, 2]);
+        arena.alloc_debuggable(7u8);
+        arena.rollback_to(checkpoint);
+        let mut out = String::new();
+        arena.debug_dump(&mut out).unwrap();
+        let expected = [
+            "0: alloc::string::String = \"first\"",
+            "1: u32 = <opaque>",
+            "2: dynamic_arena::dump::test::Unprintable = <panicked>",
+            "4: u8 = 7",
+        ];
+        assert_eq!(out.lines().collect::<Vec<_>>(), expected);
+        arena.reset();
+        out.clear();
+        arena.debug_dump(&mut out).unwrap();
+        assert_eq!(out, "");
+    }
+}
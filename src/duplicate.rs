@@ -0,0 +1,93 @@
+//! Detecting values that are registered to be dropped twice, in debug builds.
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+use crate::droplist::DropList;
+
+/// The addresses that have been registered to be dropped
+///
+/// This is only an index, and entries aren't removed when their values are dropped.
+/// A repeated address is confirmed against the drop list before it's reported,
+/// so memory that's reused (like after rolling back) never causes a false positive.
+/// Unsized values are registered through a copy of their fat pointer, so they're never reported.
+#[derive(Default)]
+pub(crate) struct RegisteredAddresses {
+    addresses: RefCell<HashSet<usize>>,
+}
+impl RegisteredAddresses {
+    /// Record the address of a value that's about to be registered,
+    /// panicking if it's already registered to be dropped
+    ///
+    /// Zero-sized values are ignored, since they can share an address.
+    pub(crate) fn check<T: ?Sized>(&self, address: usize, zero_sized: bool, items: &DropList) {
+        // If the values are being dropped, registering the value fails anyway
+        if zero_sized || items.is_dropping() {
+            return;
+        }
+        let mut addresses = self.addresses.borrow_mut();
+        if items.is_empty() {
+            // Everything that was recorded has already been dropped
+            addresses.clear();
+        }
+        if !addresses.insert(address)
+            && items
+                .iter()
+                .any(|item| !item.zero_sized() && item.position(address).is_some())
+        {
+            panic!(
+                "The value at {:#x} (of type {}) is already registered to be dropped",
+                address,
+                std::any::type_name::<T>()
+            );
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use crate::DynamicArena;
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn duplicate_registration() {
+        let arena = DynamicArena::new();
+        let value = arena.alloc(String::from("registered")) as *mut String;
+        let cause = panic::catch_unwind(AssertUnwindSafe(|| unsafe { arena.dynamic_drop(value) }))
+            .unwrap_err();
+        let message = cause.downcast::<String>().unwrap();
+        assert_eq!(
+            *message,
+            format!(
+                "The value at {:#x} (of type alloc::string::String) is already registered to be dropped",
+                value as usize
+            )
+        );
+        // The rejected registration didn't drop the value, so it's still intact
+        assert_eq!(
+            (unsafe { &*value }.as_str(), arena.pending_drop_count()),
+            ("registered", 1)
+        );
+    }
+    #[test]
+    fn distinct_addresses() {
+        struct Marker;
+        impl Drop for Marker {
+            fn drop(&mut self) {}
+        }
+        let mut arena = DynamicArena::new();
+        for round in 0..3 {
+            let checkpoint = arena.checkpoint();
+            for i in 0..1000 {
+                arena.alloc(i.to_string());
+                // Zero-sized values can share an address
+                arena.alloc(Marker);
+            }
+            // The rolled back memory is reused by the next round
+            arena.rollback_to(checkpoint);
+            arena.alloc(vec![round]);
+        }
+        assert_eq!(arena.pending_drop_count(), 3);
+        arena.reset();
+        arena.alloc(String::from("after reset"));
+    }
+}
@@ -0,0 +1,118 @@
+//! Finalizer closures, which run when the arena is torn down.
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+
+use crate::{DynamicArena, ItemBound, DEFAULT_DROP_PHASE};
+
+/// A closure that runs when it's dropped
+struct Finalizer<F: FnOnce()>(Option<F>);
+impl<F: FnOnce()> Drop for Finalizer<F> {
+    fn drop(&mut self) {
+        if let Some(func) = self.0.take() {
+            if thread::panicking() {
+                /*
+                 * Another drop already panicked, so panicking again would abort.
+                 * The first panic is the one that's propagated.
+                 */
+                let _ = panic::catch_unwind(AssertUnwindSafe(func));
+            } else {
+                func();
+            }
+        }
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    unsafe fn on_drop_unchecked<F: FnOnce()>(&self, func: F) {
+        self.alloc_registered(Finalizer(Some(func)), None, DEFAULT_DROP_PHASE);
+    }
+}
+impl<'a, S> DynamicArena<'a, S> {
+    /// Schedule the specified closure to run when the arena is dropped (or reset).
+    ///
+    /// The closure is allocated in the arena, and registered just like a value that needs to be dropped.
+    /// This means finalizers run in the default phase, interleaved with the value drops
+    /// in strict reverse registration order (see `alloc_in_phase`).
+    /// Rolling back to an earlier checkpoint also runs the finalizer.
+    /// Just like a value, the closure must satisfy the arena's `ItemBound`.
+    ///
+    /// If a finalizer panics, the remaining finalizers and values are still dropped,
+    /// and then the panic is propagated.
+    /// Any panics from later finalizers are discarded instead of aborting.
+    ///
+    /// The `defer!` macro is a shorthand for this.
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn on_drop<F: FnOnce() + 'a>(&self, func: F)
+    where
+        S: ItemBound<F>,
+    {
+        unsafe { self.on_drop_unchecked(func) }
+    }
+}
+
+/// Run the specified code when the arena is dropped (or reset)
+///
+/// This is a shorthand for `DynamicArena::on_drop` with a `move` closure.
+/// ````
+/// # #[cfg(not(feature = "no-drop-tracking"))] {
+/// # use dynamic_arena::{defer, DynamicArena};
+/// let arena = DynamicArena::new();
+/// let name = String::from("profiler");
+/// defer!(arena, println!("Flushing {}", name));
+/// # }
+/// ````
+#[macro_export]
+macro_rules! defer {
+    ($arena:expr, $($body:tt)*) => {
+        $arena.on_drop(move || { $($body)*; })
+    };
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+
+    struct DropOrder<'a>(&'a RefCell<Vec<&'static str>>, &'static str);
+    impl<'a> Drop for DropOrder<'a> {
+        fn drop(&mut self) {
+            self.0.borrow_mut().push(self.1);
+        }
+    }
+
+    #[test]
+    fn registration_order() {
+        let order = RefCell::new(Vec::new());
+        let mut arena = DynamicArena::new_bounded();
+        arena.alloc(DropOrder(&order, "value-1"));
+        arena.on_drop(|| order.borrow_mut().push("finalizer-1"));
+        arena.alloc(DropOrder(&order, "value-2"));
+        let order_ref = &order;
+        defer!(arena, order_ref.borrow_mut().push("finalizer-2"));
+        arena.reset();
+        assert_eq!(
+            *order.borrow(),
+            vec!["finalizer-2", "value-2", "finalizer-1", "value-1"]
+        );
+        let arena = DynamicArena::new_send();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        arena.on_drop(move || sender.send("sent").unwrap());
+        std::thread::spawn(move || drop(arena)).join().unwrap();
+        assert_eq!(receiver.recv(), Ok("sent"));
+    }
+    #[test]
+    fn panics_isolated() {
+        let order = RefCell::new(Vec::new());
+        let arena = DynamicArena::new_bounded();
+        arena.on_drop(|| panic!("first"));
+        arena.alloc(DropOrder(&order, "value"));
+        arena.on_drop(|| panic!("second"));
+        arena.on_drop(|| order.borrow_mut().push("finalizer"));
+        let cause = panic::catch_unwind(AssertUnwindSafe(|| drop(arena))).unwrap_err();
+        assert_eq!(*cause.downcast::<&str>().unwrap(), "second");
+        assert_eq!(*order.borrow(), vec!["finalizer", "value"]);
+    }
+}
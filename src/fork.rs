@@ -0,0 +1,225 @@
+//! Speculative forks of an arena, which can be committed or discarded.
+use std::alloc::Layout;
+use std::fmt::{self, Debug};
+use std::mem;
+use std::ptr;
+use std::slice;
+
+use bumpalo::Bump;
+
+use crate::{DynamicArena, ItemBound};
+
+/// A speculative extension of a `DynamicArena`, containing only `Copy` data.
+///
+/// The fork allocates into its own fresh chunks, leaving the parent untouched.
+/// Afterwards, `commit` folds those chunks into the parent, keeping everything that was allocated,
+/// while `discard` frees them (dropping the fork without committing also discards it).
+/// Since everything is `Copy`, there's nothing to drop either way.
+///
+/// The fork only borrows the parent, so the parent's existing data can still be read while speculating
+/// (and referenced from the fork's data).
+/// However, the parent can't allocate until the fork is committed or discarded,
+/// since the fork was given whatever remained of the parent's limit. Trying to panics.
+/// References returned by the fork are limited to the fork's lifetime,
+/// so committed data should be reachable through something
+/// that doesn't borrow the fork (like indices or raw pointers).
+///
+/// This is created by `DynamicArena::fork`.
+pub struct ArenaFork<'p, 'a, S> {
+    parent: &'p DynamicArena<'a, S>,
+    handle: Bump,
+}
+impl<'p, 'a, S> Drop for ArenaFork<'p, 'a, S> {
+    #[inline]
+    fn drop(&mut self) {
+        self.parent.forked.set(false);
+    }
+}
+impl<'p, 'a, S> Debug for ArenaFork<'p, 'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArenaFork")
+            .field("allocated_bytes", &self.allocated_bytes())
+            .finish()
+    }
+}
+impl<'p, 'a, S> ArenaFork<'p, 'a, S> {
+    /// Allocate the specified copyable value in the fork
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_copy<T: Copy>(&self, value: T) -> &mut T
+    where
+        S: ItemBound<T>,
+    {
+        unsafe {
+            let target = self.alloc_layout(Layout::new::<T>()).cast::<T>();
+            target.write(value);
+            &mut *target
+        }
+    }
+    /// Allocate a copy of the specified string in the fork
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_str(&self, s: &str) -> &mut str {
+        unsafe {
+            let target = self.alloc_layout(Layout::for_value(s));
+            ptr::copy_nonoverlapping(s.as_ptr(), target, s.len());
+            std::str::from_utf8_unchecked_mut(slice::from_raw_parts_mut(target, s.len()))
+        }
+    }
+    /// Allocate a copy of the specified slice in the fork
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy<T: Copy>(&self, src: &[T]) -> &mut [T]
+    where
+        S: ItemBound<T>,
+    {
+        unsafe {
+            let target = self.alloc_layout(Layout::for_value(src)).cast::<T>();
+            ptr::copy_nonoverlapping(src.as_ptr(), target, src.len());
+            slice::from_raw_parts_mut(target, src.len())
+        }
+    }
+    /// Allocate memory in the fork's own chunks, with the parent's minimum alignment
+    #[inline]
+    fn alloc_layout(&self, layout: Layout) -> *mut u8 {
+        self.handle
+            .alloc_layout(self.parent.aligned_layout(layout))
+            .as_ptr()
+    }
+    /// The number of bytes in the fork's own chunks
+    #[inline]
+    pub fn allocated_bytes(&self) -> usize {
+        self.handle.allocated_bytes()
+    }
+    /// Keep everything the fork allocated, folding its chunks into the parent.
+    ///
+    /// The memory is freed when the parent is reset or dropped.
+    pub fn commit(mut self) {
+        let handle = mem::take(&mut self.handle);
+        self.parent.adopted.borrow_mut().push(handle);
+    }
+    /// Free everything the fork allocated, leaving the parent as it was before the fork
+    #[inline]
+    pub fn discard(self) {
+        drop(self);
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Fork the arena for speculative allocation of `Copy` data,
+    /// which can later be committed or discarded.
+    ///
+    /// The fork starts out without any chunks,
+    /// and its allocation limit is whatever remains of the parent's limit.
+    ///
+    /// See `ArenaFork` for details.
+    ///
+    /// ## Panics
+    /// If the arena already has an outstanding fork.
+    pub fn fork(&self) -> ArenaFork<'_, 'a, S> {
+        assert!(
+            !self.forked.replace(true),
+            "The arena already has an outstanding fork"
+        );
+        let handle = Bump::new();
+        let allocated = self.handle.allocated_bytes()
+            + self
+                .adopted
+                .borrow()
+                .iter()
+                .map(Bump::allocated_bytes)
+                .sum::<usize>();
+        handle.set_allocation_limit(
+            self.handle
+                .allocation_limit()
+                .map(|limit| limit.saturating_sub(allocated)),
+        );
+        ArenaFork {
+            parent: self,
+            handle,
+        }
+    }
+    /// The number of committed forks whose chunks are retained until the next reset
+    #[inline]
+    pub fn committed_fork_count(&self) -> usize {
+        self.adopted.borrow().len()
+    }
+    /// Check that the arena isn't forked, before counting an allocation
+    #[inline]
+    pub(crate) fn check_not_forked(&self) {
+        if self.forked.get() {
+            fork_outstanding()
+        }
+    }
+}
+
+#[cold]
+#[inline(never)]
+#[cfg_attr(feature = "track-callers", track_caller)]
+fn fork_outstanding() -> ! {
+    panic!("The arena can't allocate while it has an outstanding fork")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    struct Frame {
+        depth: u32,
+        name: &'static str,
+    }
+
+    #[test]
+    fn commit() {
+        let mut arena = DynamicArena::new();
+        let base = &*arena.alloc_copy(Frame {
+            depth: 0,
+            name: "main",
+        });
+        let fork = arena.fork();
+        assert_eq!(fork.allocated_bytes(), 0);
+        // The parent's data can be read (and copied) while speculating
+        let speculated: *const Frame = fork.alloc_copy(Frame {
+            depth: base.depth + 1,
+            name: "speculated",
+        });
+        assert_eq!(fork.alloc_str("hello"), "hello");
+        assert_eq!(fork.alloc_slice_copy(&[1, 2, 3]), &[1, 2, 3]);
+        assert!(fork.allocated_bytes() > 0);
+        fork.commit();
+        assert_eq!(arena.committed_fork_count(), 1);
+        // Both the parent's and the committed data are still valid
+        assert_eq!(base.name, "main");
+        assert_eq!(unsafe { (*speculated).depth }, 1);
+        arena.alloc_copy(7u32);
+        arena.verify().unwrap();
+        arena.reset();
+        assert_eq!(arena.committed_fork_count(), 0);
+    }
+    #[test]
+    fn discard() {
+        let arena = DynamicArena::new();
+        arena.set_allocation_limit(Some(1 << 16));
+        arena.alloc_slice_fill_copy(1000, 0u8);
+        let allocated = arena.as_bumpalo().allocated_bytes();
+        let fork = arena.fork();
+        fork.alloc_slice_copy(&[0u8; 4096]);
+        // The fork can't exceed what's left of the parent's limit
+        assert!(fork.handle.try_alloc_slice_copy(&[0u8; 1 << 16]).is_err());
+        fork.discard();
+        let fork = arena.fork();
+        fork.alloc_copy(1u64);
+        drop(fork);
+        assert_eq!(arena.committed_fork_count(), 0);
+        assert_eq!(arena.as_bumpalo().allocated_bytes(), allocated);
+    }
+    #[test]
+    #[should_panic(expected = "outstanding fork")]
+    fn blocks_parent() {
+        let arena = DynamicArena::new();
+        let fork = arena.fork();
+        fork.alloc_copy(1u8);
+        arena.alloc_copy(2u8);
+    }
+}
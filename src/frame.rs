@@ -0,0 +1,133 @@
+//! Double-buffered arenas for frame-based loops.
+use std::fmt::{self, Debug};
+
+use crate::{DynamicArena, NonSend, SendAbility};
+
+/// A pair of arenas for loops where data lives for exactly one extra frame.
+///
+/// Each frame allocates into the `current` arena,
+/// while still being able to read what was allocated into the `previous` one.
+/// Calling `flip` resets the older arena (running its drops) and swaps their roles.
+/// Since `flip` takes `&mut self`, it's impossible to hold a reference
+/// into either arena across a flip.
+///
+/// In order to tune the arenas' capacities,
+/// this records the number of bytes allocated by each frame.
+pub struct FrameArenas<'a, S = NonSend> {
+    arenas: [DynamicArena<'a, S>; 2],
+    current: usize,
+    frame: u64,
+    last_frame_bytes: usize,
+    max_frame_bytes: usize,
+}
+impl<'a, S> Debug for FrameArenas<'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameArenas")
+            .field("frame", &self.frame)
+            .field("current", &self.arenas[self.current])
+            .field("last_frame_bytes", &self.last_frame_bytes)
+            .field("max_frame_bytes", &self.max_frame_bytes)
+            .finish()
+    }
+}
+impl<'a, S: SendAbility> FrameArenas<'a, S> {
+    /// Create a pair of empty arenas
+    #[inline]
+    pub fn new() -> Self {
+        FrameArenas {
+            arenas: [S::create_arena(), S::create_arena()],
+            current: 0,
+            frame: 0,
+            last_frame_bytes: 0,
+            max_frame_bytes: 0,
+        }
+    }
+}
+impl<'a, S> FrameArenas<'a, S> {
+    /// The arena for the current frame
+    #[inline]
+    pub fn current(&self) -> &DynamicArena<'a, S> {
+        &self.arenas[self.current]
+    }
+    /// The arena for the previous frame, which is reset by the next `flip`
+    #[inline]
+    pub fn previous(&self) -> &DynamicArena<'a, S> {
+        &self.arenas[1 - self.current]
+    }
+    /// Finish the current frame, making it the previous one.
+    ///
+    /// The arena for the old previous frame is reset (running its drops),
+    /// and becomes the arena for the new current frame.
+    pub fn flip(&mut self) {
+        let bytes = self.current().used_bytes();
+        self.last_frame_bytes = bytes;
+        self.max_frame_bytes = self.max_frame_bytes.max(bytes);
+        self.frame += 1;
+        self.current = 1 - self.current;
+        self.arenas[self.current].reset();
+    }
+    /// The number of frames that have been finished by calling `flip`
+    #[inline]
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+    /// The number of bytes allocated by the most recently finished frame
+    #[inline]
+    pub fn last_frame_bytes(&self) -> usize {
+        self.last_frame_bytes
+    }
+    /// The largest number of bytes allocated by any finished frame
+    #[inline]
+    pub fn max_frame_bytes(&self) -> usize {
+        self.max_frame_bytes
+    }
+}
+impl<'a, S: SendAbility> Default for FrameArenas<'a, S> {
+    #[inline]
+    fn default() -> Self {
+        FrameArenas::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use crate::test_util::DropCounted;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::cell::Cell;
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn simulated_frames() {
+        let counter = Cell::new(0);
+        let mut frames = FrameArenas::<NonSend>::new();
+        for frame in 0..5u32 {
+            frames.current().alloc(DropCounted(&counter));
+            frames
+                .current()
+                .alloc_slice_fill_copy(frame as usize * 100, 0u8);
+            // Values are dropped one frame after the one they're allocated in
+            assert_eq!(counter.get(), frame.saturating_sub(1));
+            frames.flip();
+            assert!(frames.last_frame_bytes() >= frame as usize * 100);
+        }
+        assert_eq!(frames.frame(), 5);
+        assert!(frames.max_frame_bytes() >= 400);
+        assert_eq!(counter.get(), 4);
+        drop(frames);
+        assert_eq!(counter.get(), 5);
+    }
+    #[test]
+    fn swapped_roles() {
+        let mut frames = FrameArenas::<NonSend>::default();
+        let first: *const DynamicArena = frames.current();
+        let second: *const DynamicArena = frames.previous();
+        assert_ne!(first, second);
+        frames.flip();
+        assert_eq!(frames.previous() as *const DynamicArena, first);
+        assert_eq!(frames.current() as *const DynamicArena, second);
+        frames.flip();
+        assert_eq!(frames.current() as *const DynamicArena, first);
+    }
+}
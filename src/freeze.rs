@@ -0,0 +1,233 @@
+//! Freezing an arena into a read-only view, which can be shared between threads.
+use std::cell::Cell;
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{DynamicArena, DynamicSendArena, Sendable};
+
+/// The source of the epochs, which are unique across every arena
+static NEXT_EPOCH: AtomicU64 = AtomicU64::new(1);
+
+/// The epoch of the handles issued by an arena, which changes whenever any of its values are dropped
+///
+/// Zero means no handles have been issued since the last change, so invalidating them is just a store.
+#[derive(Default)]
+pub(crate) struct HandleEpoch(Cell<u64>);
+impl HandleEpoch {
+    #[inline]
+    fn current(&self) -> u64 {
+        match self.0.get() {
+            0 => {
+                let epoch = NEXT_EPOCH.fetch_add(1, Ordering::Relaxed);
+                self.0.set(epoch);
+                epoch
+            }
+            epoch => epoch,
+        }
+    }
+    /// Invalidate every outstanding handle, since their values may have been dropped
+    #[inline]
+    pub(crate) fn invalidate(&self) {
+        self.0.set(0);
+    }
+}
+
+/// A handle to a value allocated by `alloc_shared`, which can outlive the borrow of the arena
+///
+/// Unlike a reference, this doesn't borrow the arena, so it stays valid when the arena is frozen.
+/// It can be resolved to a reference with `FrozenArena::resolve` (or `DynamicArena::resolve`).
+///
+/// Handles are invalidated when any value in the arena is dropped
+/// (by resetting, rolling back, `drop_region` or `drop_some`),
+/// even if their own value wasn't dropped.
+pub struct SharedHandle<T> {
+    value: NonNull<T>,
+    epoch: u64,
+    marker: PhantomData<T>,
+}
+impl<T> Clone for SharedHandle<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for SharedHandle<T> {}
+impl<T> Debug for SharedHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedHandle").field(&self.value).finish()
+    }
+}
+/// Resolving a handle only gives out shared references, and the value is `Send + Sync`
+unsafe impl<T: Sync> Send for SharedHandle<T> {}
+unsafe impl<T: Sync> Sync for SharedHandle<T> {}
+
+#[inline]
+#[track_caller]
+fn check_epoch<T>(handle: &SharedHandle<T>, epoch: u64) {
+    assert!(
+        handle.epoch == epoch,
+        "The handle belongs to a different arena, or values were dropped since it was allocated"
+    );
+}
+
+impl<'a> DynamicArena<'a, Sendable> {
+    /// Allocate the specified value, returning a handle that remains valid once the arena is frozen
+    ///
+    /// Since the frozen arena can be shared between threads, the value must also be `Sync`.
+    /// Otherwise, this is the same as `alloc`.
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_shared<T: Send + Sync + 'a>(&self, value: T) -> SharedHandle<T> {
+        let value = NonNull::from(self.alloc(value));
+        SharedHandle {
+            value,
+            epoch: self.handle_epoch.current(),
+            marker: PhantomData,
+        }
+    }
+    /// Resolve a handle returned by `alloc_shared`
+    ///
+    /// ## Panics
+    /// If the handle belongs to a different arena, or it was invalidated (see `SharedHandle`).
+    #[inline]
+    #[track_caller]
+    pub fn resolve<T>(&self, handle: SharedHandle<T>) -> &T {
+        check_epoch(&handle, self.handle_epoch.0.get());
+        // The value is still alive, and this arena allocated it
+        unsafe { handle.value.as_ref() }
+    }
+    /// Freeze the arena, so it can be shared between threads but never allocated into again
+    ///
+    /// References into the arena don't survive freezing, so values should be allocated
+    /// with `alloc_shared` and resolved from the frozen arena instead.
+    /// Everything is still dropped when the frozen arena is dropped.
+    pub fn freeze(self) -> FrozenArena<'a> {
+        let allocated_bytes = self.stats().allocated_bytes;
+        let pending_drop_count = self.pending_drop_count();
+        FrozenArena {
+            epoch: self.handle_epoch.0.get(),
+            allocated_bytes,
+            pending_drop_count,
+            arena: self,
+        }
+    }
+}
+
+/// A read-only arena that's `Send + Sync`, as returned by `DynamicArena::freeze`
+///
+/// Nothing can be allocated into the arena,
+/// and its values are only accessible through the handles returned by `alloc_shared`.
+pub struct FrozenArena<'a> {
+    arena: DynamicSendArena<'a>,
+    /// A snapshot of the arena's state, so the interior mutability is never touched
+    epoch: u64,
+    allocated_bytes: usize,
+    pending_drop_count: usize,
+}
+/// The arena is never accessed through a shared reference,
+/// and the only values that are accessible are `Sync`.
+unsafe impl<'a> Sync for FrozenArena<'a> {}
+impl<'a> FrozenArena<'a> {
+    /// Resolve a handle returned by `alloc_shared`, before the arena was frozen
+    ///
+    /// ## Panics
+    /// If the handle belongs to a different arena, or it was invalidated (see `SharedHandle`).
+    #[inline]
+    #[track_caller]
+    pub fn resolve<T>(&self, handle: SharedHandle<T>) -> &T {
+        check_epoch(&handle, self.epoch);
+        // The value is still alive, and this arena allocated it
+        unsafe { handle.value.as_ref() }
+    }
+    /// The number of bytes that were allocated when the arena was frozen
+    #[inline]
+    pub fn allocated_bytes(&self) -> usize {
+        self.allocated_bytes
+    }
+    /// The number of values that will be dropped along with the arena
+    #[inline]
+    pub fn pending_drop_count(&self) -> usize {
+        self.pending_drop_count
+    }
+    /// Unfreeze the arena, so it can be allocated into again
+    ///
+    /// The handles remain valid, until any of the arena's values are dropped.
+    #[inline]
+    pub fn thaw(self) -> DynamicSendArena<'a> {
+        self.arena
+    }
+}
+impl<'a> Debug for FrozenArena<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrozenArena")
+            .field("allocated_bytes", &self.allocated_bytes)
+            .field("pending_drop_count", &self.pending_drop_count)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::thread;
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    struct Symbol {
+        name: String,
+        parent: Option<SharedHandle<Symbol>>,
+    }
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn shared_readers() {
+        let arena = DynamicArena::new_send();
+        let root = arena.alloc_shared(Symbol {
+            name: String::from("root"),
+            parent: None,
+        });
+        let children: Vec<_> = (0..8)
+            .map(|i| {
+                arena.alloc_shared(Symbol {
+                    name: format!("child{}", i),
+                    parent: Some(root),
+                })
+            })
+            .collect();
+        assert_eq!(arena.resolve(children[3]).name, "child3");
+        let frozen = arena.freeze();
+        assert_eq!(frozen.pending_drop_count(), 9);
+        thread::scope(|scope| {
+            for (i, &child) in children.iter().enumerate() {
+                let frozen = &frozen;
+                scope.spawn(move || {
+                    let child = frozen.resolve(child);
+                    assert_eq!(child.name, format!("child{}", i));
+                    assert_eq!(frozen.resolve(child.parent.unwrap()).name, "root");
+                });
+            }
+        });
+        let arena = frozen.thaw();
+        assert_eq!(arena.resolve(root).name, "root");
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    #[should_panic(expected = "values were dropped since it was allocated")]
+    fn invalidated() {
+        let mut arena = DynamicArena::new_send();
+        let handle = arena.alloc_shared(String::from("dropped"));
+        arena.reset();
+        arena.freeze().resolve(handle);
+    }
+    #[test]
+    #[should_panic(expected = "The handle belongs to a different arena")]
+    fn other_arena() {
+        let arena = DynamicArena::new_send();
+        let handle = arena.alloc_shared(5u32);
+        let other = DynamicArena::new_send();
+        other.alloc_shared(6u32);
+        other.freeze().resolve(handle);
+    }
+}
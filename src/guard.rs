@@ -0,0 +1,135 @@
+//! Runtime-checked resets, using guard tokens held by readers.
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::DynamicArena;
+
+/// A token indicating that someone is still reading from an arena,
+/// which prevents `DynamicArena::try_reset` from succeeding.
+///
+/// Unlike a reference, this doesn't borrow the arena,
+/// so it can be passed through type-erased handles and callbacks
+/// where the borrow checker can't help.
+/// The count is atomic, so guards can be sent to other threads.
+///
+/// This is created by `DynamicArena::read_guard`.
+#[derive(Debug)]
+pub struct ArenaReadGuard {
+    readers: Arc<AtomicUsize>,
+}
+impl Clone for ArenaReadGuard {
+    #[inline]
+    fn clone(&self) -> Self {
+        self.readers.fetch_add(1, Ordering::Acquire);
+        ArenaReadGuard {
+            readers: Arc::clone(&self.readers),
+        }
+    }
+}
+impl Drop for ArenaReadGuard {
+    #[inline]
+    fn drop(&mut self) {
+        self.readers.fetch_sub(1, Ordering::Release);
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Acquire a guard which prevents `try_reset` from succeeding until it's dropped
+    ///
+    /// Guards can be nested, and the arena can only be reset once all of them are gone.
+    pub fn read_guard(&self) -> ArenaReadGuard {
+        let readers = self.readers.get_or_init(Default::default);
+        readers.fetch_add(1, Ordering::Acquire);
+        ArenaReadGuard {
+            readers: Arc::clone(readers),
+        }
+    }
+    /// The number of read guards that are currently alive
+    #[inline]
+    pub fn active_readers(&self) -> usize {
+        self.readers
+            .get()
+            .map_or(0, |readers| readers.load(Ordering::Acquire))
+    }
+    /// Reset the arena (just like `reset`), unless there are any active read guards.
+    ///
+    /// If the reset is blocked, nothing is dropped.
+    pub fn try_reset(&mut self) -> Result<(), ResetBlocked> {
+        match self.active_readers() {
+            0 => {
+                self.reset();
+                Ok(())
+            }
+            readers => Err(ResetBlocked { readers }),
+        }
+    }
+}
+
+/// An error indicating that an arena couldn't be reset,
+/// because there were active read guards.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ResetBlocked {
+    readers: usize,
+}
+impl ResetBlocked {
+    /// The number of read guards that were alive
+    #[inline]
+    pub fn readers(&self) -> usize {
+        self.readers
+    }
+}
+impl Display for ResetBlocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unable to reset arena with {} active readers",
+            self.readers
+        )
+    }
+}
+impl Error for ResetBlocked {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::rc::Rc;
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn blocked() {
+        let tracker = Rc::new(());
+        let mut arena = DynamicArena::new();
+        assert_eq!(arena.try_reset(), Ok(()));
+        arena.alloc(Rc::clone(&tracker));
+        let guard = arena.read_guard();
+        let err = arena.try_reset().unwrap_err();
+        assert_eq!(err.readers(), 1);
+        assert_eq!(
+            err.to_string(),
+            "Unable to reset arena with 1 active readers"
+        );
+        // Nothing was dropped
+        assert_eq!(Rc::strong_count(&tracker), 2);
+        drop(guard);
+        assert_eq!(arena.try_reset(), Ok(()));
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+    #[test]
+    fn nested() {
+        let mut arena = DynamicArena::new_send();
+        let outer = arena.read_guard();
+        let inner = arena.read_guard();
+        let cloned = inner.clone();
+        assert_eq!(arena.active_readers(), 3);
+        drop(inner);
+        assert_eq!(arena.try_reset().unwrap_err().readers(), 2);
+        std::thread::spawn(move || drop(cloned)).join().unwrap();
+        assert_eq!(arena.try_reset().unwrap_err().readers(), 1);
+        drop(outer);
+        assert_eq!(arena.active_readers(), 0);
+        assert!(arena.try_reset().is_ok());
+    }
+}
@@ -0,0 +1,233 @@
+//! A herd of arenas, for building a single structure from many threads at once.
+use std::fmt::{self, Debug};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::{DynamicArena, DynamicSendArena};
+
+/// A collection of `Sendable` arenas that share a single lifetime,
+/// so several threads can allocate at once without any locking.
+///
+/// Each thread takes a `Member` from the herd and allocates into it directly,
+/// and returns it to the herd when the guard is dropped.
+/// Everything allocated by a member lives as long as the herd itself (not just the guard),
+/// so values in one member can refer to values in another,
+/// like the siblings of a tree built by fork-join parallelism.
+///
+/// The members are never reset, and they're only dropped when the herd is dropped,
+/// which drops everything that was allocated by any of them.
+pub struct SendableHerd<'a> {
+    /// The members that aren't currently taken
+    idle: Mutex<Vec<DynamicSendArena<'a>>>,
+}
+impl SendableHerd<'static> {
+    /// Create a new empty herd, whose values must be `'static`
+    #[inline]
+    pub fn new() -> Self {
+        SendableHerd::new_bounded()
+    }
+}
+impl Default for SendableHerd<'static> {
+    #[inline]
+    fn default() -> Self {
+        SendableHerd::new()
+    }
+}
+impl<'a> SendableHerd<'a> {
+    /// Create a new empty herd, bounded by the inferred lifetime for this type `'a`
+    #[inline]
+    pub fn new_bounded() -> Self {
+        SendableHerd {
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, Vec<DynamicSendArena<'a>>> {
+        // The list is always consistent, so poisoning is harmless
+        self.idle.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    /// Take a member from the herd, creating a new one if they're all taken
+    ///
+    /// Only taking and returning members needs to lock the herd, so each thread should keep
+    /// its member for as long as possible (like for the duration of a rayon task).
+    pub fn get(&self) -> Member<'_, 'a> {
+        let arena = self.lock().pop().unwrap_or_else(DynamicArena::new_send);
+        Member {
+            herd: self,
+            arena: Some(arena),
+        }
+    }
+    /// The number of members that aren't currently taken
+    #[inline]
+    pub fn idle_member_count(&self) -> usize {
+        self.lock().len()
+    }
+    /// The total number of bytes in the chunks of all the members that aren't currently taken
+    pub fn allocated_bytes(&self) -> usize {
+        self.lock()
+            .iter()
+            .map(|arena| arena.as_bumpalo().allocated_bytes())
+            .sum()
+    }
+}
+impl<'a> Debug for SendableHerd<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("SendableHerd");
+        match self.idle.try_lock() {
+            Ok(idle) => debug.field("idle_members", &idle.len()),
+            Err(_) => debug.field("idle_members", &format_args!("<locked>")),
+        };
+        debug.finish()
+    }
+}
+
+/// A member of a `SendableHerd`, which is returned to the herd when it's dropped
+///
+/// Allocations return references that live as long as the herd, rather than the member.
+/// This is sound because the member's arena is never reset or dropped before the herd is,
+/// and its chunks never move even though the arena itself is moved back into the herd.
+pub struct Member<'h, 'a> {
+    herd: &'h SendableHerd<'a>,
+    arena: Option<DynamicSendArena<'a>>,
+}
+impl<'h, 'a> Member<'h, 'a> {
+    #[inline]
+    fn arena(&self) -> &DynamicSendArena<'a> {
+        self.arena.as_ref().unwrap()
+    }
+    /// Allocate the specified value in the member, living as long as the herd
+    ///
+    /// See `DynamicArena::alloc` for details.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc<T: Send + 'a>(&self, value: T) -> &'h mut T {
+        let target: *mut T = self.arena().alloc(value);
+        // The chunks live as long as the herd
+        unsafe { &mut *target }
+    }
+    /// Allocate the specified copyable value in the member, living as long as the herd
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_copy<T: Copy + Send>(&self, value: T) -> &'h mut T {
+        let target: *mut T = self.arena().alloc_copy(value);
+        // The chunks live as long as the herd
+        unsafe { &mut *target }
+    }
+    /// Allocate a copy of the specified slice in the member, living as long as the herd
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_slice_copy<T: Copy + Send>(&self, src: &[T]) -> &'h mut [T] {
+        let target: *mut [T] = self.arena().alloc_slice_copy(src);
+        // The chunks live as long as the herd
+        unsafe { &mut *target }
+    }
+    /// Allocate a copy of the specified string in the member, living as long as the herd
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_str(&self, s: &str) -> &'h mut str {
+        let target: *mut str = self.arena().alloc_str(s);
+        // The chunks live as long as the herd
+        unsafe { &mut *target }
+    }
+}
+impl<'h, 'a> Drop for Member<'h, 'a> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(arena) = self.arena.take() {
+            self.herd.lock().push(arena);
+        }
+    }
+}
+impl<'h, 'a> Debug for Member<'h, 'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Member").field(self.arena()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use crate::test_util::DropCounted;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+    use std::thread;
+
+    #[derive(Copy, Clone)]
+    struct Node<'h> {
+        value: u32,
+        children: &'h [&'h Node<'h>],
+    }
+    impl<'h> Node<'h> {
+        fn sum(&self) -> u32 {
+            self.value + self.children.iter().map(|child| child.sum()).sum::<u32>()
+        }
+    }
+
+    fn build<'h>(member: &Member<'h, '_>, depth: u32) -> &'h Node<'h> {
+        let children: Vec<&'h Node<'h>> = (0..depth).map(|_| build(member, depth - 1)).collect();
+        member.alloc_copy(Node {
+            value: 1,
+            children: member.alloc_slice_copy(&children),
+        })
+    }
+
+    #[test]
+    fn parallel_tree() {
+        let herd = SendableHerd::new();
+        let barrier = Barrier::new(4);
+        let subtrees: Vec<&Node<'_>> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..4)
+                .map(|_| {
+                    let (herd, barrier) = (&herd, &barrier);
+                    scope.spawn(move || {
+                        let member = herd.get();
+                        // Every worker holds its own member at once
+                        barrier.wait();
+                        build(&member, 5)
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect()
+        });
+        // The siblings are spread across the members
+        assert_eq!(herd.idle_member_count(), 4);
+        let member = herd.get();
+        let root = member.alloc_copy(Node {
+            value: 1,
+            children: member.alloc_slice_copy(&subtrees),
+        });
+        drop(member);
+        // Each subtree has 1 + 5 + 5*4 + 5*4*3 + 5*4*3*2 + 5*4*3*2*1 nodes
+        assert_eq!(root.sum(), 1 + 4 * 326);
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn drops_members() {
+        let counter = AtomicUsize::new(0);
+        let herd = SendableHerd::new_bounded();
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                let (herd, counter) = (&herd, &counter);
+                scope.spawn(move || {
+                    let member = herd.get();
+                    for _ in 0..100 {
+                        member.alloc(DropCounted(counter));
+                    }
+                });
+            }
+        });
+        let name = herd.get().alloc_str("outlives the member");
+        assert_eq!(name, "outlives the member");
+        assert_eq!(counter.load(Ordering::Relaxed), 0);
+        drop(herd);
+        assert_eq!(counter.load(Ordering::Relaxed), 400);
+    }
+}
@@ -0,0 +1,148 @@
+//! A histogram of allocation sizes, enabled by the `size-histogram` feature.
+use std::cell::Cell;
+use std::fmt::{self, Display};
+
+use crate::DynamicArena;
+
+/// The number of buckets in a `SizeHistogram`
+///
+/// There's a bucket for each power of two up to 64 KiB, plus one for anything larger.
+pub const SIZE_BUCKETS: usize = 18;
+
+/// The bucket for allocations of the specified size
+#[inline]
+fn bucket(size: usize) -> usize {
+    // The number of bits needed for `size - 1` is the exponent of the next power of two
+    let bits = (usize::BITS - size.saturating_sub(1).leading_zeros()) as usize;
+    bits.min(SIZE_BUCKETS - 1)
+}
+
+/// The counters for each bucket
+pub(crate) struct SizeCounters {
+    buckets: [Cell<u64>; SIZE_BUCKETS],
+}
+impl Default for SizeCounters {
+    fn default() -> Self {
+        SizeCounters {
+            buckets: [(); SIZE_BUCKETS].map(|()| Cell::new(0)),
+        }
+    }
+}
+impl SizeCounters {
+    #[inline]
+    pub(crate) fn record(&self, size: usize) {
+        let counter = &self.buckets[bucket(size)];
+        counter.set(counter.get() + 1);
+    }
+    pub(crate) fn clear(&self) {
+        for counter in &self.buckets {
+            counter.set(0);
+        }
+    }
+}
+
+/// The distribution of allocation sizes in an arena, in power-of-two buckets
+///
+/// Bucket `i` counts the allocations of more than `2^(i-1)` bytes (and at most `2^i` bytes),
+/// except that the first bucket includes empty allocations
+/// and the last one includes everything larger than 64 KiB.
+/// The `Display` implementation prints a table of the non-empty buckets.
+///
+/// This is created by `DynamicArena::size_histogram`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SizeHistogram {
+    counts: [u64; SIZE_BUCKETS],
+}
+impl SizeHistogram {
+    /// The number of allocations in each bucket
+    #[inline]
+    pub fn counts(&self) -> [u64; SIZE_BUCKETS] {
+        self.counts
+    }
+    /// The largest allocation size in the specified bucket,
+    /// or `None` for the last bucket (which has no limit)
+    #[inline]
+    pub fn bucket_limit(index: usize) -> Option<usize> {
+        if index + 1 < SIZE_BUCKETS {
+            Some(1 << index)
+        } else {
+            None
+        }
+    }
+}
+impl Display for SizeHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total: u64 = self.counts.iter().sum();
+        for (index, &count) in self.counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            match SizeHistogram::bucket_limit(index) {
+                Some(limit) => write!(f, "<= {:>6} bytes", limit)?,
+                None => write!(f, " > {:>6} bytes", 1 << (SIZE_BUCKETS - 2))?,
+            }
+            writeln!(
+                f,
+                ": {:>10} ({:>5.1}%)",
+                count,
+                count as f64 * 100.0 / total as f64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// The distribution of allocation sizes
+    ///
+    /// Every allocation from the arena's chunks is counted,
+    /// including copies, slices and raw layouts (but not memory reused by `set_recycling`).
+    /// The counts are cumulative across resets, until `reset_peaks` is called.
+    pub fn size_histogram(&self) -> SizeHistogram {
+        SizeHistogram {
+            counts: self.size_counters.buckets.each_ref().map(Cell::get),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::alloc::Layout;
+
+    #[test]
+    fn buckets() {
+        assert_eq!(bucket(0), 0);
+        assert_eq!(bucket(1), 0);
+        assert_eq!(bucket(2), 1);
+        assert_eq!(bucket(3), 2);
+        assert_eq!(bucket(4), 2);
+        assert_eq!(bucket(5), 3);
+        assert_eq!(bucket(65536), 16);
+        assert_eq!(bucket(65537), 17);
+        assert_eq!(bucket(usize::MAX), 17);
+    }
+    #[test]
+    fn recorded() {
+        let mut arena = DynamicArena::new();
+        arena.alloc_copy(1u8);
+        arena.alloc(String::new());
+        arena.alloc_slice_fill_copy(1000, 0u8);
+        arena.alloc_str("abc");
+        arena.alloc_layout(Layout::from_size_align(100_000, 1).unwrap());
+        arena.reset();
+        let histogram = arena.size_histogram();
+        let mut expected = [0; SIZE_BUCKETS];
+        expected[0] = 1;
+        expected[bucket(std::mem::size_of::<String>())] += 1;
+        expected[10] += 1;
+        expected[2] += 1;
+        expected[17] += 1;
+        assert_eq!(histogram.counts(), expected);
+        let table = histogram.to_string();
+        assert!(table.contains("<=   1024 bytes:          1 ( 20.0%)"));
+        assert!(table.contains(" >  65536 bytes"));
+        arena.reset_peaks();
+        assert_eq!(arena.size_histogram().counts(), [0; SIZE_BUCKETS]);
+    }
+}
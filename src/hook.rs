@@ -0,0 +1,237 @@
+//! Observing the arena's slow-path events, with a programmatic hook.
+use std::cell::{Cell, RefCell};
+
+use crate::{DynamicArena, ItemBound};
+
+/// An event reported to the hook set by `DynamicArena::set_alloc_hook`
+///
+/// These are all slow-path events, so nothing is reported for ordinary allocations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AllocEvent {
+    /// The arena acquired a new chunk with the specified capacity (in bytes)
+    ChunkAllocated {
+        /// The capacity of the new chunk
+        size: usize,
+    },
+    /// A fallible allocation failed because the allocation limit would be exceeded
+    LimitExceeded {
+        /// The number of bytes that were requested
+        requested: usize,
+    },
+    /// The arena was reset
+    Reset {
+        /// The number of bytes that were allocated before the reset
+        bytes: usize,
+        /// The number of values that were dropped
+        items: usize,
+    },
+}
+
+/// The message for the debug assertion when the hook allocates from the arena it's observing
+const HOOK_ALLOCATED: &str = "The allocation hook must not allocate";
+
+/// The hook, type-erased
+pub(crate) struct AllocHook<'a> {
+    func: RefCell<Box<dyn FnMut(AllocEvent) + 'a>>,
+}
+/// The hook is only `Send` in a `Sendable` arena
+unsafe impl Send for AllocHook<'_> {}
+
+/// Notices when the arena acquires a new chunk, by watching its total capacity
+#[derive(Default)]
+pub(crate) struct ChunkWatcher {
+    capacity: Cell<usize>,
+}
+
+/// The usage of an arena before it's reset (or dropped)
+pub(crate) struct Usage {
+    pub(crate) bytes: usize,
+    pub(crate) items: usize,
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Remove the allocation hook, returning whether there was one
+    pub fn clear_alloc_hook(&mut self) -> bool {
+        self.alloc_hook.take().is_some()
+    }
+    fn set_alloc_hook_unchecked(&mut self, hook: Box<dyn FnMut(AllocEvent) + 'a>) {
+        self.sync_chunks();
+        self.alloc_hook = Some(AllocHook {
+            func: RefCell::new(hook),
+        });
+    }
+    /// Check whether a new chunk has been acquired since the last check
+    ///
+    /// This is only a comparison, unless something is observing the arena.
+    #[inline]
+    pub(crate) fn check_chunks(&self) {
+        #[cfg(feature = "registry")]
+        let registered = self.is_registered();
+        #[cfg(not(feature = "registry"))]
+        let registered = false;
+        if cfg!(feature = "tracing") || registered || self.alloc_hook.is_some() {
+            let capacity = self.handle.allocated_bytes();
+            let known = self.chunks.capacity.replace(capacity);
+            if capacity > known {
+                self.chunk_allocated(capacity - known);
+            }
+        }
+    }
+    /// Forget about the chunks that were freed (by resetting)
+    #[inline]
+    pub(crate) fn sync_chunks(&self) {
+        self.chunks.capacity.set(self.handle.allocated_bytes());
+    }
+    #[cold]
+    fn chunk_allocated(&self, size: usize) {
+        #[cfg(feature = "tracing")]
+        crate::trace::chunk_allocated(size, self.handle.allocated_bytes());
+        #[cfg(feature = "registry")]
+        self.publish_chunks();
+        self.notify(AllocEvent::ChunkAllocated { size });
+    }
+    #[cold]
+    pub(crate) fn limit_exceeded(&self, requested: usize, usage: usize, limit: Option<usize>) {
+        #[cfg(feature = "tracing")]
+        crate::trace::limit_exceeded(requested, usage, limit);
+        #[cfg(not(feature = "tracing"))]
+        let _ = (usage, limit);
+        self.notify(AllocEvent::LimitExceeded { requested });
+    }
+    /// The usage that's about to be reclaimed by resetting
+    pub(crate) fn usage(&mut self) -> Usage {
+        let adopted: usize = self
+            .adopted
+            .get_mut()
+            .iter()
+            .map(|adopted| adopted.allocated_bytes() - adopted.chunk_capacity())
+            .sum();
+        Usage {
+            bytes: self.used_bytes() + adopted,
+            items: self.items.len(),
+        }
+    }
+    /// Report a reset, which reclaimed the specified usage
+    pub(crate) fn notify_reset(&self, usage: Usage) {
+        self.sync_chunks();
+        #[cfg(feature = "registry")]
+        self.publish_reset();
+        #[cfg(feature = "tracing")]
+        crate::trace::reset(&usage);
+        self.notify(AllocEvent::Reset {
+            bytes: usage.bytes,
+            items: usage.items,
+        });
+    }
+    fn notify(&self, event: AllocEvent) {
+        if let Some(ref hook) = self.alloc_hook {
+            match hook.func.try_borrow_mut() {
+                Ok(mut func) => func(event),
+                Err(_) => debug_assert!(false, "{}", HOOK_ALLOCATED),
+            }
+        }
+    }
+    /// Check that the hook isn't running, since it must not allocate
+    #[cfg(debug_assertions)]
+    #[inline]
+    pub(crate) fn check_not_in_hook(&self) {
+        if let Some(ref hook) = self.alloc_hook {
+            assert!(hook.func.try_borrow_mut().is_ok(), "{}", HOOK_ALLOCATED);
+        }
+    }
+}
+impl<'a, S> DynamicArena<'a, S> {
+    /// Set a hook, which observes the arena's slow-path events (see `AllocEvent`).
+    ///
+    /// The hook is never invoked for ordinary allocations that fit in the current chunk,
+    /// so it doesn't slow down the fast path.
+    /// It replaces any previous hook.
+    /// Just like a value, the hook must satisfy the arena's `ItemBound`.
+    ///
+    /// The hook must not allocate from the arena it's observing.
+    /// Debug builds check every allocation while the hook is running,
+    /// while release builds silently drop any events it would cause.
+    #[inline]
+    pub fn set_alloc_hook<F: FnMut(AllocEvent) + 'a>(&mut self, hook: F)
+    where
+        S: ItemBound<F>,
+    {
+        self.set_alloc_hook_unchecked(Box::new(hook))
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn events() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut arena = DynamicArena::new();
+        let recorded = Rc::clone(&events);
+        arena.set_alloc_hook(Box::new(move |event| recorded.borrow_mut().push(event)));
+        arena.alloc(String::from("first"));
+        arena.alloc_copy(1u32);
+        assert!(matches!(
+            events.borrow()[..],
+            [AllocEvent::ChunkAllocated { .. }]
+        ));
+        arena.alloc_slice_fill_copy(100_000, 0u8);
+        assert_eq!(events.borrow().len(), 2);
+        let capacity = arena.as_bumpalo().allocated_bytes();
+        arena.as_bumpalo().set_allocation_limit(Some(capacity));
+        assert!(arena.try_alloc_str(&"x".repeat(1_000_000)).is_err());
+        arena.reset();
+        let events = events.borrow();
+        assert_eq!(
+            events[2],
+            AllocEvent::LimitExceeded {
+                requested: 1_000_000
+            }
+        );
+        match events[3] {
+            AllocEvent::Reset { bytes, items } => {
+                assert!(bytes >= 100_000);
+                assert_eq!(items, 1);
+            }
+            other => panic!("Unexpected event: {:?}", other),
+        }
+        assert_eq!(events.len(), 4);
+    }
+    #[test]
+    fn sendable_cleared() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut arena = DynamicArena::new_send();
+        arena.set_alloc_hook(Box::new(move |event| sender.send(event).unwrap()));
+        let arena = std::thread::spawn(move || {
+            arena.alloc(String::from("sent"));
+            arena
+        })
+        .join()
+        .unwrap();
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(AllocEvent::ChunkAllocated { .. })
+        ));
+        let mut arena = arena;
+        assert!(arena.clear_alloc_hook());
+        arena.reset();
+        assert!(receiver.try_recv().is_err());
+    }
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "The allocation hook must not allocate")]
+    fn allocating_hook() {
+        let mut arena = Box::new(DynamicArena::new());
+        let observed = Rc::new(Cell::new(std::ptr::null::<DynamicArena>()));
+        let target = Rc::clone(&observed);
+        // Even an allocation that fits in the current chunk is caught
+        arena.set_alloc_hook(move |_| {
+            unsafe { &*target.get() }.alloc_copy(1u8);
+        });
+        observed.set(&*arena);
+        arena.alloc_slice_fill_copy(100_000, 0u8);
+    }
+}
@@ -0,0 +1,180 @@
+//! Recording every allocation for assertions in tests, enabled by the `test-util` feature.
+use std::cell::{Cell, RefCell};
+use std::fmt::{self, Debug};
+use std::ops::{Deref, DerefMut};
+
+use crate::{DynamicArena, NonSend};
+
+/// A single allocation, as recorded by `InstrumentedArena`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AllocRecord {
+    type_name: Option<&'static str>,
+    bytes: usize,
+}
+impl AllocRecord {
+    /// The name of the allocated type (from `std::any::type_name`),
+    /// or `None` for allocations made through the untyped APIs (like `alloc_layout` and `alloc_str`)
+    #[inline]
+    pub fn type_name(&self) -> Option<&'static str> {
+        self.type_name
+    }
+    /// The number of bytes that were requested
+    #[inline]
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+/// The allocations and drop registrations since the events were last taken
+#[derive(Default)]
+pub(crate) struct Recorder {
+    records: RefCell<Vec<AllocRecord>>,
+    registered_drops: Cell<usize>,
+}
+impl Recorder {
+    #[inline]
+    pub(crate) fn record(&self, type_name: Option<&'static str>, bytes: usize) {
+        self.records
+            .borrow_mut()
+            .push(AllocRecord { type_name, bytes });
+    }
+    #[inline]
+    pub(crate) fn record_drop(&self) {
+        self.registered_drops.set(self.registered_drops.get() + 1);
+    }
+}
+
+/// An arena that records every allocation, for asserting on them in tests
+///
+/// This derefs to the wrapped `DynamicArena`, so it can be used anywhere an arena is expected.
+/// Allocations that reuse memory (from `dealloc_last` or the free lists) aren't recorded,
+/// since they don't request any new bytes.
+///
+/// ````
+/// use dynamic_arena::{DynamicArena, InstrumentedArena};
+/// let arena = InstrumentedArena::new(DynamicArena::new());
+/// arena.alloc(String::from("node"));
+/// arena.alloc_copy(5u32);
+/// arena.assert_allocations(2);
+/// arena.assert_registered_drops(1);
+/// ````
+pub struct InstrumentedArena<'a, S = NonSend> {
+    arena: DynamicArena<'a, S>,
+}
+impl<'a, S> InstrumentedArena<'a, S> {
+    /// Start recording the allocations of the specified arena
+    pub fn new(mut arena: DynamicArena<'a, S>) -> Self {
+        arena.recorder = Some(Box::default());
+        InstrumentedArena { arena }
+    }
+    /// Stop recording, returning the arena
+    pub fn into_inner(mut self) -> DynamicArena<'a, S> {
+        self.arena.recorder = None;
+        self.arena
+    }
+    #[inline]
+    fn recorder(&self) -> &Recorder {
+        self.arena.recorder.as_ref().unwrap()
+    }
+    /// Take the allocations that were recorded since the last call,
+    /// which also restarts the counts checked by the assertions
+    pub fn take_events(&self) -> Vec<AllocRecord> {
+        let recorder = self.recorder();
+        recorder.registered_drops.set(0);
+        recorder.records.take()
+    }
+    /// Assert that the specified number of allocations were recorded since the events were last taken
+    #[track_caller]
+    pub fn assert_allocations(&self, expected: usize) {
+        let records = self.recorder().records.borrow();
+        assert!(
+            records.len() == expected,
+            "Expected {} arena allocations, but there were {}: {:?}",
+            expected,
+            records.len(),
+            *records
+        );
+    }
+    /// Assert that the specified number of values were registered to be dropped
+    /// since the events were last taken
+    #[track_caller]
+    pub fn assert_registered_drops(&self, expected: usize) {
+        let actual = self.recorder().registered_drops.get();
+        assert!(
+            actual == expected,
+            "Expected {} values to be registered for drop, but there were {}",
+            expected,
+            actual
+        );
+    }
+}
+impl<'a, S> Deref for InstrumentedArena<'a, S> {
+    type Target = DynamicArena<'a, S>;
+    #[inline]
+    fn deref(&self) -> &DynamicArena<'a, S> {
+        &self.arena
+    }
+}
+impl<'a, S> DerefMut for InstrumentedArena<'a, S> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut DynamicArena<'a, S> {
+        &mut self.arena
+    }
+}
+impl<'a, S> Debug for InstrumentedArena<'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("InstrumentedArena")
+            .field(&self.arena)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::CollectIn;
+
+    fn lookup<'a>(arena: &'a DynamicArena<'_, crate::Sendable>, key: &str) -> &'a str {
+        match key {
+            "static" => "static",
+            _ => arena.alloc_str(key),
+        }
+    }
+
+    #[test]
+    fn records() {
+        let mut arena = InstrumentedArena::new(DynamicArena::new_send());
+        assert_eq!(lookup(&arena, "static"), "static");
+        arena.assert_allocations(0);
+        arena.alloc(String::from("node"));
+        arena.alloc_str("name");
+        let _: &[String] = (0..3).map(|i| i.to_string()).collect_in(&*arena);
+        arena.assert_registered_drops(2);
+        let events = arena.take_events();
+        assert_eq!(events[0].type_name(), Some("alloc::string::String"));
+        assert_eq!(
+            events[1],
+            AllocRecord {
+                type_name: None,
+                bytes: 4
+            }
+        );
+        arena.assert_allocations(0);
+        arena.assert_registered_drops(0);
+        arena.reset();
+        arena.alloc_copy(1u8);
+        arena.assert_allocations(1);
+        let mut arena = arena.into_inner();
+        arena.alloc_copy(2u8);
+        assert!(arena.recorder.is_none());
+        arena.reset();
+    }
+    #[test]
+    #[should_panic(expected = "Expected 1 arena allocations, but there were 2")]
+    fn failed_assertion() {
+        let arena = InstrumentedArena::new(DynamicArena::new());
+        arena.alloc_copy(1u32);
+        arena.alloc_copy(2u32);
+        arena.assert_allocations(1);
+    }
+}
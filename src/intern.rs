@@ -0,0 +1,381 @@
+//! Interners whose storage is backed by a `DynamicArena`.
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+use crate::{DynamicArena, ItemBound, NonSend};
+
+/// A compact identifier for a string interned in a `SymbolTable`.
+///
+/// Symbols are handed out densely starting at zero, in the order strings are first interned.
+/// This makes them suitable for serialization and as indices into side tables,
+/// as long as they're only ever resolved against the table that created them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+impl Symbol {
+    /// Create a symbol from its raw integer id
+    ///
+    /// This is the inverse of `Symbol::as_u32`,
+    /// and is intended for deserializing previously interned ids.
+    #[inline]
+    pub const fn from_u32(id: u32) -> Symbol {
+        Symbol(id)
+    }
+    /// The raw integer id of this symbol
+    #[inline]
+    pub const fn as_u32(self) -> u32 {
+        self.0
+    }
+    /// The id of this symbol as an index, for use with side tables
+    #[inline]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A symbol table mapping strings to dense `Symbol` ids.
+///
+/// The string data is allocated in the arena,
+/// so resolved strings are valid for the entire lifetime of the arena borrow.
+/// Interning the same string twice always returns the same `Symbol`.
+pub struct SymbolTable<'arena, 'a, S = NonSend> {
+    arena: &'arena DynamicArena<'a, S>,
+    ids: HashMap<&'arena str, Symbol>,
+    strings: Vec<&'arena str>,
+}
+impl<'arena, 'a, S> SymbolTable<'arena, 'a, S> {
+    /// Create an empty symbol table whose strings will be allocated in the specified arena
+    pub fn new(arena: &'arena DynamicArena<'a, S>) -> Self {
+        SymbolTable {
+            arena,
+            ids: HashMap::new(),
+            strings: Vec::new(),
+        }
+    }
+    /// Intern the specified string, returning its unique symbol
+    ///
+    /// The string is only copied into the arena the first time it's interned.
+    ///
+    /// ## Panics
+    /// If more than `u32::MAX` distinct strings have been interned.
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(&existing) = self.ids.get(s) {
+            return existing;
+        }
+        assert!(
+            self.strings.len() <= u32::MAX as usize,
+            "Too many symbols: {}",
+            self.strings.len()
+        );
+        let symbol = Symbol(self.strings.len() as u32);
+        let s: &'arena str = self.arena.alloc_str(s);
+        self.strings.push(s);
+        self.ids.insert(s, symbol);
+        symbol
+    }
+    /// Lookup the symbol for the specified string, without interning it
+    #[inline]
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.ids.get(s).copied()
+    }
+    /// Resolve the string corresponding to the specified symbol
+    ///
+    /// ## Panics
+    /// If the symbol wasn't created by this table.
+    #[inline]
+    pub fn resolve(&self, symbol: Symbol) -> &'arena str {
+        match self.strings.get(symbol.index()) {
+            Some(s) => s,
+            None => panic!("Unknown symbol: {:?}", symbol),
+        }
+    }
+    /// The number of distinct strings that have been interned
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+    /// Check if no strings have been interned
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+    /// Iterate over all the interned strings and their symbols, in order of their ids
+    #[inline]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (Symbol, &'arena str)> + '_ {
+        self.strings
+            .iter()
+            .enumerate()
+            .map(|(index, &s)| (Symbol(index as u32), s))
+    }
+}
+
+/// A reference to a deduplicated value, which is compared by address instead of by value.
+///
+/// When values are interned, equal values share the same allocation,
+/// so comparing their addresses is equivalent to (and much faster than) comparing the values.
+/// The `PartialEq`, `Eq`, `Hash`, `PartialOrd`, and `Ord` implementations all use the address
+/// (ignoring the length of slices and the vtable of trait objects),
+/// while `Deref` and `Debug` forward to the underlying value.
+///
+/// ## Mixing interners
+/// Comparisons are only meaningful between values from the same interner.
+/// Two equal values interned in different interners (or different arenas)
+/// have different addresses, so they will compare unequal.
+/// Likewise, the ordering is based on the address and has nothing to do with the value,
+/// so it is only useful for things like sorted maps, and is not stable across runs.
+#[repr(transparent)]
+pub struct Interned<'arena, T: ?Sized>(&'arena T);
+impl<'arena, T: ?Sized> Interned<'arena, T> {
+    /// Wrap a reference to an already deduplicated value
+    ///
+    /// This is the responsibility of the caller,
+    /// since comparisons are meaningless unless equal values have the same address.
+    #[inline]
+    pub const fn from_ref(value: &'arena T) -> Self {
+        Interned(value)
+    }
+    /// The underlying reference, valid for the lifetime of the arena
+    #[inline]
+    pub const fn get(self) -> &'arena T {
+        self.0
+    }
+    /// The address of the value, ignoring any pointer metadata
+    #[inline]
+    fn addr(self) -> *const () {
+        (self.0 as *const T).cast::<()>()
+    }
+}
+impl<'arena, T: ?Sized> Clone for Interned<'arena, T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'arena, T: ?Sized> Copy for Interned<'arena, T> {}
+impl<'arena, T: ?Sized> Deref for Interned<'arena, T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+impl<'arena, T: ?Sized> PartialEq for Interned<'arena, T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.addr() == other.addr()
+    }
+}
+impl<'arena, T: ?Sized> Eq for Interned<'arena, T> {}
+impl<'arena, T: ?Sized> Hash for Interned<'arena, T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr().hash(state)
+    }
+}
+impl<'arena, T: ?Sized> PartialOrd for Interned<'arena, T> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<'arena, T: ?Sized> Ord for Interned<'arena, T> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.addr().cmp(&other.addr())
+    }
+}
+impl<'arena, T: ?Sized + Debug> Debug for Interned<'arena, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        T::fmt(self.0, f)
+    }
+}
+
+/// A hash-consing interner, which deduplicates structurally equal `Copy` values.
+///
+/// Each distinct value is allocated in the arena exactly once (via `DynamicArena::alloc_copy`),
+/// so interning equal values is guaranteed to return the same address.
+/// Since the values are `Copy`, they're free to contain references to
+/// other values in the same arena (including previously interned ones).
+pub struct ValueInterner<'arena, 'a, T, S = NonSend> {
+    arena: &'arena DynamicArena<'a, S>,
+    values: HashSet<&'arena T>,
+}
+impl<'arena, 'a, T: Copy + Hash + Eq, S: ItemBound<T>> ValueInterner<'arena, 'a, T, S> {
+    /// Create an empty interner whose values will be allocated in the specified arena
+    pub fn new(arena: &'arena DynamicArena<'a, S>) -> Self {
+        ValueInterner {
+            arena,
+            values: HashSet::new(),
+        }
+    }
+    /// Intern the specified value,
+    /// returning the existing allocation if an equal value has already been interned.
+    pub fn intern(&mut self, value: T) -> &'arena T {
+        if let Some(&existing) = self.values.get(&value) {
+            return existing;
+        }
+        let allocated: &'arena T = self.arena.alloc_copy(value);
+        self.values.insert(allocated);
+        allocated
+    }
+    /// Intern the specified value, wrapping the result so it's compared by address
+    ///
+    /// See `Interned` for the caveats of comparing values from different interners.
+    #[inline]
+    pub fn intern_wrapped(&mut self, value: T) -> Interned<'arena, T> {
+        Interned(self.intern(value))
+    }
+    /// Lookup the existing allocation for the specified value, without interning it
+    #[inline]
+    pub fn get(&self, value: &T) -> Option<&'arena T> {
+        self.values.get(value).copied()
+    }
+    /// The number of distinct values that have been interned
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    /// Check if no values have been interned
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::ptr;
+
+    #[test]
+    fn symbols() {
+        let arena = DynamicArena::new();
+        let mut table = SymbolTable::new(&arena);
+        assert!(table.is_empty());
+        let foo = table.intern("foo");
+        let bar = table.intern("bar");
+        assert_eq!(foo.as_u32(), 0);
+        assert_eq!(bar.as_u32(), 1);
+        assert_eq!(table.intern(&String::from("foo")), foo);
+        assert_eq!(table.get("bar"), Some(bar));
+        assert_eq!(table.get("baz"), None);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.resolve(foo), "foo");
+        assert_eq!(table.resolve(Symbol::from_u32(1)), "bar");
+        assert_eq!(
+            table.iter().collect::<Vec<_>>(),
+            vec![(foo, "foo"), (bar, "bar")]
+        );
+    }
+    #[test]
+    fn resolve_outlives_table() {
+        let arena = DynamicArena::new_send();
+        let text = {
+            let mut table = SymbolTable::new(&arena);
+            let sym = table.intern("text");
+            table.resolve(sym)
+        };
+        assert_eq!(text, "text");
+    }
+    #[test]
+    #[should_panic(expected = "Unknown symbol")]
+    fn unknown_symbol() {
+        let arena = DynamicArena::new();
+        let table = SymbolTable::new(&arena);
+        table.resolve(Symbol::from_u32(7));
+    }
+    #[derive(Copy, Clone, PartialEq, Eq, Hash)]
+    enum Expr<'a> {
+        Const(u32),
+        Add(&'a Expr<'a>, &'a Expr<'a>),
+    }
+    #[test]
+    fn hash_consing() {
+        let arena = DynamicArena::new();
+        let mut interner = ValueInterner::new(&arena);
+        let one = interner.intern(Expr::Const(1));
+        let two = interner.intern(Expr::Const(2));
+        let first = interner.intern(Expr::Add(one, two));
+        let (lhs, rhs) = (
+            interner.intern(Expr::Const(1)),
+            interner.intern(Expr::Const(2)),
+        );
+        let second = interner.intern(Expr::Add(lhs, rhs));
+        assert!(ptr::eq(first, second));
+        assert!(!ptr::eq(interner.intern(Expr::Add(two, one)), first));
+        assert!(ptr::eq(interner.get(&Expr::Const(2)).unwrap(), two));
+        assert!(interner.get(&Expr::Const(3)).is_none());
+        assert_eq!(interner.len(), 4);
+    }
+    /// A value whose hash only has a handful of distinct values
+    #[derive(Copy, Clone, PartialEq, Eq)]
+    struct Colliding(u32);
+    impl Hash for Colliding {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            state.write_u32(self.0 % 7);
+        }
+    }
+    #[test]
+    fn colliding_hashes() {
+        let arena = DynamicArena::new();
+        let mut interner = ValueInterner::new(&arena);
+        let first = (0..2000)
+            .map(|i| interner.intern(Colliding(i)) as *const Colliding)
+            .collect::<Vec<_>>();
+        for (i, &expected) in first.iter().enumerate() {
+            let actual = interner.intern(Colliding(i as u32));
+            assert_eq!(actual.0, i as u32);
+            assert!(ptr::eq(actual, expected));
+        }
+        assert_eq!(interner.len(), 2000);
+    }
+    #[test]
+    fn stress() {
+        const COUNT: u64 = 1_000_000;
+        let arena = DynamicArena::new();
+        let mut interner = ValueInterner::new(&arena);
+        for i in 0..COUNT {
+            interner.intern((i, i * 3));
+        }
+        for i in (0..COUNT).step_by(997) {
+            let expected = interner.get(&(i, i * 3)).unwrap();
+            assert!(ptr::eq(interner.intern((i, i * 3)), expected));
+        }
+        assert_eq!(interner.len(), COUNT as usize);
+    }
+    #[test]
+    fn interned_identity() {
+        let arena = DynamicArena::new();
+        let other_arena = DynamicArena::new();
+        let mut interner = ValueInterner::new(&arena);
+        let mut other = ValueInterner::new(&other_arena);
+        let first = interner.intern_wrapped(String::from("tiny").len());
+        let second = interner.intern_wrapped(4);
+        assert_eq!(first, second);
+        assert_eq!(*first, 4);
+        assert_eq!(format!("{:?}", first), "4");
+        let hash = |value: Interned<usize>| {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(first), hash(second));
+        // Equal values from different interners are distinct
+        let foreign = other.intern_wrapped(4);
+        assert_ne!(first, foreign);
+        assert_ne!(first.cmp(&foreign), Ordering::Equal);
+        let copied = first;
+        assert!(ptr::eq(copied.get(), first.get()));
+    }
+    #[test]
+    fn interned_not_copy() {
+        let value = String::from("not copy");
+        let first = Interned::from_ref(&value);
+        let second = first;
+        assert_eq!(first, second);
+        assert_eq!(&**first, "not copy");
+    }
+}
@@ -0,0 +1,99 @@
+//! Auditing the values leaked by `alloc_unchecked`, enabled by the `leak-audit` feature.
+use std::cell::RefCell;
+use std::mem;
+
+use crate::DynamicArena;
+
+/// The values of a single type that were leaked by `alloc_unchecked`,
+/// as reported by `DynamicArena::leaked_report`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LeakedType {
+    type_name: &'static str,
+    size: usize,
+    count: usize,
+}
+impl LeakedType {
+    /// The name of the type (from `std::any::type_name`)
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+    /// The size of each value, in bytes
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+    /// The number of values that were leaked
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// The leaked types, in the order they were first leaked
+///
+/// Leaks should be rare, so the types are just searched linearly.
+#[derive(Default)]
+pub(crate) struct LeakAudit {
+    leaked: RefCell<Vec<LeakedType>>,
+}
+impl LeakAudit {
+    #[cold]
+    pub(crate) fn record<T>(&self) {
+        let type_name = std::any::type_name::<T>();
+        let mut leaked = self.leaked.borrow_mut();
+        match leaked.iter_mut().find(|leak| leak.type_name == type_name) {
+            Some(leak) => leak.count += 1,
+            None => leaked.push(LeakedType {
+                type_name,
+                size: mem::size_of::<T>(),
+                count: 1,
+            }),
+        }
+    }
+    /// Report the leaks when the arena is dropped
+    #[cfg(feature = "tracing")]
+    pub(crate) fn trace(&self) {
+        for leak in self.leaked.borrow().iter() {
+            crate::trace::leaked(leak.type_name, leak.size, leak.count);
+        }
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// The values that need to be dropped, but were leaked by `alloc_unchecked`
+    ///
+    /// Intentional leaks (from `alloc_leak` or `alloc_unchecked_intentional`) aren't recorded.
+    /// The report covers the whole life of the arena (it isn't cleared by resetting).
+    /// With the `tracing` feature, any leaks are also reported when the arena is dropped.
+    pub fn leaked_report(&self) -> Vec<LeakedType> {
+        self.leaks.leaked.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recorded() {
+        let mut arena = DynamicArena::new();
+        unsafe {
+            arena.alloc_unchecked(String::new());
+            arena.alloc_unchecked(5u32);
+            arena.alloc_unchecked(Vec::<u8>::new());
+            arena.alloc_unchecked(String::new());
+            arena.alloc_unchecked_intentional(String::new());
+        }
+        arena.alloc_leak(Vec::<u8>::new());
+        arena.alloc(String::from("dropped"));
+        arena.reset();
+        let report = arena.leaked_report();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].type_name(), std::any::type_name::<String>());
+        assert_eq!(report[0].size(), mem::size_of::<String>());
+        assert_eq!(report[0].count(), 2);
+        assert_eq!(report[1].type_name(), std::any::type_name::<Vec<u8>>());
+        assert_eq!(report[1].count(), 1);
+    }
+}
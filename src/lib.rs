@@ -1,19 +1,247 @@
 //! Implements dynamically typed arenas, where any type of item can be allocated.
+//!
+//! ## Drop order
+//! Registered values are dropped in reverse registration order (last in, first out),
+//! just like local variables, so a value can safely use anything that was allocated before it
+//! from its destructor. This applies to dropping, resetting and rolling back an arena,
+//! along with the other ways of dropping early (like `drop_region` and `drop_some`).
+//! Phases of teardown (see `DynamicArena::alloc_in_phase`) still run in ascending order,
+//! with the values in each phase dropped in reverse.
+//!
+//! **This is a change from earlier versions, which dropped values in registration order.**
+//! The only exceptions are `drop_parallel`, which doesn't guarantee any order,
+//! and arenas built with `DynamicArenaBuilder::address_ordered_teardown`.
+//!
+//! The drop list itself lives in the arena, as a linked list of entries starting from the newest,
+//! so registering a value never touches the heap.
+//! Each entry is a few words, allocated along with its value,
+//! which counts towards the arena's byte usage (and allocation limit).
+//! Values of the same type that are allocated back-to-back share a single entry,
+//! so homogeneous bursts (like building one kind of node at a time) barely use any space for it.
+//!
+//! If a destructor panics, the remaining values are still dropped,
+//! and then the first panic is resumed (any later ones are discarded).
+//! Dropping an arena while the thread is already unwinding never aborts.
+//!
+//! Destructors must not register new values with the arena that's dropping them
+//! (like by calling `alloc` through a stashed pointer to the arena).
+//! This always panics, and the new value is dropped immediately instead of being registered.
+//!
+//! ## Without drop tracking
+//! Arenas that only ever hold values without destructors can compile out the drop list entirely
+//! with the `no-drop-tracking` feature, which shrinks the arena and removes the teardown code.
+//! Registering anything that needs to be dropped (with `alloc`, `dynamic_drop`, `collect_in` and so on)
+//! is then a compile-time error, while values without destructors work exactly the same.
+//! Most of the crate's own tests allocate values with destructors,
+//! so `cargo test --features no-drop-tracking` skips them, and runs this feature's own test instead.
+//!
+//! ## Chunk memory
+//! Every chunk is allocated by bumpalo, which always uses the global allocator
+//! (its `Bump` doesn't have an allocator parameter), so chunk memory can't come from a custom source.
+//! Chunk traffic can still be accounted for and bounded per arena:
+//! `set_alloc_hook` reports each new chunk (as `AllocEvent::ChunkAllocated`) and each reset,
+//! `set_allocation_limit` caps the total size of the chunks,
+//! and `DynamicArenaBuilder::contiguous` confines an arena to a single pre-allocated chunk.
+//!
+//! ## Tracing
+//! With the `tracing` feature, arenas emit events with the target `dynamic_arena`.
+//! Their names and fields are stable:
+//! - `chunk_allocated` (debug), with the `chunk_bytes` of the new chunk
+//!   and the arena's total `capacity_bytes`
+//! - `limit_exceeded` (warn), with the `requested_bytes`, `usage_bytes` and `limit_bytes`
+//! - `reset` (debug), with the `reclaimed_bytes` and `dropped_items`
+//! - `dropped` (debug), with the `allocated_bytes`, `dropped_items`
+//!   and the `drop_micros` spent running the destructors
+//! - `leaked` (warn), with the `type_name`, `size_bytes` and `count` of the values
+//!   leaked by `alloc_unchecked`, when an arena is dropped (with the `leak-audit` feature)
+//! - `drop_profiled` (debug), with the `type_name`, `dropped_items` and `drop_micros`
+//!   of each type dropped when an arena is dropped (with the `drop-profile` feature)
 #![deny(missing_docs)]
+#![cfg_attr(feature = "nightly-allocator", feature(allocator_api))]
 use std::alloc::Layout;
-use std::cell::RefCell;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::convert::TryFrom;
+use std::fmt::{self, Debug};
 use std::marker::PhantomData;
-use std::mem;
+use std::mem::{self, MaybeUninit};
 use std::os::raw::c_void;
 use std::ptr::{self, NonNull};
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
 
 use bumpalo::Bump;
 
+mod absorb;
+mod adaptive;
+mod affinity;
+mod align;
+#[cfg(any(feature = "nightly-allocator", feature = "allocator-api2"))]
+mod allocator;
+mod attribution;
+mod bitset;
+mod budget;
+mod builder;
+mod bump;
+mod capacity;
+mod chain;
+mod child;
+mod collect;
+mod contains;
+mod context;
+mod convert;
+#[cfg(feature = "nightly")]
+mod dangle;
+mod dealloc;
+mod decommit;
+mod drop_safe;
+#[cfg(not(feature = "no-drop-tracking"))]
+mod droplist;
+#[cfg(feature = "debug-dump")]
+mod dump;
+#[cfg(debug_assertions)]
+mod duplicate;
+mod exclusive;
+mod finalize;
+mod fork;
+mod frame;
+mod freeze;
+mod guard;
+mod herd;
+#[cfg(feature = "size-histogram")]
+mod histogram;
+mod hook;
+#[cfg(feature = "test-util")]
+mod instrument;
+mod intern;
+#[cfg(feature = "leak-audit")]
+mod leaks;
+mod limit;
+mod matrix;
+mod memo;
+mod metadata;
+mod offset;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod phase;
+mod pool;
+#[cfg(feature = "drop-profile")]
+mod profile;
+mod raw;
+mod recycle;
+mod region;
+#[cfg(feature = "registry")]
+pub mod registry;
+mod scope;
+mod scratch;
+#[cfg(feature = "http-pool")]
+mod service;
+#[cfg(feature = "track-callers")]
+mod sites;
+mod slice;
+mod small;
+mod stats;
+mod string;
+mod sync;
+mod tagged;
+mod teardown;
+#[cfg(test)]
+pub(crate) mod test_util;
+#[cfg(feature = "tracing")]
+mod trace;
+mod type_map;
+#[cfg(feature = "type-stats")]
+mod type_stats;
+#[cfg(feature = "no-drop-tracking")]
+mod untracked;
+mod verify;
+
+use self::droplist::DropList;
+#[cfg(feature = "no-drop-tracking")]
+use self::untracked as droplist;
+
+pub use self::affinity::FinalizerQueue;
+pub use self::attribution::RegionGuard;
+pub use self::bitset::ArenaBitSet;
+pub use self::builder::DynamicArenaBuilder;
+pub use self::chain::ChainedArena;
+pub use self::child::ChildArena;
+pub use self::collect::{CollectIn, FromIteratorIn};
+pub use self::drop_safe::DropSafe;
+pub use self::exclusive::ExclusiveArena;
+pub use self::fork::ArenaFork;
+pub use self::frame::FrameArenas;
+pub use self::freeze::{FrozenArena, SharedHandle};
+pub use self::guard::{ArenaReadGuard, ResetBlocked};
+pub use self::herd::{Member, SendableHerd};
+#[cfg(feature = "size-histogram")]
+pub use self::histogram::{SizeHistogram, SIZE_BUCKETS};
+pub use self::hook::AllocEvent;
+#[cfg(feature = "test-util")]
+pub use self::instrument::{AllocRecord, InstrumentedArena};
+pub use self::intern::{Interned, Symbol, SymbolTable, ValueInterner};
+#[cfg(feature = "leak-audit")]
+pub use self::leaks::LeakedType;
+pub use self::limit::AllocError;
+pub use self::matrix::Arena2D;
+pub use self::memo::ArenaMemo;
+pub use self::offset::ArenaOffset;
+pub use self::phase::DEFAULT_DROP_PHASE;
+pub use self::pool::{ArenaPool, PooledArena};
+#[cfg(feature = "drop-profile")]
+pub use self::profile::{DropProfile, DroppedType};
+pub use self::scope::Checkpoint;
+pub use self::scratch::with_scratch;
+#[cfg(feature = "http-pool")]
+pub use self::service::{ArenaFuture, ArenaLayer, ArenaService, RequestArena};
+#[cfg(feature = "track-callers")]
+pub use self::sites::AllocationSite;
+pub use self::small::SmallDynamicArena;
+pub use self::stats::ArenaStats;
+pub use self::string::InteriorNulError;
+pub use self::sync::DynamicSyncArena;
+pub use self::tagged::TaggedPtr;
+pub use self::type_map::ArenaTypeMap;
+#[cfg(feature = "type-stats")]
+pub use self::type_stats::TypeStat;
+pub use self::verify::VerifyError;
+
 /// Marker trait that indicates whether or a `DynamicArena` may be sent across threads
+///
+/// Along with `ItemBound`, this can be implemented for custom markers
+/// (creating the arena with `DynamicArenaBuilder::marker`).
 pub trait SendAbility: Sized {
     /// Create an arena corresponding to this type of thread-safety
     fn create_arena<'a>() -> DynamicArena<'a, Self>;
+    /// Create an arena corresponding to this type of thread-safety,
+    /// with pre-allocated capacity for the specified number of items and bytes.
+    ///
+    /// The default implementation uses `DynamicArenaBuilder::marker` with the specified capacities,
+    /// so markers that configure anything else in `create_arena` should override it as well.
+    #[inline]
+    fn create_arena_with_capacity<'a>(
+        item_capacity: usize,
+        byte_capacity: usize,
+    ) -> DynamicArena<'a, Self> {
+        DynamicArenaBuilder::new()
+            .marker::<Self>()
+            .item_capacity(item_capacity)
+            .byte_capacity(byte_capacity)
+            .build()
+    }
 }
+/// The bound that a `SendAbility` marker places on the items in its arenas
+///
+/// Every method that puts a value in the arena requires `S: ItemBound<T>`,
+/// so implementing this for a custom marker is enough to use it with all of them.
+/// For example, a marker could require `Send + Sync` for every item,
+/// or a trait that's local to another crate.
+///
+/// ## Safety
+/// If the marker is `Send`, then the arena is `Send` as well,
+/// so every type it accepts must also be `Send`.
+pub unsafe trait ItemBound<T: ?Sized> {}
+unsafe impl<T: ?Sized + Send> ItemBound<T> for Sendable {}
+unsafe impl<T: ?Sized> ItemBound<T> for NonSend {}
 /// Marker type that indicates you expect everything in the `DynamicArena` to be `Send`
 ///
 /// Although this prevents you from allocating non-`Send` types in the arena,
@@ -21,6 +249,7 @@ pub trait SendAbility: Sized {
 /// We can't safely implement `Send` for `DynamicArena` without this bound,
 /// since you could otherwise place a `Rc` in the arena, send it across threads,
 /// and then proceed to drop the arena and mutate the reference count.
+#[derive(Debug)]
 pub struct Sendable {
     _marker: (),
 }
@@ -29,11 +258,19 @@ impl SendAbility for Sendable {
     fn create_arena<'a>() -> DynamicArena<'a, Self> {
         DynamicArena::new_send()
     }
+    #[inline]
+    fn create_arena_with_capacity<'a>(
+        item_capacity: usize,
+        byte_capacity: usize,
+    ) -> DynamicArena<'a, Self> {
+        DynamicArena::with_capacity(item_capacity, byte_capacity)
+    }
 }
 /// Marker type that indiates everything in the `DynamicArena` isn't nesicarrily `Send`.
 ///
 /// This prevents you from `Send`ing the arena itself across threads,
 /// as described in the `Sendable` docs.
+#[derive(Debug)]
 pub struct NonSend {
     _marker: std::rc::Rc<()>,
 }
@@ -42,19 +279,199 @@ impl SendAbility for NonSend {
     fn create_arena<'a>() -> DynamicArena<'a, Self> {
         DynamicArena::new_bounded()
     }
+    #[inline]
+    fn create_arena_with_capacity<'a>(
+        item_capacity: usize,
+        byte_capacity: usize,
+    ) -> DynamicArena<'a, Self> {
+        DynamicArena::with_capacity(item_capacity, byte_capacity)
+    }
 }
 
+/// The drop entry for a run of contiguous values of the same type (usually just one)
+///
+/// Values allocated back-to-back with the same drop function extend the previous run,
+/// instead of each needing their own entry (see `DropList::extend_head`).
 struct DynamicArenaItem {
     drop: unsafe fn(*mut c_void),
+    /// The address of the first value, which is the most recently registered
     value: *mut c_void,
+    /// The number of values in the run
+    count: u32,
+    /// The distance between the values, which is the size of each one
+    ///
+    /// Values bigger than `u32::MAX` saturate, and never form runs.
+    stride: u32,
+    /// The region this item belongs to, if `tagged` (see `DynamicArena::drop_region`)
+    ///
+    /// This isn't an `Option`, to keep the item small.
+    tag: u32,
+    tagged: bool,
+    /// The phase of teardown this item is dropped in (see `DynamicArena::alloc_in_phase`)
+    phase: u8,
+    /// The registration order of the oldest value, which is assigned by the drop list
+    ///
+    /// The other values in the run follow it, so the most recent one is `serial + count - 1`.
+    /// This never goes backwards, so checkpoints can refer to it (see `Checkpoint`).
+    serial: u64,
+}
+impl DynamicArenaItem {
+    /// The entry for a single value of the specified size
+    #[inline]
+    fn single(
+        drop: unsafe fn(*mut c_void),
+        value: *mut c_void,
+        size: usize,
+        tag: Option<u32>,
+        phase: u8,
+    ) -> Self {
+        DynamicArenaItem {
+            drop,
+            value,
+            count: 1,
+            stride: u32::try_from(size).unwrap_or(u32::MAX),
+            tag: tag.unwrap_or(0),
+            tagged: tag.is_some(),
+            phase,
+            serial: 0,
+        }
+    }
+    /// Whether this item belongs to the region with the specified tag
+    #[inline]
+    fn has_tag(&self, tag: u32) -> bool {
+        self.tagged && self.tag == tag
+    }
+    /// Whether the values are zero-sized, so they don't occupy any memory (see `DynamicArena::verify`)
+    #[inline]
+    fn zero_sized(&self) -> bool {
+        self.stride == 0
+    }
+    /// The number of values in the run
+    #[inline]
+    fn len(&self) -> usize {
+        self.count as usize
+    }
+    /// The serial of the next value after this run
+    #[inline]
+    #[cfg_attr(feature = "no-drop-tracking", allow(dead_code))]
+    fn serial_end(&self) -> u64 {
+        self.serial + u64::from(self.count)
+    }
+    /// The address of each value, starting from the most recent one
+    #[inline]
+    fn addresses(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len()).map(move |index| self.value_at(index) as usize)
+    }
+    /// The pointer to the value at the specified index, derived from the run's own pointer
+    #[inline]
+    fn value_at(&self, index: usize) -> *mut c_void {
+        self.value
+            .cast::<u8>()
+            .wrapping_add(index * self.stride as usize)
+            .cast()
+    }
+    /// The index of the value at the specified address, if it's part of this run
+    #[inline]
+    fn position(&self, address: usize) -> Option<usize> {
+        let offset = address.checked_sub(self.value as usize)?;
+        let index = match self.stride {
+            0 if offset == 0 => 0,
+            0 => return None,
+            stride if offset % stride as usize == 0 => offset / stride as usize,
+            _ => return None,
+        };
+        (index < self.len()).then_some(index)
+    }
+    /// Split off the most recent `count` values into their own entry
+    #[inline]
+    #[cfg_attr(feature = "no-drop-tracking", allow(dead_code))]
+    fn split_newest(&mut self, count: usize) -> DynamicArenaItem {
+        debug_assert!(count > 0 && count < self.len());
+        let newest = DynamicArenaItem {
+            count: count as u32,
+            serial: self.serial_end() - count as u64,
+            ..*self
+        };
+        self.value = self.value_at(count);
+        self.count -= count as u32;
+        newest
+    }
+}
+/// Drops the rest of a run if one of its values panics, discarding any later panics
+struct DropRemaining<'i>(&'i mut DynamicArenaItem);
+impl DropRemaining<'_> {
+    /// Drop the next value, after removing it from the run
+    #[inline]
+    unsafe fn drop_next(&mut self) {
+        let value = self.0.value;
+        self.0.value = self.0.value_at(1);
+        self.0.count -= 1;
+        (self.0.drop)(value)
+    }
+}
+impl Drop for DropRemaining<'_> {
+    fn drop(&mut self) {
+        // Each iteration removes one value, and later panics are discarded (like `DropPanic`)
+        for _ in 0..self.0.count {
+            let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| unsafe {
+                self.drop_next()
+            }));
+        }
+    }
 }
 impl Drop for DynamicArenaItem {
     #[inline]
     fn drop(&mut self) {
-        unsafe { (self.drop)(self.value) }
+        if self.count == 1 {
+            unsafe { (self.drop)(self.value) }
+        } else {
+            let mut remaining = DropRemaining(self);
+            while remaining.0.count > 0 {
+                unsafe { remaining.drop_next() }
+            }
+        }
     }
 }
 unsafe impl Send for DynamicArenaItem {}
+/// The panic message when a value is registered while the arena is dropping its values
+///
+/// Destructors must never allocate values that need to be dropped in the arena that's dropping them
+/// (like through a stashed pointer to the arena), since the drop list is in use.
+/// This is always detected, and the value is dropped immediately instead of being registered.
+const TEARDOWN_REGISTRATION: &str =
+    "Values can't be registered with an arena while it's dropping its values";
+/// Drops a value of the specified type, given its type-erased address
+///
+/// Each type gets its own copy of this shim, whose address is stored in the drop list.
+/// This avoids calling `ptr::drop_in_place` through a transmuted function pointer.
+///
+/// The type must have thin pointers (which includes every `Sized` type).
+pub(crate) unsafe fn drop_glue<T: ?Sized>(value: *mut c_void) {
+    // The pointers have the same size, so the address is the entire pointer
+    ptr::drop_in_place(mem::transmute_copy::<*mut c_void, *mut T>(&value))
+}
+/// Drops an unsized value, given the address of its fat pointer
+unsafe fn drop_unsized<T: ?Sized>(fat: *mut c_void) {
+    ptr::drop_in_place(*fat.cast::<*mut T>())
+}
+/// Where the drop entry for a newly registered value goes
+#[derive(Copy, Clone)]
+enum EntrySlot {
+    /// A new entry is allocated separately
+    Allocate,
+    /// A new entry is written to memory that was allocated along with the value
+    At(NonNull<u8>),
+    /// The value extends the most recent run, since it's right before it (see `DropList::precedes_head`)
+    ExtendRun,
+}
+/// Drops a value in place if registering its drop function panics
+struct RegistrationGuard<T: ?Sized>(*mut T);
+impl<T: ?Sized> Drop for RegistrationGuard<T> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.0) }
+    }
+}
 
 /// An alias for an arena allocator which requires that everything is `Send + 'a`.
 pub type DynamicSendArena<'a> = DynamicArena<'a, Sendable>;
@@ -104,6 +521,8 @@ pub type DynamicSendArena<'a> = DynamicArena<'a, Sendable>;
 /// Then, when someone needs to arena-allocate the struct they can use
 /// the same arena to allocate the `String` and `Vec<u32>` first,
 /// before they proceed to allocate the copyable struct.
+// The layout can't depend on the marker, so it can be changed in place (see `with_marker`)
+#[repr(C)]
 pub struct DynamicArena<'a, S = NonSend> {
     /// The underlying arena, where we request that they allocate arbitrary bytes.
     handle: Bump,
@@ -114,7 +533,82 @@ pub struct DynamicArena<'a, S = NonSend> {
     /// and each item could invoke completely different code for completely different types.
     /// This is only needed for types that need to be dropped (as determined by `mem::needs_drop`),
     /// and types that need need to be dropped don't need to be added.
-    items: RefCell<Vec<DynamicArenaItem>>,
+    /// The entries are allocated in the arena itself, right after their values.
+    items: DropList,
+    /// Empty bump allocators returned by dropped child arenas,
+    /// which are reused by the next child (see `DynamicArena::child`).
+    spare_bumps: RefCell<Vec<Bump>>,
+    /// The most spare bump allocators that are kept (see `DynamicArena::set_spare_children_limit`).
+    spare_limit: usize,
+    /// The per-cycle allocation budget, if one has been configured
+    /// (see `DynamicArena::set_cycle_budget`).
+    budget: Option<Box<self::budget::CycleBudget<'a>>>,
+    /// The capacity learned from previous cycles, if adaptive capacity is enabled
+    /// (see `DynamicArena::set_adaptive_capacity`).
+    learned: Option<self::adaptive::LearnedCapacity>,
+    /// The number of active read guards, created the first time one is requested
+    /// (see `DynamicArena::read_guard`).
+    readers: OnceCell<Arc<AtomicUsize>>,
+    /// The memory given back by `DynamicArena::reset_and_decommit`
+    released: self::decommit::ReleasedMemory,
+    /// The free lists of deallocated memory, if recycling is enabled
+    /// (see `DynamicArena::set_recycling`).
+    recycler: Option<Box<self::recycle::FreeLists>>,
+    /// The finalizers that are waiting for a context
+    /// (see `DynamicArena::register_finalizer`).
+    finalizers: RefCell<Vec<self::context::ContextualFinalizer>>,
+    /// The chunks of committed forks, which are kept until the arena is reset
+    /// (see `DynamicArena::fork`).
+    adopted: RefCell<Vec<Bump>>,
+    /// Whether there's an outstanding fork, which blocks allocation (see `DynamicArena::fork`).
+    forked: Cell<bool>,
+    /// The high-water marks (see `DynamicArena::stats`).
+    peaks: self::stats::Peaks,
+    /// Whether teardown drops values in address order (see `DynamicArenaBuilder::address_ordered_teardown`).
+    address_ordered: bool,
+    /// The alignment of every allocation, at least (see `DynamicArenaBuilder::min_align`).
+    min_align: usize,
+    /// The allocations of each type (see `DynamicArena::type_stats`).
+    #[cfg(feature = "type-stats")]
+    type_stats: self::type_stats::TypeStats,
+    /// The distribution of allocation sizes (see `DynamicArena::size_histogram`).
+    #[cfg(feature = "size-histogram")]
+    size_counters: self::histogram::SizeCounters,
+    /// The allocations from each call site (see `DynamicArena::allocation_sites`).
+    #[cfg(feature = "track-callers")]
+    allocation_sites: self::sites::AllocationSites,
+    /// Observes the slow-path events (see `DynamicArena::set_alloc_hook`).
+    alloc_hook: Option<self::hook::AllocHook<'a>>,
+    /// Notices new chunks, so they can be reported.
+    chunks: self::hook::ChunkWatcher,
+    /// The number of allocations since the last reset (see `DynamicArena::len`).
+    allocations: Cell<usize>,
+    /// The bytes attributed to each region (see `DynamicArena::push_region`).
+    regions: self::attribution::RegionAccounting,
+    /// The metadata attached to allocations (see `DynamicArena::alloc_with_meta`).
+    metadata: self::metadata::MetadataTable,
+    /// The values of the type map (see `DynamicArena::type_map`).
+    type_slots: self::type_map::TypeSlots,
+    /// Invalidates the handles returned by `alloc_shared` when values are dropped.
+    handle_epoch: self::freeze::HandleEpoch,
+    /// The values leaked by `alloc_unchecked` (see `DynamicArena::leaked_report`).
+    #[cfg(feature = "leak-audit")]
+    leaks: self::leaks::LeakAudit,
+    /// The arena's entry in the registry, if it's named (see `DynamicArenaBuilder::name`).
+    #[cfg(feature = "registry")]
+    registration: Option<self::registry::Registration>,
+    /// The values that can be printed (see `DynamicArena::debug_dump`).
+    #[cfg(feature = "debug-dump")]
+    dump: self::dump::DebugDump,
+    /// The names of the types registered to be dropped (see `DynamicArena::drop_profiled`).
+    #[cfg(feature = "drop-profile")]
+    drop_names: self::profile::DropNames,
+    /// Records every allocation, if the arena is instrumented (see `InstrumentedArena`).
+    #[cfg(feature = "test-util")]
+    recorder: Option<Box<self::instrument::Recorder>>,
+    /// The addresses registered to be dropped, for catching duplicates in debug builds.
+    #[cfg(debug_assertions)]
+    registered: self::duplicate::RegisteredAddresses,
     /// This is the magic `PhantomData` combination to have proper lifetime invariance.
     ///
     /// Otherwise the lifetime would be 'variant',
@@ -140,23 +634,82 @@ impl<'a, S> DynamicArena<'a, S> {
     ///
     /// NOTE: The "item" capacity excludes `Copy` references that
     /// don't need to be dropped.
+    /// Their drop entries live in the arena, so this adds space for them to the first chunk.
     pub fn with_capacity(item_capacity: usize, byte_capacity: usize) -> Self {
+        DynamicArenaBuilder::new()
+            .item_capacity(item_capacity)
+            .byte_capacity(byte_capacity)
+            .with_marker()
+            .build()
+    }
+    /// Try to create an arena with pre-allocated capacity for the specified number of items
+    /// and bytes, returning an error instead of aborting if the capacity can't be allocated.
+    pub fn try_with_capacity(
+        item_capacity: usize,
+        byte_capacity: usize,
+    ) -> Result<Self, AllocError> {
+        DynamicArenaBuilder::new()
+            .item_capacity(item_capacity)
+            .byte_capacity(byte_capacity)
+            .with_marker()
+            .try_build()
+    }
+    #[inline]
+    pub(crate) fn from_parts(handle: Bump) -> Self {
         DynamicArena {
-            handle: Bump::with_capacity(byte_capacity),
-            items: RefCell::new(Vec::with_capacity(item_capacity)),
+            handle,
+            items: Default::default(),
+            spare_bumps: RefCell::new(Vec::new()),
+            spare_limit: self::child::DEFAULT_SPARE_LIMIT,
+            budget: None,
+            learned: None,
+            readers: OnceCell::new(),
+            released: Default::default(),
+            recycler: None,
+            finalizers: RefCell::new(Vec::new()),
+            adopted: RefCell::new(Vec::new()),
+            forked: Cell::new(false),
+            peaks: Default::default(),
+            address_ordered: false,
+            min_align: 1,
+            #[cfg(feature = "type-stats")]
+            type_stats: Default::default(),
+            #[cfg(feature = "size-histogram")]
+            size_counters: Default::default(),
+            #[cfg(feature = "track-callers")]
+            allocation_sites: Default::default(),
+            alloc_hook: None,
+            chunks: Default::default(),
+            allocations: Cell::new(0),
+            regions: Default::default(),
+            metadata: Default::default(),
+            type_slots: Default::default(),
+            handle_epoch: Default::default(),
+            #[cfg(feature = "leak-audit")]
+            leaks: Default::default(),
+            #[cfg(feature = "registry")]
+            registration: None,
+            #[cfg(feature = "debug-dump")]
+            dump: Default::default(),
+            #[cfg(feature = "drop-profile")]
+            drop_names: Default::default(),
+            #[cfg(feature = "test-util")]
+            recorder: None,
+            #[cfg(debug_assertions)]
+            registered: Default::default(),
             marker: PhantomData,
             send: PhantomData,
         }
     }
-    /// Allocate the specified value in this arena,
-    /// returning a reference which will be valid for the lifetime of the entire arena.
-    ///
-    /// The bound on the item requires that `T: Copy`
-    /// to ensure there's no drop function that needs to be invoked.
+    /// Allocate a copyable value, shared by both versions of `alloc_copy`
     #[inline]
     #[allow(clippy::mut_from_ref)]
-    pub fn alloc_copy<T: Copy + Send>(&self, value: T) -> &mut T {
-        unsafe { self.alloc_unchecked(value) }
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub(crate) fn alloc_copy_value<T: Copy>(&self, value: T) -> &mut T {
+        let target = unsafe { self.alloc_value(value) };
+        #[cfg(feature = "debug-dump")]
+        self.dump.record_opaque::<T>(target);
+        target
     }
     /// Allocate the specified value in this arena,
     /// without calling its `Drop` function.
@@ -167,25 +720,251 @@ impl<'a, S> DynamicArena<'a, S> {
     /// ## Safety
     /// Technically, this function is safe to use.
     /// However, it leaks memory unconditionally (without calling Drop).
+    /// Prefer `alloc_leak` for deliberate leaks, which is the safe equivalent.
+    ///
+    /// Since the value is never dropped, it doesn't need to be `Send` (even in a `Sendable` arena).
+    ///
+    /// With the `leak-audit` feature, values that need to be dropped are recorded
+    /// in the `leaked_report`. Use `alloc_leak` (or `alloc_unchecked_intentional`) for deliberate leaks.
     #[inline]
     #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
     pub unsafe fn alloc_unchecked<T>(&self, value: T) -> &mut T {
-        let ptr = self.alloc_layout(Layout::new::<T>()).as_ptr().cast::<T>();
+        #[cfg(feature = "leak-audit")]
+        if mem::needs_drop::<T>() {
+            self.leaks.record::<T>();
+        }
+        self.alloc_unchecked_intentional(value)
+    }
+    /// Allocate the specified value in this arena, deliberately leaking it.
+    ///
+    /// This is the same as `alloc_unchecked`,
+    /// except that it's never recorded by the `leak-audit` feature.
+    ///
+    /// ## Safety
+    /// See `alloc_unchecked`.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub unsafe fn alloc_unchecked_intentional<T>(&self, value: T) -> &mut T {
+        let target = self.alloc_value(value);
+        #[cfg(feature = "track-callers")]
+        self.allocation_sites
+            .mark_unchecked(std::panic::Location::caller());
+        #[cfg(feature = "debug-dump")]
+        self.dump.record_opaque::<T>(target);
+        target
+    }
+    /// Allocate the specified value in this arena, intentionally never running its destructor.
+    ///
+    /// Just like `Box::leak`, this is safe since leaking is always safe in Rust.
+    /// The arena's memory is still reclaimed when it's dropped (or reset),
+    /// but anything the value owns (like the heap memory of a `String`) is leaked.
+    ///
+    /// Since the value is never dropped, it doesn't need to be `Copy`, `Send` or outlive `'a`.
+    /// With the `leak-audit` feature, these leaks aren't recorded in the `leaked_report`,
+    /// which only covers the accidental leaks from `alloc_unchecked`.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_leak<T>(&self, value: T) -> &mut T {
+        unsafe { self.alloc_unchecked_intentional(value) }
+    }
+    /// Allocate the specified value without registering its drop function
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub(crate) unsafe fn alloc_value<T>(&self, value: T) -> &mut T {
+        &mut *self.alloc_value_raw(value)
+    }
+    /// Like `alloc_value`, but without ever forming a reference to the value
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub(crate) unsafe fn alloc_value_raw<T>(&self, value: T) -> *mut T {
+        let ptr = self
+            .alloc_layout_as(Layout::new::<T>(), Some(std::any::type_name::<T>()))
+            .as_ptr()
+            .cast::<T>();
         ptr.write(value);
-        &mut *ptr
+        ptr
+    }
+    /// Allocate the specified value and register its drop function,
+    /// placing its drop entry right before it so they only take a single allocation
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub(crate) unsafe fn alloc_registered<T>(
+        &self,
+        value: T,
+        tag: Option<u32>,
+        phase: u8,
+    ) -> *mut T {
+        if !mem::needs_drop::<T>() {
+            return self.alloc_value_raw(value);
+        }
+        let type_name = Some(std::any::type_name::<T>());
+        if self
+            .items
+            .head_matches(drop_glue::<T>, mem::size_of::<T>(), tag, phase)
+        {
+            let target = self
+                .handle
+                .alloc_layout(self.aligned_layout(Layout::new::<T>()))
+                .as_ptr()
+                .cast::<T>();
+            if self.items.precedes_head(target.cast()) {
+                self.record_bytes_as(type_name, mem::size_of::<T>());
+                target.write(value);
+                self.link_drop(target, EntrySlot::ExtendRun, tag, phase);
+                return target;
+            }
+            // Something else was allocated after the run, so the bytes are given back for a new entry
+            drop(bumpalo::collections::Vec::from_raw_parts_in(
+                target,
+                0,
+                1,
+                &self.handle,
+            ));
+        }
+        let combined = Layout::new::<T>().extend(DropList::NODE_LAYOUT);
+        let (layout, offset) = match combined {
+            // Recycled blocks are sized for separate values and entries
+            Ok(combined) if self.recycler.is_none() => combined,
+            _ => {
+                let target = self.alloc_value_raw(value);
+                self.register_drop(target, tag, phase);
+                return target;
+            }
+        };
+        // Like the separately allocated entries, the entry isn't counted as part of the value
+        let block = self.alloc_block(layout, type_name, mem::size_of::<T>());
+        let target = block.as_ptr().cast::<T>();
+        target.write(value);
+        let node = NonNull::new_unchecked(block.as_ptr().add(offset));
+        self.link_drop(target, EntrySlot::At(node), tag, phase);
+        target
+    }
+    /// Try to allocate the specified value along with its drop entry, returning it back on failure
+    ///
+    /// Unlike `alloc_registered`, the entry never needs a separate allocation,
+    /// so nothing is registered unless both of them fit.
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub(crate) unsafe fn try_alloc_registered<T>(&self, value: T) -> Result<*mut T, T> {
+        let type_name = Some(std::any::type_name::<T>());
+        let (layout, offset) = if mem::needs_drop::<T>() {
+            match Layout::new::<T>().extend(DropList::NODE_LAYOUT) {
+                Ok(combined) => combined,
+                Err(_) => return Err(value),
+            }
+        } else {
+            (Layout::new::<T>(), 0)
+        };
+        let block = match self.try_alloc_block(layout, type_name, mem::size_of::<T>()) {
+            Some(block) => block,
+            None => return Err(value),
+        };
+        let target = block.as_ptr().cast::<T>();
+        target.write(value);
+        if mem::needs_drop::<T>() {
+            let node = NonNull::new_unchecked(block.as_ptr().add(offset));
+            self.link_drop(target, EntrySlot::At(node), None, DEFAULT_DROP_PHASE);
+        }
+        Ok(target)
+    }
+    /// Allocate a copy of the specified string in this arena.
+    ///
+    /// Strings never need to be dropped,
+    /// so this is available regardless of the arena's `SendAbility`.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_str(&self, s: &str) -> &mut str {
+        unsafe {
+            let ptr = self.alloc_layout(Layout::for_value(s)).as_ptr();
+            ptr::copy_nonoverlapping(s.as_ptr(), ptr, s.len());
+            std::str::from_utf8_unchecked_mut(std::slice::from_raw_parts_mut(ptr, s.len()))
+        }
     }
     /// Allocate space for an object with the specified layout
     ///
-    /// The returned pointer points at uninitialized memory
+    /// The returned pointer points at uninitialized memory.
+    /// This is safe (just like [Bump::alloc_layout]), since only the use of the memory is unsafe.
+    /// Nothing is registered to be dropped.
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_layout(&self, layout: Layout) -> NonNull<u8> {
+        unsafe { self.alloc_layout_as(layout, None) }
+    }
+    /// Allocate uninitialized bytes with the specified layout, as a slice
     ///
-    /// ## Safety
-    /// Technically, only the use of the memory is unsafe.
+    /// The slice has the layout's size and starts at its alignment,
+    /// so it can be initialized without any `unsafe` code.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_layout_slice(&self, layout: Layout) -> &mut [MaybeUninit<u8>] {
+        let ptr = self.alloc_layout(layout).cast::<MaybeUninit<u8>>();
+        // Uninitialized bytes don't need to be initialized
+        unsafe { std::slice::from_raw_parts_mut(ptr.as_ptr(), layout.size()) }
+    }
+    /// Allocate space for an object with the specified layout
     ///
-    /// It would theoretically be possible to mark this function safe,
-    /// just like [Bump::alloc_layout].
+    /// ## Safety
+    /// This is always safe, since it's the same as `alloc_layout`.
+    /// It only exists for callers that relied on `alloc_layout` being an `unsafe fn`.
+    #[deprecated(since = "0.1.7", note = "`alloc_layout` is safe now")]
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub unsafe fn alloc_layout_unchecked(&self, layout: Layout) -> NonNull<u8> {
+        self.alloc_layout(layout)
+    }
+    /// Allocate space for the specified layout,
+    /// accounting for it as the specified type (if it's known)
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub(crate) unsafe fn alloc_layout_as(
+        &self,
+        layout: Layout,
+        type_name: Option<&'static str>,
+    ) -> NonNull<u8> {
+        self.alloc_block(layout, type_name, layout.size())
+    }
+    /// Allocate space for the specified layout,
+    /// only accounting for the specified number of bytes (excluding any drop entries)
     #[inline]
-    pub unsafe fn alloc_layout(&self, layout: Layout) -> NonNull<u8> {
-        self.handle.alloc_layout(layout)
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    unsafe fn alloc_block(
+        &self,
+        layout: Layout,
+        type_name: Option<&'static str>,
+        bytes: usize,
+    ) -> NonNull<u8> {
+        let layout = self.aligned_layout(layout);
+        if let Some(block) = self.recycled_layout(layout) {
+            self.count_allocation();
+            return block;
+        }
+        let ptr = self.handle.alloc_layout(layout);
+        self.record_bytes_as(type_name, bytes);
+        ptr
+    }
+    /// Like `alloc_block`, but returning `None` if a new chunk can't be allocated
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub(crate) unsafe fn try_alloc_block(
+        &self,
+        layout: Layout,
+        type_name: Option<&'static str>,
+        bytes: usize,
+    ) -> Option<NonNull<u8>> {
+        let layout = self.aligned_layout(layout);
+        if let Some(block) = self.recycled_layout(layout) {
+            self.count_allocation();
+            return Some(block);
+        }
+        let ptr = self.handle.try_alloc_layout(layout).ok()?;
+        self.record_bytes_as(type_name, bytes);
+        Some(ptr)
     }
     /// Dynamically drop the specified value,
     /// invoking the drop function when the arena is dropped.
@@ -199,15 +978,92 @@ impl<'a, S> DynamicArena<'a, S> {
     /// Normally these invariants are statically checked by the `alloc` method,
     /// which ensures that the memory is owned and all pointers
     /// would be valid for the lifetime of the entire arena.
+    ///
+    /// If registering the value panics (like if the drop list is already borrowed),
+    /// the value is dropped immediately before the panic propagates, so it's never leaked.
+    ///
+    /// In debug builds, registering a value that's already registered panics
+    /// instead of dropping it twice. The original registration is kept.
+    ///
+    /// The value can be unsized (like a slice or trait object allocated with `alloc_layout`).
+    /// Since the drop list only holds thin pointers,
+    /// the fat pointer is stored in the arena alongside the value.
     #[inline]
-    pub unsafe fn dynamic_drop<T>(&self, value: *mut T) {
+    pub unsafe fn dynamic_drop<T: ?Sized>(&self, value: *mut T) {
+        self.register_drop(value, None, DEFAULT_DROP_PHASE);
+    }
+    /// Register the drop function for the specified value,
+    /// with an optional region tag and the phase it's dropped in
+    #[inline]
+    pub(crate) unsafe fn register_drop<T: ?Sized>(
+        &self,
+        value: *mut T,
+        tag: Option<u32>,
+        phase: u8,
+    ) {
         if mem::needs_drop::<T>() {
-            self.items.borrow_mut().push(DynamicArenaItem {
-                drop: mem::transmute::<unsafe fn(*mut T), unsafe fn(*mut c_void)>(
-                    ptr::drop_in_place::<T>,
-                ),
-                value: value as *mut c_void,
-            })
+            self.link_drop(value, EntrySlot::Allocate, tag, phase);
+        }
+    }
+    /// Register the drop function for the specified value, writing its entry to the specified memory
+    ///
+    /// ## Safety
+    /// This has the same requirements as `dynamic_drop`,
+    /// and the memory must have `DropList::NODE_LAYOUT` and stay valid until the value is dropped.
+    #[inline]
+    pub(crate) unsafe fn register_drop_at<T>(&self, value: *mut T, node: NonNull<u8>) {
+        self.link_drop(value, EntrySlot::At(node), None, DEFAULT_DROP_PHASE);
+    }
+    /// Link the drop entry for the specified value into the drop list
+    #[inline]
+    unsafe fn link_drop<T: ?Sized>(
+        &self,
+        value: *mut T,
+        slot: EntrySlot,
+        tag: Option<u32>,
+        phase: u8,
+    ) {
+        #[cfg(feature = "no-drop-tracking")]
+        self::untracked::assert_no_drop::<T>();
+        let size = mem::size_of_val(&*value);
+        let zero_sized = size == 0;
+        // A duplicate is rejected before the guard, since the value is still registered
+        #[cfg(debug_assertions)]
+        self.registered
+            .check::<T>(value as *mut c_void as usize, zero_sized, &self.items);
+        // If anything panics before the value is registered, it's dropped instead of leaked
+        let guard = RegistrationGuard(value);
+        let (drop_fn, erased): (unsafe fn(*mut c_void), _) =
+            if mem::size_of::<*mut T>() == mem::size_of::<*mut c_void>() {
+                (drop_glue::<T>, value as *mut c_void)
+            } else {
+                let fat = self.handle.alloc(value) as *mut *mut T;
+                (drop_unsized::<T>, fat as *mut c_void)
+            };
+        #[cfg(feature = "drop-profile")]
+        self.drop_names.record::<T>(drop_fn);
+        if self.items.is_dropping() {
+            panic!("{}", TEARDOWN_REGISTRATION);
+        }
+        let item = || DynamicArenaItem::single(drop_fn, erased, size, tag, phase);
+        let len = match slot {
+            EntrySlot::Allocate => {
+                let node = self
+                    .recycled_layout(DropList::NODE_LAYOUT)
+                    .unwrap_or_else(|| self.handle.alloc_layout(DropList::NODE_LAYOUT));
+                self.items.push(node, item())
+            }
+            EntrySlot::At(node) => self.items.push(node, item()),
+            EntrySlot::ExtendRun => self.items.extend_head(erased),
+        };
+        mem::forget(guard);
+        self.record_peak_items(len);
+        #[cfg(feature = "registry")]
+        self.publish_items(len);
+        self.record_item();
+        #[cfg(feature = "test-util")]
+        if let Some(ref recorder) = self.recorder {
+            recorder.record_drop();
         }
     }
     /// Retrieve the underlying [bump allocator](bumpalo::Bump) for this arena
@@ -215,6 +1071,128 @@ impl<'a, S> DynamicArena<'a, S> {
     pub fn as_bumpalo(&self) -> &'_ bumpalo::Bump {
         &self.handle
     }
+    /// The number of bytes used in the arena's chunks
+    ///
+    /// This excludes the unused part of the current chunk,
+    /// but includes any space wasted at the end of previous chunks.
+    #[inline]
+    pub(crate) fn used_bytes(&self) -> usize {
+        self.handle.allocated_bytes() - self.handle.chunk_capacity()
+    }
+    /// The number of allocations made since the arena was created (or last reset)
+    ///
+    /// This counts allocations rather than live values,
+    /// so it includes `Copy` values, slices, strings and raw layouts,
+    /// along with values that were already dropped by rolling back.
+    /// Methods that allocate several pieces (like `alloc_strs`) count each of them.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.allocations.get()
+    }
+    /// Check whether nothing has been allocated since the arena was created (or last reset)
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    #[inline]
+    pub(crate) fn count_allocation(&self) {
+        self.check_not_forked();
+        #[cfg(debug_assertions)]
+        self.check_not_in_hook();
+        self.allocations.set(self.allocations.get() + 1);
+    }
+    /// Drop everything in this arena, leaving it empty but keeping its memory for reuse.
+    ///
+    /// This runs all the registered drop functions, then resets the underlying bump allocator.
+    /// The largest chunk of memory is retained (as with [Bump::reset]),
+    /// along with the capacity of the drop list,
+    /// so the arena is "warm" and can be reused without asking the allocator for more memory.
+    ///
+    /// Since this requires `&mut self`,
+    /// it's statically impossible for any references into the arena to be alive.
+    pub fn reset(&mut self) {
+        let usage = self.usage();
+        self.sample_peaks();
+        self.learn_capacity();
+        self.release_contents();
+        self.adopted.get_mut().clear();
+        self.handle.reset();
+        self.allocations.set(0);
+        self.reset_cycle();
+        self.apply_learned_capacity();
+        self.notify_reset(usage);
+    }
+    /// Drop everything in the arena and forget about its addresses,
+    /// so its chunks can be reset (or otherwise reused) without leaving anything dangling
+    pub(crate) fn release_contents(&mut self) {
+        self.discard_finalizers();
+        self.run_drops();
+        self.clear_free_lists();
+        self.handle_epoch.invalidate();
+        self.regions.clear();
+        self.metadata.clear();
+        self.type_slots.clear();
+        #[cfg(feature = "debug-dump")]
+        self.dump.clear();
+    }
+    /// Clear the list of registered drop functions without running any of them.
+    ///
+    /// This intentionally leaks everything owned by the values in the arena
+    /// (like the heap memory of a `String`), although the arena's own memory is still freed.
+    /// It's useful for short-lived programs that would otherwise spend their shutdown
+    /// running millions of trivial destructors.
+    ///
+    /// This is safe, since leaking is always safe in Rust.
+    /// Values allocated after this call are dropped as usual.
+    pub fn forget_all(&mut self) {
+        // The entries are just plain data in the arena, so they can be forgotten
+        self.items.forget_all();
+    }
+    /// Run all of the registered drop functions, clearing the list
+    #[inline]
+    fn run_drops(&mut self) {
+        self.truncate_drops(0);
+    }
+    /// Run the drop functions registered after the first `len` items,
+    /// removing them from the list
+    ///
+    /// The drops run one phase at a time (see `alloc_in_phase`),
+    /// and in reverse registration order within each phase (unless they're address-ordered).
+    ///
+    /// This is panic-safe, since each item is removed from the list before it's dropped.
+    /// If any of the drops panic, the rest still run, and then the first panic is resumed.
+    ///
+    /// The list stays locked while the drops run, so destructors that try to register
+    /// more values with the arena panic instead of corrupting it (see `TEARDOWN_REGISTRATION`).
+    #[inline]
+    fn truncate_drops(&mut self, len: usize) {
+        let count = match self.items.len().checked_sub(len) {
+            Some(count) if count > 0 => count,
+            _ => return,
+        };
+        self.handle_epoch.invalidate();
+        let items = &self.items;
+        let lock = items.lock();
+        let mut first_panic = self::teardown::DropPanic::default();
+        if self.address_ordered {
+            first_panic.drop_all(self::phase::sorted_by_address(items.split_off(len)));
+        } else if items
+            .iter()
+            .take(count)
+            .all(|item| item.phase == DEFAULT_DROP_PHASE)
+        {
+            // The most recent entries are first, so they can be dropped straight off the list
+            while let Some(item) = items.pop_at_most(items.len() - len) {
+                first_panic.drop_item(item);
+            }
+        } else {
+            first_panic.drop_all(self::phase::sorted_by_phase(items.split_off(len)));
+        }
+        drop(lock);
+        #[cfg(feature = "registry")]
+        self.publish_items(len);
+        first_panic.resume();
+    }
 }
 impl<'a> DynamicArena<'a, Sendable> {
     /// Create a new empty arena, bounded by the inferred lifetime for this type `'a`
@@ -222,28 +1200,7 @@ impl<'a> DynamicArena<'a, Sendable> {
     /// Since this arena has been marked `Sendable`,
     /// all items in the arena need to implement `Send`.
     pub fn new_send() -> Self {
-        DynamicArena {
-            handle: Bump::new(),
-            items: RefCell::new(Vec::new()),
-            marker: PhantomData,
-            send: PhantomData,
-        }
-    }
-    /// Allocate the specified value in this arena,
-    /// returning a reference which will be valid for the lifetime of the entire arena.
-    ///
-    /// The bound on this item requires that `T: 'a`
-    /// to ensure the drop function is safe to invoke.
-    /// Additionally, since the arena is `Sendable`,
-    /// the bound on the item also requires that `T: Send`.
-    #[inline]
-    #[allow(clippy::mut_from_ref)]
-    pub fn alloc<T: Send + 'a>(&self, value: T) -> &mut T {
-        unsafe {
-            let target = self.alloc_unchecked(value);
-            self.dynamic_drop(target);
-            target
-        }
+        DynamicArena::builder().send().build()
     }
 }
 impl<'a> DynamicArena<'a, NonSend> {
@@ -252,27 +1209,59 @@ impl<'a> DynamicArena<'a, NonSend> {
     /// Since this arena has been marked `NonSend`,
     /// the items in the arena don't necessarily need to implement `Send`.
     pub fn new_bounded() -> Self {
-        DynamicArena {
-            handle: Bump::new(),
-            items: RefCell::new(Vec::new()),
-            marker: PhantomData,
-            send: PhantomData,
-        }
+        DynamicArena::builder().build()
     }
+}
+impl<'a, S> DynamicArena<'a, S> {
     /// Allocate the specified value in this arena,
     /// returning a reference which will be valid for the lifetime of the entire arena.
     ///
     /// The bound on this item requires that `T: 'a`
     /// to ensure the drop function is safe to invoke.
+    /// Additionally, the item must satisfy the arena's `ItemBound`,
+    /// which requires that `T: Send` if the arena is `Sendable`.
     #[inline]
     #[allow(clippy::mut_from_ref)]
-    pub fn alloc<T: 'a>(&self, value: T) -> &mut T {
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc<T: 'a>(&self, value: T) -> &mut T
+    where
+        S: ItemBound<T>,
+    {
         unsafe {
-            let target = self.alloc_unchecked(value);
-            self.dynamic_drop(target);
-            target
+            // The returned reference is derived from the registered pointer, not the other way around
+            let target = self.alloc_registered(value, None, DEFAULT_DROP_PHASE);
+            #[cfg(feature = "debug-dump")]
+            self.dump.record_opaque::<T>(target);
+            &mut *target
         }
     }
+    /// Allocate the specified value in this arena,
+    /// returning a reference which will be valid for the lifetime of the entire arena.
+    ///
+    /// The bound on the item requires that `T: Copy`
+    /// to ensure there's no drop function that needs to be invoked.
+    /// Just like `alloc`, the item must satisfy the arena's `ItemBound`.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_copy<T: Copy>(&self, value: T) -> &mut T
+    where
+        S: ItemBound<T>,
+    {
+        self.alloc_copy_value(value)
+    }
+}
+impl<S> DynamicArena<'static, S> {
+    /// Leak the entire arena, so that references into it are valid forever.
+    ///
+    /// Nothing in the arena is ever dropped, and its memory is never freed.
+    /// This is a statement that the arena lives for the rest of the program,
+    /// and the returned reference can still be used to allocate more values.
+    /// Just like `Box::leak`, this is safe.
+    #[inline]
+    pub fn leak(self) -> &'static DynamicArena<'static, S> {
+        Box::leak(Box::new(self))
+    }
 }
 impl<'a, S: SendAbility> Default for DynamicArena<'a, S> {
     #[inline]
@@ -280,30 +1269,80 @@ impl<'a, S: SendAbility> Default for DynamicArena<'a, S> {
         S::create_arena()
     }
 }
+impl<'a, S: SendAbility> DynamicArena<'a, S> {
+    /// Create an arena with pre-allocated capacity, for generic code over `S: SendAbility`
+    ///
+    /// This is just like `Default`, but it uses `SendAbility::create_arena_with_capacity`.
+    #[inline]
+    pub fn default_with_capacity(item_capacity: usize, byte_capacity: usize) -> Self {
+        S::create_arena_with_capacity(item_capacity, byte_capacity)
+    }
+}
 unsafe impl<'a, S: SendAbility + Send> Send for DynamicArena<'a, S> {}
 impl<'a, S> Drop for DynamicArena<'a, S> {
     #[inline]
     fn drop(&mut self) {
+        #[cfg(feature = "tracing")]
+        let (usage, start) = (self.usage(), std::time::Instant::now());
         // Items must be dropped before the arena
-        self.items.get_mut().clear();
+        self.discard_finalizers();
+        #[cfg(all(feature = "tracing", feature = "drop-profile"))]
+        self::trace::drop_profiled(&self.run_profiled_drops());
+        self.run_drops();
+        self.type_slots.clear();
+        #[cfg(feature = "tracing")]
+        self::trace::dropped(&usage, start);
+        #[cfg(all(feature = "tracing", feature = "leak-audit"))]
+        self.leaks.trace();
+    }
+}
+/// A summary of the arena's usage, since its contents are type-erased
+///
+/// This never allocates, and it doesn't panic if the arena is being mutated.
+impl<'a, S> Debug for DynamicArena<'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let marker = std::any::type_name::<S>();
+        let marker = marker.rsplit("::").next().unwrap_or(marker);
+        let adopted: usize = self
+            .adopted
+            .borrow()
+            .iter()
+            .map(|adopted| adopted.allocated_bytes() - adopted.chunk_capacity())
+            .sum();
+        let chunks = self
+            .adopted
+            .borrow()
+            .iter()
+            .map(self::stats::chunk_count)
+            .sum::<usize>()
+            + self::stats::chunk_count(&self.handle);
+        let mut debug = f.debug_struct("DynamicArena");
+        debug
+            .field("marker", &format_args!("{}", marker))
+            .field("allocated_bytes", &(self.used_bytes() + adopted))
+            .field("chunk_count", &chunks);
+        if self.items.is_dropping() {
+            debug.field("registered_drop_count", &format_args!("<locked>"));
+        } else {
+            debug.field("registered_drop_count", &self.items.len());
+        }
+        debug.finish()
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use crate::test_util::DropCounted;
     use std::cell::Cell;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::cell::RefCell;
 
+    #[cfg(not(feature = "no-drop-tracking"))]
     const EXPECTED_DROP_COUNT: u32 = 4787;
     const EXPECTED_DEPTHS: &[u32] = &[5, 27, 43];
 
-    struct DropCounted<'a>(&'a Cell<u32>);
-    impl<'a> Drop for DropCounted<'a> {
-        fn drop(&mut self) {
-            let old_count = self.0.get();
-            self.0.set(old_count + 1);
-        }
-    }
     #[derive(Copy, Clone)]
     pub struct SelfReferential<'a>(u32, Option<&'a SelfReferential<'a>>);
     impl<'a> SelfReferential<'a> {
@@ -337,6 +1376,31 @@ mod test {
         for _ in 0..5 {
             verify_copyable(do_copyable(&arena));
         }
+        arena.verify().unwrap();
+    }
+    /// A copyable value that isn't `Send`, since it contains a raw pointer
+    #[derive(Copy, Clone)]
+    struct RawSpan {
+        start: *const u8,
+        len: usize,
+    }
+    #[test]
+    fn copyable_non_send() {
+        let arena = DynamicArena::new();
+        let text = arena.alloc_str("raw pointers");
+        let span = arena.alloc_copy(RawSpan {
+            start: text.as_ptr(),
+            len: 3,
+        });
+        // Shared references to a `Cell` aren't `Send` either
+        let counter = Cell::new(7u32);
+        let snapshot = arena.alloc_copy(&counter);
+        snapshot.set(8);
+        assert_eq!(
+            unsafe { std::slice::from_raw_parts(span.start, span.len) },
+            b"raw"
+        );
+        assert_eq!((arena.pending_drop_count(), counter.get()), (0, 8));
     }
     #[test]
     fn self_referential() {
@@ -344,7 +1408,9 @@ mod test {
         for _ in 0..5 {
             verify_self_referential(do_self_referential(&arena));
         }
+        arena.verify().unwrap();
     }
+    #[cfg(not(feature = "no-drop-tracking"))]
     #[test]
     fn drop_counted() {
         let cell = Box::new(Cell::new(0));
@@ -353,9 +1419,11 @@ mod test {
             do_drop_counted(&arena, &cell);
             assert_eq!(cell.get(), 0);
         }
+        arena.verify().unwrap();
         drop(arena);
         assert_eq!(cell.get(), EXPECTED_DROP_COUNT);
     }
+    #[cfg(not(feature = "no-drop-tracking"))]
     #[test]
     fn mixed() {
         let cell = Cell::new(0);
@@ -368,10 +1436,295 @@ mod test {
             }
             assert_eq!(cell.get(), 0);
         }
+        arena.verify().unwrap();
         drop(arena);
         assert_eq!(cell.get(), EXPECTED_DROP_COUNT);
     }
-    fn do_copyable<'a, S>(arena: &'a DynamicArena<S>) -> Vec<&'a u32> {
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn runs() {
+        let cell = Cell::new(0);
+        let mut arena = DynamicArena::new_bounded();
+        do_drop_counted(&arena, &cell);
+        // The values are allocated back-to-back, so there's only a new entry for each chunk
+        // (Miri doesn't guarantee that pointers to the same function are equal)
+        if !cfg!(miri) {
+            assert!(arena.items.iter().count() < 16);
+        }
+        assert_eq!(arena.pending_drop_count(), EXPECTED_DROP_COUNT as usize);
+        let checkpoint = arena.checkpoint();
+        let later: Vec<*mut DropCounted<_>> = (0..10)
+            .map(|_| arena.alloc(DropCounted(&cell)) as *mut _)
+            .collect();
+        assert!(unsafe { arena.dealloc_last(later[9]) });
+        // Deallocating from the middle of a run splits it
+        assert!(!unsafe { arena.dealloc_last(later[4]) });
+        assert_eq!(cell.get(), 2);
+        arena.rollback_to(checkpoint);
+        assert_eq!(cell.get(), 10);
+        arena.verify().unwrap();
+        drop(arena);
+        assert_eq!(cell.get(), EXPECTED_DROP_COUNT + 10);
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn reset() {
+        let cell = Cell::new(0);
+        let mut arena = DynamicArena::new_bounded();
+        do_drop_counted(&arena, &cell);
+        let allocated = arena.as_bumpalo().allocated_bytes();
+        arena.verify().unwrap();
+        arena.reset();
+        arena.verify().unwrap();
+        assert_eq!(cell.get(), EXPECTED_DROP_COUNT);
+        // The chunk is kept, so nothing is freed
+        assert!(arena.as_bumpalo().allocated_bytes() > 0);
+        assert!(arena.as_bumpalo().allocated_bytes() <= allocated);
+        // The largest chunk is the one that's kept, which held at least half of the values
+        let half = EXPECTED_DROP_COUNT as usize * mem::size_of::<DropCounted<&Cell<u32>>>() / 2;
+        assert!(arena.byte_capacity() >= half);
+        for _ in 0..5 {
+            verify_copyable(do_copyable(&arena));
+            verify_self_referential(do_self_referential(&arena));
+        }
+        do_drop_counted(&arena, &cell);
+        arena.verify().unwrap();
+        drop(arena);
+        assert_eq!(cell.get(), EXPECTED_DROP_COUNT * 2);
+    }
+    /// A backing store, which records when it's been dropped
+    #[cfg(not(feature = "no-drop-tracking"))]
+    struct Store<'d> {
+        entries: Vec<u32>,
+        dropped: &'d Cell<bool>,
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    impl<'d> Drop for Store<'d> {
+        fn drop(&mut self) {
+            self.dropped.set(true);
+        }
+    }
+    /// A cache over an earlier store, which reads it from its destructor
+    #[cfg(not(feature = "no-drop-tracking"))]
+    struct Cache<'d> {
+        store: *const Store<'d>,
+        total: &'d Cell<u32>,
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    impl<'d> Drop for Cache<'d> {
+        fn drop(&mut self) {
+            let store = unsafe { &*self.store };
+            assert!(
+                !store.dropped.get(),
+                "The store was dropped before the cache"
+            );
+            self.total
+                .set(self.total.get() + store.entries.iter().sum::<u32>());
+        }
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn reverse_drop_order() {
+        let (dropped, total) = (Cell::new(false), Cell::new(0));
+        let mut arena = DynamicArena::new_bounded();
+        for _ in 0..2 {
+            dropped.set(false);
+            let store: *const Store<'_> = arena.alloc(Store {
+                entries: vec![1, 2, 3],
+                dropped: &dropped,
+            });
+            for _ in 0..1000 {
+                arena.alloc(Cache {
+                    store,
+                    total: &total,
+                });
+            }
+            arena.reset();
+            assert!(dropped.get());
+        }
+        assert_eq!(total.get(), 2 * 1000 * 6);
+        // The last value registered is the first one dropped
+        let order = RefCell::new(Vec::new());
+        let arena = DynamicArena::new_bounded();
+        for id in 0..5 {
+            let order = &order;
+            arena.on_drop(move || order.borrow_mut().push(id));
+        }
+        drop(arena);
+        assert_eq!(*order.borrow(), vec![4, 3, 2, 1, 0]);
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn failed_registration() {
+        let cell = Cell::new(0);
+        let arena = DynamicArena::new_bounded();
+        let lock = arena.items.lock();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            arena.alloc(DropCounted(&cell));
+        }));
+        assert!(result.is_err());
+        drop(lock);
+        // The value was dropped, rather than leaked
+        assert_eq!((cell.get(), arena.pending_drop_count()), (1, 0));
+        drop(arena);
+        assert_eq!(cell.get(), 1);
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn overaligned_drop() {
+        #[repr(align(64))]
+        struct Aligned<'a>(DropCounted<&'a Cell<u32>>, [u8; 3]);
+        let cell = Cell::new(0);
+        let arena = DynamicArena::new_bounded();
+        for _ in 0..10 {
+            arena.alloc_copy(1u8);
+            let value = arena.alloc(Aligned(DropCounted(&cell), [7; 3]));
+            assert_eq!((value.0 .0.get(), value.1), (0, [7; 3]));
+            assert_eq!(value as *mut Aligned as usize % 64, 0);
+        }
+        assert_eq!(arena.pending_drop_count(), 10);
+        drop(arena);
+        assert_eq!(cell.get(), 10);
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn unsized_drop() {
+        let cell = Cell::new(0);
+        let tracker = std::rc::Rc::new(());
+        let mut arena = DynamicArena::new_bounded();
+        unsafe {
+            let ptr = arena
+                .alloc_layout(Layout::array::<DropCounted<&Cell<u32>>>(3).unwrap())
+                .cast::<DropCounted<&Cell<u32>>>();
+            for index in 0..3 {
+                ptr.as_ptr().add(index).write(DropCounted(&cell));
+            }
+            let slice = ptr::slice_from_raw_parts_mut(ptr.as_ptr(), 3);
+            arena.dynamic_drop(slice);
+            let ptr = arena.alloc_layout(Layout::new::<std::rc::Rc<()>>());
+            let any = ptr.as_ptr().cast::<std::rc::Rc<()>>();
+            any.write(std::rc::Rc::clone(&tracker));
+            arena.dynamic_drop(any as *mut dyn std::any::Any);
+        }
+        assert_eq!(
+            (
+                arena.pending_drop_count(),
+                std::rc::Rc::strong_count(&tracker)
+            ),
+            (2, 2)
+        );
+        arena.reset();
+        assert_eq!((cell.get(), std::rc::Rc::strong_count(&tracker)), (3, 1));
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn forget_all() {
+        let cell = Cell::new(0);
+        let mut arena = DynamicArena::new_bounded();
+        do_drop_counted(&arena, &cell);
+        arena.forget_all();
+        assert!(arena.items.is_empty());
+        assert_eq!(cell.get(), 0);
+        arena.alloc(DropCounted(&cell));
+        arena.verify().unwrap();
+        drop(arena);
+        assert_eq!(cell.get(), 1);
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    // The arena is leaked on purpose, which Miri reports as an error
+    #[cfg_attr(miri, ignore)]
+    fn leak() {
+        let tracker = std::rc::Rc::new(());
+        let arena = DynamicArena::new();
+        arena.alloc(std::rc::Rc::clone(&tracker));
+        let leaked: &'static DynamicArena = arena.leak();
+        let forever: &'static u32 = leaked.alloc_copy(42);
+        assert_eq!(*forever, 42);
+        assert_eq!(std::rc::Rc::strong_count(&tracker), 2);
+    }
+    #[test]
+    fn layout_slice() {
+        let arena = DynamicArena::new();
+        for &align in &[1, 8, 4096] {
+            let empty = arena.alloc_layout_slice(Layout::from_size_align(0, align).unwrap());
+            assert_eq!((empty.len(), empty.as_ptr() as usize % align), (0, 0));
+        }
+        let huge = Layout::from_size_align(100, 1 << 16).unwrap();
+        assert_eq!(arena.alloc_layout(huge).as_ptr() as usize % huge.align(), 0);
+        let bytes = arena.alloc_layout_slice(huge);
+        assert_eq!(
+            (bytes.len(), bytes.as_ptr() as usize % huge.align()),
+            (100, 0)
+        );
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            *byte = MaybeUninit::new(index as u8);
+        }
+        assert_eq!(unsafe { bytes[99].assume_init() }, 99);
+    }
+    #[test]
+    fn alloc_leak() {
+        let tracker = std::rc::Rc::new(());
+        let mut arena = DynamicArena::new_send();
+        // Neither `Send` nor `Copy`, but it's never dropped
+        let leaked = arena.alloc_leak(std::rc::Rc::clone(&tracker));
+        assert_eq!(std::rc::Rc::strong_count(leaked), 2);
+        assert_eq!(arena.pending_drop_count(), 0);
+        arena.reset();
+        assert_eq!(std::rc::Rc::strong_count(&tracker), 2);
+    }
+    /// Generic code that can only rely on the `SendAbility` trait
+    fn presized<S: SendAbility>() -> Vec<DynamicArena<'static, S>> {
+        (0..3)
+            .map(|_| DynamicArena::default_with_capacity(32, 4096))
+            .collect()
+    }
+    #[test]
+    fn capacity_generic() {
+        fn check<S: SendAbility + ItemBound<u32>>() {
+            for arena in presized::<S>() {
+                assert!(arena.as_bumpalo().chunk_capacity() >= 4096);
+                assert!(arena.item_capacity() >= 32);
+                verify_copyable(do_copyable(&arena));
+            }
+        }
+        check::<NonSend>();
+        check::<Sendable>();
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn allocation_count() {
+        let mut arena = DynamicArena::new();
+        assert!(arena.is_empty());
+        arena.alloc_copy(1u32);
+        assert_eq!(arena.len(), 1);
+        arena.alloc(String::from("dropped"));
+        unsafe { arena.alloc_unchecked(2u64) };
+        arena.alloc_layout(Layout::new::<[u8; 7]>());
+        arena.alloc_str("text");
+        let _: &mut str = "abc".chars().collect_in(&arena);
+        assert_eq!(arena.len(), 6);
+        assert!(!arena.is_empty());
+        arena.reset();
+        assert!(arena.is_empty());
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn debug_summary() {
+        let arena = DynamicArena::new_send();
+        arena.alloc(String::from("dropped"));
+        let summary = format!("{:?}", arena);
+        assert!(summary.starts_with("DynamicArena { marker: Sendable, allocated_bytes: "));
+        assert!(summary.ends_with("chunk_count: 1, registered_drop_count: 1 }"));
+        let _lock = arena.items.lock();
+        let locked = format!("{:?}", arena);
+        assert!(locked.ends_with("registered_drop_count: <locked> }"));
+        let child = DynamicArena::new();
+        assert!(
+            format!("{:?}", child.child()).starts_with("ChildArena(DynamicArena { marker: NonSend")
+        );
+    }
+    fn do_copyable<'a, S: ItemBound<u32>>(arena: &'a DynamicArena<S>) -> Vec<&'a u32> {
         let mut results = Vec::new();
         for i in 0..10 {
             results.push(&*arena.alloc_copy(i * 3));
@@ -383,6 +1736,7 @@ mod test {
             assert_eq!(**actual, expected * 3);
         }
     }
+    #[cfg(not(feature = "no-drop-tracking"))]
     fn do_drop_counted<'a, 'd: 'a>(arena: &'a DynamicArena<'d>, counter: &'d Cell<u32>) {
         for _ in 0..EXPECTED_DROP_COUNT {
             arena.alloc(DropCounted(counter));
@@ -397,6 +1751,7 @@ mod test {
     }
     fn verify_self_referential<'a>(results: Vec<&'a SelfReferential<'a>>) {
         for (&actual, &depth) in results.iter().zip(EXPECTED_DEPTHS.iter()) {
+            assert_eq!(actual.0, depth);
             assert_eq!(actual.depth(), depth);
         }
     }
@@ -0,0 +1,252 @@
+//! Allocation limits, along with fallible allocation.
+use std::alloc::Layout;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::mem;
+use std::ptr::{self, NonNull};
+
+use crate::{DynamicArena, ItemBound};
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Limit the total number of bytes this arena can allocate from the system,
+    /// or remove the limit with `None`.
+    ///
+    /// This uses bumpalo's limit, which is only enforced when allocating new chunks
+    /// (so it's the total size of the chunks that's limited).
+    /// The fallible methods (like `try_alloc`) return an `AllocError` if the limit would be exceeded.
+    /// All the other methods panic, just as if the system allocator had run out of memory.
+    #[inline]
+    pub fn set_allocation_limit(&self, limit: Option<usize>) {
+        self.handle.set_allocation_limit(limit);
+    }
+    /// The current allocation limit in bytes, if any
+    #[inline]
+    pub fn allocation_limit(&self) -> Option<usize> {
+        self.handle.allocation_limit()
+    }
+    /// Try to allocate the specified copyable value in this arena,
+    /// returning an error if the allocation limit would be exceeded.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn try_alloc_copy<T: Copy>(&self, value: T) -> Result<&mut T, AllocError>
+    where
+        S: ItemBound<T>,
+    {
+        unsafe { self.try_alloc_unchecked(value) }
+    }
+    /// Try to allocate a copy of the specified string in this arena,
+    /// returning an error if the allocation limit would be exceeded.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn try_alloc_str(&self, s: &str) -> Result<&mut str, AllocError> {
+        unsafe {
+            let ptr = self
+                .try_alloc_layout_as(Layout::for_value(s), None)?
+                .as_ptr();
+            ptr::copy_nonoverlapping(s.as_ptr(), ptr, s.len());
+            Ok(std::str::from_utf8_unchecked_mut(
+                std::slice::from_raw_parts_mut(ptr, s.len()),
+            ))
+        }
+    }
+    /// Try to allocate space for an object with the specified layout,
+    /// returning an error if the allocation limit would be exceeded.
+    ///
+    /// Otherwise, this is the same as `alloc_layout`.
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn try_alloc_layout(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        unsafe { self.try_alloc_layout_as(layout, None) }
+    }
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    unsafe fn try_alloc_layout_as(
+        &self,
+        layout: Layout,
+        type_name: Option<&'static str>,
+    ) -> Result<NonNull<u8>, AllocError> {
+        self.try_alloc_block(layout, type_name, layout.size())
+            .ok_or_else(|| self.alloc_failed(layout.size()))
+    }
+    /// Report that allocating the specified number of bytes failed
+    #[cold]
+    fn alloc_failed(&self, requested: usize) -> AllocError {
+        let error = AllocError {
+            requested,
+            usage: self.handle.allocated_bytes(),
+            limit: self.handle.allocation_limit(),
+        };
+        self.limit_exceeded(error.requested, error.usage, error.limit);
+        error
+    }
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    unsafe fn try_alloc_unchecked<T>(&self, value: T) -> Result<&mut T, AllocError> {
+        let ptr = self
+            .try_alloc_layout_as(Layout::new::<T>(), Some(std::any::type_name::<T>()))?
+            .as_ptr()
+            .cast::<T>();
+        ptr.write(value);
+        Ok(&mut *ptr)
+    }
+}
+impl<'a, S> DynamicArena<'a, S> {
+    /// Try to allocate the specified value in this arena,
+    /// returning an error if the allocation limit would be exceeded.
+    ///
+    /// The value and its drop entry are allocated together,
+    /// so a failure never leaves the value partially registered.
+    /// On failure the value is dropped immediately (use `try_alloc_or_return` to get it back).
+    /// Otherwise, this is the same as `alloc`.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn try_alloc<T: 'a>(&self, value: T) -> Result<&mut T, AllocError>
+    where
+        S: ItemBound<T>,
+    {
+        self.try_alloc_or_return(value).map_err(|(error, _)| error)
+    }
+    /// Try to allocate the specified value in this arena,
+    /// returning the error along with the value if the allocation limit would be exceeded.
+    ///
+    /// Otherwise, this is the same as `try_alloc`.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn try_alloc_or_return<T: 'a>(&self, value: T) -> Result<&mut T, (AllocError, T)>
+    where
+        S: ItemBound<T>,
+    {
+        unsafe {
+            match self.try_alloc_registered(value) {
+                Ok(target) => Ok(&mut *target),
+                Err(value) => Err((self.alloc_failed(mem::size_of::<T>()), value)),
+            }
+        }
+    }
+}
+
+/// An error indicating that an allocation would exceed the arena's allocation limit
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AllocError {
+    pub(crate) requested: usize,
+    pub(crate) usage: usize,
+    pub(crate) limit: Option<usize>,
+}
+impl AllocError {
+    /// The number of bytes that were requested
+    #[inline]
+    pub fn requested_size(&self) -> usize {
+        self.requested
+    }
+    /// The number of bytes the arena had already allocated from the system
+    #[inline]
+    pub fn current_usage(&self) -> usize {
+        self.usage
+    }
+    /// The allocation limit at the time of the failure
+    ///
+    /// This is `None` if there wasn't a limit (so the system allocator itself failed).
+    #[inline]
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+}
+impl Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to allocate {} bytes with {} bytes in use",
+            self.requested, self.usage
+        )?;
+        if let Some(limit) = self.limit {
+            write!(f, " (limit is {})", limit)?;
+        }
+        Ok(())
+    }
+}
+impl Error for AllocError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::rc::Rc;
+
+    #[test]
+    fn limited() {
+        let arena = DynamicArena::new();
+        arena.alloc_copy(0u8);
+        let usage = arena.as_bumpalo().allocated_bytes();
+        arena.set_allocation_limit(Some(usage));
+        assert_eq!(arena.allocation_limit(), Some(usage));
+        // Allocations that fit in the current chunk still succeed
+        assert_eq!(arena.try_alloc_str("small").unwrap(), "small");
+        let err = arena.try_alloc_str(&"x".repeat(usage * 2)).unwrap_err();
+        assert_eq!(err.requested_size(), usage * 2);
+        assert_eq!(err.current_usage(), usage);
+        assert_eq!(err.limit(), Some(usage));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Failed to allocate {} bytes with {} bytes in use (limit is {})",
+                usage * 2,
+                usage,
+                usage
+            )
+        );
+        assert!(arena.try_alloc_copy([0u64; 1024]).is_err());
+        arena.set_allocation_limit(None);
+        assert!(arena.try_alloc_copy([0u64; 1024]).is_ok());
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn failed_values_dropped() {
+        let tracker = Rc::new(());
+        let arena = DynamicArena::new();
+        arena.set_allocation_limit(Some(0));
+        let err = arena.try_alloc((Rc::clone(&tracker), [0u8; 128]));
+        assert!(err.is_err());
+        assert_eq!(Rc::strong_count(&tracker), 1);
+        arena.set_allocation_limit(None);
+        arena.try_alloc(Rc::clone(&tracker)).unwrap();
+        assert_eq!(Rc::strong_count(&tracker), 2);
+        drop(arena);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+        let arena = DynamicArena::new_send();
+        assert_eq!(*arena.try_alloc(String::from("sent")).unwrap(), "sent");
+    }
+    #[test]
+    #[should_panic(expected = "out of memory")]
+    fn infallible_panics() {
+        let arena = DynamicArena::new();
+        arena.set_allocation_limit(Some(0));
+        arena.alloc_copy([0u8; 64]);
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn no_partial_registration() {
+        let tracker = Rc::new(());
+        let arena: DynamicArena = DynamicArena::with_capacity(4, 0);
+        arena.set_allocation_limit(Some(arena.as_bumpalo().allocated_bytes()));
+        let mut allocated = 0;
+        let (err, returned) = loop {
+            match arena.try_alloc_or_return(Rc::clone(&tracker)) {
+                Ok(_) => allocated += 1,
+                Err(failed) => break failed,
+            }
+        };
+        assert!(allocated >= 1);
+        assert_eq!(err.requested_size(), mem::size_of::<Rc<()>>());
+        // Everything that was allocated is registered, and the rest was handed back
+        assert_eq!(arena.pending_drop_count(), allocated);
+        assert_eq!(Rc::strong_count(&tracker), allocated + 2);
+        drop(returned);
+        arena.verify().unwrap();
+        drop(arena);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+}
@@ -0,0 +1,215 @@
+//! Two-dimensional slices allocated in a `DynamicArena`.
+use std::ops::{Index, IndexMut};
+use std::slice;
+
+use crate::slice::array_layout;
+use crate::{DynamicArena, ItemBound};
+
+/// A two-dimensional view of a contiguous slice in an arena, stored in row-major order.
+///
+/// This is created by `DynamicArena::alloc_2d` or `DynamicArena::alloc_2d_with`.
+pub struct Arena2D<'arena, T> {
+    data: &'arena mut [T],
+    rows: usize,
+    cols: usize,
+}
+impl<'arena, T> Arena2D<'arena, T> {
+    /// The number of rows
+    #[inline]
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+    /// The number of columns
+    #[inline]
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+    /// The elements of the specified row
+    ///
+    /// ## Panics
+    /// If the row is out of bounds.
+    #[inline]
+    pub fn row(&self, row: usize) -> &[T] {
+        self.check_row(row);
+        &self.data[row * self.cols..(row + 1) * self.cols]
+    }
+    /// The elements of the specified row, mutably
+    ///
+    /// ## Panics
+    /// If the row is out of bounds.
+    #[inline]
+    pub fn row_mut(&mut self, row: usize) -> &mut [T] {
+        self.check_row(row);
+        &mut self.data[row * self.cols..(row + 1) * self.cols]
+    }
+    /// Get the element at the specified position, or `None` if it's out of bounds
+    #[inline]
+    pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+        if row < self.rows && col < self.cols {
+            Some(&self.data[row * self.cols + col])
+        } else {
+            None
+        }
+    }
+    /// Mutably get the element at the specified position, or `None` if it's out of bounds
+    #[inline]
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut T> {
+        if row < self.rows && col < self.cols {
+            Some(&mut self.data[row * self.cols + col])
+        } else {
+            None
+        }
+    }
+    /// All the elements, as a flat slice in row-major order
+    #[inline]
+    pub fn as_flat(&self) -> &[T] {
+        self.data
+    }
+    /// All the elements, as a flat mutable slice in row-major order
+    #[inline]
+    pub fn as_flat_mut(&mut self) -> &mut [T] {
+        self.data
+    }
+    /// Convert this into the underlying flat slice, with the full lifetime of the arena
+    #[inline]
+    pub fn into_flat(self) -> &'arena mut [T] {
+        self.data
+    }
+    #[inline]
+    fn check_row(&self, row: usize) {
+        assert!(
+            row < self.rows,
+            "Row {} out of bounds for {} rows",
+            row,
+            self.rows
+        );
+    }
+}
+impl<'arena, T> Index<(usize, usize)> for Arena2D<'arena, T> {
+    type Output = T;
+    #[inline]
+    fn index(&self, (row, col): (usize, usize)) -> &T {
+        match self.get(row, col) {
+            Some(value) => value,
+            None => panic!(
+                "Index ({}, {}) out of bounds for {}x{}",
+                row, col, self.rows, self.cols
+            ),
+        }
+    }
+}
+impl<'arena, T> IndexMut<(usize, usize)> for Arena2D<'arena, T> {
+    #[inline]
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut T {
+        let (rows, cols) = (self.rows, self.cols);
+        match self.get_mut(row, col) {
+            Some(value) => value,
+            None => panic!(
+                "Index ({}, {}) out of bounds for {}x{}",
+                row, col, rows, cols
+            ),
+        }
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Allocate a `rows` by `cols` matrix in a single contiguous allocation,
+    /// with every element initialized to a copy of `fill`.
+    ///
+    /// If either dimension is zero, nothing is allocated.
+    ///
+    /// ## Panics
+    /// If the total size of the matrix overflows.
+    #[inline]
+    pub fn alloc_2d<T: Copy>(&self, rows: usize, cols: usize, fill: T) -> Arena2D<'_, T>
+    where
+        S: ItemBound<T>,
+    {
+        self.alloc_2d_with(rows, cols, |_, _| fill)
+    }
+    /// Allocate a `rows` by `cols` matrix in a single contiguous allocation,
+    /// initializing each element with the result of calling `func(row, col)`.
+    ///
+    /// The elements are initialized in row-major order.
+    /// If either dimension is zero, nothing is allocated.
+    ///
+    /// ## Panics
+    /// If the total size of the matrix overflows.
+    pub fn alloc_2d_with<T: Copy>(
+        &self,
+        rows: usize,
+        cols: usize,
+        mut func: impl FnMut(usize, usize) -> T,
+    ) -> Arena2D<'_, T>
+    where
+        S: ItemBound<T>,
+    {
+        let len = match rows.checked_mul(cols) {
+            Some(len) => len,
+            None => panic!("Capacity overflow for {}x{} matrix", rows, cols),
+        };
+        let data: &mut [T] = if len == 0 {
+            &mut []
+        } else {
+            unsafe {
+                let ptr = self
+                    .alloc_layout(array_layout::<T>(len))
+                    .as_ptr()
+                    .cast::<T>();
+                let mut index = 0;
+                for row in 0..rows {
+                    for col in 0..cols {
+                        // Since `T: Copy`, there's nothing to cleanup if this panics
+                        ptr.add(index).write(func(row, col));
+                        index += 1;
+                    }
+                }
+                slice::from_raw_parts_mut(ptr, len)
+            }
+        };
+        Arena2D { data, rows, cols }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn matrix() {
+        let arena = DynamicArena::new();
+        let mut matrix = arena.alloc_2d_with(3, 4, |row, col| (row * 10 + col) as u32);
+        assert_eq!((matrix.rows(), matrix.cols()), (3, 4));
+        assert_eq!(matrix.row(1), &[10, 11, 12, 13]);
+        assert_eq!(matrix.get(2, 3), Some(&23));
+        assert_eq!(matrix.get(3, 0), None);
+        assert_eq!(matrix.get(0, 4), None);
+        matrix.row_mut(0)[1] = 99;
+        *matrix.get_mut(2, 0).unwrap() = 7;
+        matrix[(1, 1)] += 1;
+        assert_eq!(matrix[(0, 1)], 99);
+        assert_eq!(
+            matrix.as_flat(),
+            &[0, 99, 2, 3, 10, 12, 12, 13, 7, 21, 22, 23]
+        );
+        let filled = arena.alloc_2d(2, 2, 1.5f64);
+        assert_eq!(filled.into_flat(), &[1.5; 4]);
+    }
+    #[test]
+    fn degenerate() {
+        let arena = DynamicArena::new();
+        let before = arena.used_bytes();
+        let empty_rows = arena.alloc_2d(0, 1000, 0u8);
+        assert!(empty_rows.as_flat().is_empty());
+        let mut empty_cols = arena.alloc_2d_with(5, 0, |_, _| -> u64 { unreachable!() });
+        assert!(empty_cols.row_mut(4).is_empty());
+        assert_eq!(empty_cols.get(0, 0), None);
+        assert_eq!(arena.used_bytes(), before);
+    }
+    #[test]
+    #[should_panic(expected = "Capacity overflow")]
+    fn overflow() {
+        let arena = DynamicArena::new();
+        arena.alloc_2d(usize::MAX, 2, 0u8);
+    }
+}
@@ -0,0 +1,119 @@
+//! Memoization of arena-allocated values.
+use std::borrow::Borrow;
+use std::collections::hash_map::{Entry, HashMap};
+use std::hash::Hash;
+
+use crate::{DynamicArena, ItemBound, NonSend};
+
+/// A cache of values allocated in an arena, keyed by a hashable key.
+///
+/// This computes each value at most once, allocating it in the arena on first use.
+/// Unlike `ValueInterner` this is keyed instead of deduplicating the values themselves,
+/// so the values don't need to be `Copy` or even `Hash`.
+/// Values that need to be dropped are registered with the arena just like `DynamicArena::alloc`.
+///
+/// Since `get_or_alloc_with` takes `&mut self`, the closures computing the values
+/// can't recursively use the memo (it's statically rejected by the borrow checker).
+/// However, they're still free to allocate in the arena itself.
+pub struct ArenaMemo<'arena, 'a, K, V, S = NonSend> {
+    arena: &'arena DynamicArena<'a, S>,
+    values: HashMap<K, &'arena V>,
+}
+impl<'arena, 'a, K: Hash + Eq, V, S> ArenaMemo<'arena, 'a, K, V, S> {
+    /// Create an empty memo whose values will be allocated in the specified arena
+    pub fn new(arena: &'arena DynamicArena<'a, S>) -> Self {
+        ArenaMemo {
+            arena,
+            values: HashMap::new(),
+        }
+    }
+    /// Get the value that has already been computed for the specified key
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&'arena V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.values.get(key).copied()
+    }
+    /// Check if a value has already been computed for the specified key
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.values.contains_key(key)
+    }
+    #[inline]
+    fn get_or_insert_with(&mut self, key: K, alloc: impl FnOnce() -> &'arena V) -> &'arena V {
+        match self.values.entry(key) {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => entry.insert(alloc()),
+        }
+    }
+    /// The number of values that have been computed
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    /// Check if no values have been computed yet
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+impl<'arena, 'a, K: Hash + Eq, V: 'a, S: ItemBound<V>> ArenaMemo<'arena, 'a, K, V, S> {
+    /// Get the value corresponding to the specified key,
+    /// computing and allocating it in the arena if it's not already present.
+    ///
+    /// Just like `DynamicArena::alloc`, the values must satisfy the arena's `ItemBound`.
+    pub fn get_or_alloc_with(&mut self, key: K, func: impl FnOnce() -> V) -> &'arena V {
+        let arena = self.arena;
+        self.get_or_insert_with(key, || arena.alloc(func()))
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use crate::test_util::DropCounted;
+    use std::cell::Cell;
+
+    #[test]
+    fn memoized() {
+        let counter = Cell::new(0);
+        let computed = Cell::new(0);
+        let arena = DynamicArena::new_bounded();
+        {
+            let mut memo = ArenaMemo::new(&arena);
+            for round in 0..3 {
+                for key in 0..10u32 {
+                    let value = memo.get_or_alloc_with(key, || {
+                        computed.set(computed.get() + 1);
+                        (key * 2, DropCounted(&counter))
+                    });
+                    assert_eq!(value.0, key * 2);
+                }
+                assert_eq!(computed.get(), 10, "Round {}", round);
+            }
+            assert_eq!(memo.len(), 10);
+            assert!(memo.contains_key(&3));
+            assert_eq!(memo.get(&4).unwrap().0, 8);
+            assert!(memo.get(&10).is_none());
+        }
+        // The values are owned by the arena, not the memo
+        assert_eq!(counter.get(), 0);
+        drop(arena);
+        assert_eq!(counter.get(), 10);
+    }
+    #[test]
+    fn sendable() {
+        let arena = DynamicArena::new_send();
+        let mut memo = ArenaMemo::new(&arena);
+        let first = memo.get_or_alloc_with("lowered", || String::from("value"));
+        let second = memo.get_or_alloc_with("lowered", || unreachable!());
+        assert!(std::ptr::eq(first, second));
+        assert_eq!(first, "value");
+    }
+}
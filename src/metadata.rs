@@ -0,0 +1,268 @@
+//! Attaching metadata (like source spans) to allocations, without storing it in the values.
+use std::any::TypeId;
+use std::cell::Cell;
+use std::ptr::{self, NonNull};
+
+use bumpalo::Bump;
+
+use crate::{DynamicArena, ItemBound};
+
+/// An entry in the metadata table, chained to the other entries in the same bucket
+struct MetaEntry {
+    address: usize,
+    type_id: TypeId,
+    meta: NonNull<()>,
+    next: Option<NonNull<MetaEntry>>,
+}
+
+/// The metadata of each allocation, keyed by its address
+///
+/// This is a hash table that lives in the arena, along with the metadata itself,
+/// so it's freed along with the chunks and never touches the heap.
+/// Growing the table only allocates new buckets (the old ones are wasted until the arena is reset),
+/// since the entries are relinked in place.
+pub(crate) struct MetadataTable {
+    /// The buckets, which are null until the first entry is inserted
+    buckets: Cell<*mut Option<NonNull<MetaEntry>>>,
+    /// The base two logarithm of the number of buckets
+    bits: Cell<u32>,
+    /// The number of entries in the table
+    len: Cell<usize>,
+}
+/// The metadata is only `Send` in a `Sendable` arena
+unsafe impl Send for MetadataTable {}
+impl Default for MetadataTable {
+    #[inline]
+    fn default() -> Self {
+        MetadataTable {
+            buckets: Cell::new(ptr::null_mut()),
+            bits: Cell::new(0),
+            len: Cell::new(0),
+        }
+    }
+}
+impl MetadataTable {
+    /// The number of buckets the table starts with
+    const INITIAL_BITS: u32 = 4;
+    /// The bucket for the specified address, using Fibonacci hashing
+    ///
+    /// ## Safety
+    /// The table must have buckets.
+    #[inline]
+    unsafe fn bucket(&self, address: usize) -> *mut Option<NonNull<MetaEntry>> {
+        let hash = (address as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        let index = (hash >> (64 - self.bits.get())) as usize;
+        self.buckets.get().add(index)
+    }
+    /// Find the entry for the specified address
+    #[inline]
+    fn find(&self, address: usize) -> Option<NonNull<MetaEntry>> {
+        if self.buckets.get().is_null() {
+            return None;
+        }
+        let mut next = unsafe { *self.bucket(address) };
+        while let Some(entry) = next {
+            let entry_ref = unsafe { entry.as_ref() };
+            if entry_ref.address == address {
+                return Some(entry);
+            }
+            next = entry_ref.next;
+        }
+        None
+    }
+    /// The type and location of the metadata attached to the specified address
+    #[inline]
+    fn get(&self, address: usize) -> Option<(TypeId, NonNull<()>)> {
+        self.find(address)
+            .map(|entry| unsafe { (entry.as_ref().type_id, entry.as_ref().meta) })
+    }
+    /// Attach the metadata to the specified address, replacing any existing metadata
+    ///
+    /// The table is allocated in the specified bump, which must be the arena's own.
+    fn insert(&self, handle: &Bump, address: usize, type_id: TypeId, meta: NonNull<()>) {
+        if let Some(entry) = self.find(address) {
+            unsafe {
+                (*entry.as_ptr()).type_id = type_id;
+                (*entry.as_ptr()).meta = meta;
+            }
+            return;
+        }
+        if self.len.get() >= self.capacity() {
+            self.grow(handle);
+        }
+        unsafe {
+            let bucket = self.bucket(address);
+            let entry = handle.alloc(MetaEntry {
+                address,
+                type_id,
+                meta,
+                next: *bucket,
+            });
+            *bucket = Some(NonNull::from(entry));
+        }
+        self.len.set(self.len.get() + 1);
+    }
+    /// The number of entries the buckets can hold before growing (a load factor of one)
+    #[inline]
+    fn capacity(&self) -> usize {
+        if self.buckets.get().is_null() {
+            0
+        } else {
+            1 << self.bits.get()
+        }
+    }
+    /// Double the number of buckets, relinking every entry into the new ones
+    #[cold]
+    fn grow(&self, handle: &Bump) {
+        let (old, old_capacity) = (self.buckets.get(), self.capacity());
+        let bits = if old.is_null() {
+            Self::INITIAL_BITS
+        } else {
+            self.bits.get() + 1
+        };
+        let buckets = handle.alloc_slice_fill_copy(1 << bits, None).as_mut_ptr();
+        self.buckets.set(buckets);
+        self.bits.set(bits);
+        for index in 0..old_capacity {
+            let mut next = unsafe { *old.add(index) };
+            while let Some(entry) = next {
+                unsafe {
+                    let entry_ptr = entry.as_ptr();
+                    next = (*entry_ptr).next;
+                    let bucket = self.bucket((*entry_ptr).address);
+                    (*entry_ptr).next = *bucket;
+                    *bucket = Some(entry);
+                }
+            }
+        }
+    }
+    /// Forget the metadata of the allocation at the specified address, since its memory was reclaimed
+    #[inline]
+    pub(crate) fn forget(&self, address: usize) {
+        if self.len.get() == 0 {
+            return;
+        }
+        unsafe {
+            let mut link = self.bucket(address);
+            while let Some(entry) = *link {
+                if entry.as_ref().address == address {
+                    *link = entry.as_ref().next;
+                    self.len.set(self.len.get() - 1);
+                    return;
+                }
+                link = ptr::addr_of_mut!((*entry.as_ptr()).next);
+            }
+        }
+    }
+    /// Forget every entry, since the arena's chunks (including the table) are being reset
+    pub(crate) fn clear(&mut self) {
+        *self = MetadataTable::default();
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Retrieve the metadata attached to the specified value by `alloc_with_meta`
+    ///
+    /// This returns `None` if the value doesn't have any metadata,
+    /// or if its metadata has a different type.
+    /// Zero-sized values may share an address, so only the most recent one keeps its metadata.
+    pub fn metadata<M: 'static, T: ?Sized>(&self, value: &T) -> Option<&M> {
+        let address = value as *const T as *const u8 as usize;
+        match self.metadata.get(address) {
+            // The metadata lives in the arena until it's reset
+            Some((type_id, meta)) if type_id == TypeId::of::<M>() => {
+                Some(unsafe { &*meta.as_ptr().cast::<M>() })
+            }
+            _ => None,
+        }
+    }
+    /// Copy the metadata into the arena, attaching it to the specified value
+    ///
+    /// ## Safety
+    /// The metadata must be `Send` if the arena is `Sendable`.
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    unsafe fn attach_metadata<T, M: Copy + 'static>(&self, value: *const T, meta: M) {
+        let meta = NonNull::from(self.alloc_value(meta)).cast::<()>();
+        self.metadata
+            .insert(&self.handle, value as usize, TypeId::of::<M>(), meta);
+    }
+}
+impl<'a, S> DynamicArena<'a, S> {
+    /// Allocate the specified value, attaching the specified metadata to it
+    ///
+    /// The metadata is copied into the arena and can be retrieved with `metadata`,
+    /// which is useful for mapping nodes back to their source spans without storing them in every node.
+    /// Otherwise, this is the same as `alloc` (and the metadata must satisfy the `ItemBound` as well).
+    /// Allocations without metadata don't pay anything for this.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_with_meta<T: 'a, M: Copy + 'static>(&self, value: T, meta: M) -> &mut T
+    where
+        S: ItemBound<T> + ItemBound<M>,
+    {
+        let target = self.alloc(value);
+        unsafe { self.attach_metadata::<T, M>(target, meta) };
+        target
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    struct Span {
+        start: u32,
+        end: u32,
+    }
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn interleaved() {
+        let mut arena = DynamicArena::new();
+        let mut nodes = Vec::new();
+        for i in 0..1000u32 {
+            let node: &String = arena.alloc_with_meta(
+                i.to_string(),
+                Span {
+                    start: i,
+                    end: i + 1,
+                },
+            );
+            nodes.push(node);
+            arena.alloc(String::from("plain"));
+            arena.alloc_copy(i);
+        }
+        for (i, node) in nodes.iter().enumerate() {
+            let span = arena.metadata::<Span, _>(*node).unwrap();
+            assert_eq!(span.start, i as u32);
+            // The type of the metadata must match
+            assert_eq!(arena.metadata::<u32, _>(*node), None);
+        }
+        let plain = arena.alloc(String::from("plain"));
+        assert_eq!(arena.metadata::<Span, _>(plain), None);
+        let file = arena.alloc_with_meta(0u64, "main.rs");
+        assert_eq!(arena.metadata::<&str, _>(file), Some(&"main.rs"));
+        drop(nodes);
+        arena.reset();
+        let reused = arena.alloc(String::new());
+        assert_eq!(arena.metadata::<Span, _>(reused), None);
+    }
+    #[test]
+    fn deallocated() {
+        let arena = DynamicArena::new_send();
+        let kept: *mut u64 = arena.alloc_with_meta(1u64, Span { start: 0, end: 1 });
+        let discarded: *mut u64 = arena.alloc_with_meta(2u64, Span { start: 1, end: 2 });
+        unsafe {
+            // The metadata was allocated after the value, so the bytes aren't reclaimed
+            assert!(!arena.dealloc_last(discarded));
+            assert_eq!(arena.metadata::<Span, _>(&*discarded), None);
+            assert_eq!(
+                arena.metadata::<Span, _>(&*kept),
+                Some(&Span { start: 0, end: 1 })
+            );
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Compressed 32-bit references to the values in a contiguous arena.
+use std::convert::TryFrom;
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem;
+
+use crate::DynamicArena;
+
+/// The position of a value in a contiguous arena, which takes half the space of a reference
+///
+/// Offsets are measured from the end of the arena's first chunk (since chunks are filled downwards),
+/// so they stay the same as more values are allocated, and they can be serialized.
+/// They're only meaningful for the arena that created them (until it's reset),
+/// which is why resolving them is `unsafe`.
+///
+/// This is created by `DynamicArena::offset_of`.
+pub struct ArenaOffset<T> {
+    offset: u32,
+    marker: PhantomData<fn() -> T>,
+}
+impl<T> ArenaOffset<T> {
+    /// Recreate an offset from its raw value (usually a deserialized one)
+    #[inline]
+    pub const fn from_u32(offset: u32) -> Self {
+        ArenaOffset {
+            offset,
+            marker: PhantomData,
+        }
+    }
+    /// The raw value of the offset
+    #[inline]
+    pub const fn to_u32(self) -> u32 {
+        self.offset
+    }
+}
+impl<T> Clone for ArenaOffset<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for ArenaOffset<T> {}
+impl<T> PartialEq for ArenaOffset<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset
+    }
+}
+impl<T> Eq for ArenaOffset<T> {}
+impl<T> Hash for ArenaOffset<T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.offset.hash(state)
+    }
+}
+impl<T> Debug for ArenaOffset<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ArenaOffset").field(&self.offset).finish()
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// The offset of the specified value in this arena, which can be resolved back with `resolve_offset`
+    ///
+    /// This is meant for arenas built with `DynamicArenaBuilder::contiguous`,
+    /// where every value is in the same chunk and every offset fits in 32 bits.
+    ///
+    /// ## Panics
+    /// If the value isn't in the arena's first chunk,
+    /// or its offset doesn't fit in a `u32` (which can't happen in a contiguous arena).
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn offset_of<T>(&self, value: &T) -> ArenaOffset<T> {
+        let address = value as *const T as usize;
+        let (start, end) = self.first_chunk();
+        assert!(
+            address >= start && address.saturating_add(mem::size_of::<T>()) <= end,
+            "The value at {:#x} isn't in the arena's first chunk",
+            address
+        );
+        match u32::try_from(end - address) {
+            Ok(offset) => ArenaOffset::from_u32(offset),
+            Err(_) => panic!(
+                "The value at {:#x} is more than 4 GiB into the arena",
+                address
+            ),
+        }
+    }
+    /// Resolve an offset created by `offset_of`, returning a reference to the value
+    ///
+    /// ## Safety
+    /// The offset must have come from `offset_of` on this arena, since it was last reset,
+    /// and the value must not be mutably borrowed.
+    /// In debug builds, offsets outside the arena's first chunk panic.
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub unsafe fn resolve_offset<T>(&self, offset: ArenaOffset<T>) -> &T {
+        let (ptr, len) = self
+            .handle
+            .iter_allocated_chunks_raw()
+            .last()
+            .expect("The arena doesn't have any chunks");
+        let offset = offset.to_u32() as usize;
+        debug_assert!(
+            offset >= mem::size_of::<T>() && offset <= len,
+            "The offset {} doesn't belong to this arena",
+            offset
+        );
+        // Derived from the chunk's own pointer, which covers the whole chunk
+        &*ptr.add(len - offset).cast::<T>()
+    }
+    /// The used part of the first chunk the arena allocated, which is its only chunk if it's contiguous
+    #[inline]
+    fn first_chunk(&self) -> (usize, usize) {
+        // The chunk is only compared against, never accessed
+        unsafe { self.handle.iter_allocated_chunks_raw() }
+            .last()
+            .map_or((0, 0), |(ptr, len)| (ptr as usize, ptr as usize + len))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::AllocError;
+    use std::panic::AssertUnwindSafe;
+
+    #[derive(Clone, Copy)]
+    struct Node {
+        value: u64,
+        next: Option<ArenaOffset<Node>>,
+    }
+
+    #[test]
+    fn linked_offsets() {
+        let arena: DynamicArena = DynamicArena::builder().contiguous(64 * 1024).build();
+        assert_eq!(mem::size_of::<ArenaOffset<Node>>(), 4);
+        let mut head = None;
+        for value in 0..1000 {
+            let node = arena.alloc_copy(Node { value, next: head });
+            head = Some(arena.offset_of(node));
+        }
+        // Offsets resolve the same way after more allocations, and after a round trip
+        arena.alloc_slice_fill_copy(1000, 0u8);
+        let mut offset: Option<ArenaOffset<Node>> =
+            head.map(|head| ArenaOffset::from_u32(head.to_u32()));
+        let mut expected = 1000;
+        while let Some(current) = offset {
+            let node = unsafe { arena.resolve_offset(current) };
+            expected -= 1;
+            assert_eq!(node.value, expected);
+            offset = node.next;
+        }
+        assert_eq!(expected, 0);
+    }
+
+    #[test]
+    fn never_grows() {
+        let arena: DynamicArena = DynamicArena::builder().contiguous(4096).build();
+        let chunk = arena.as_bumpalo().chunk_capacity();
+        let result: Result<Vec<_>, AllocError> =
+            (0..chunk).map(|_| arena.try_alloc_copy(0u64)).collect();
+        assert!(result.is_err());
+        assert_eq!(
+            unsafe { arena.as_bumpalo().iter_allocated_chunks_raw() }.count(),
+            1
+        );
+        let other = 5u64;
+        let foreign = std::panic::catch_unwind(AssertUnwindSafe(|| arena.offset_of(&other)));
+        assert!(foreign.is_err());
+    }
+}
@@ -0,0 +1,252 @@
+//! Parallel initialization and teardown of `Sendable` arenas, using rayon.
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use rayon::prelude::*;
+
+use crate::slice::{array_layout, init_slice_with};
+use crate::{DynamicArena, Sendable};
+
+/// The minimum number of elements initialized by each task,
+/// so cheap initializers aren't dominated by scheduling
+const MIN_FILL_CHUNK: usize = 4096;
+
+/// The slice being filled, which each task only writes at its own disjoint indices
+struct FillTarget<T>(*mut T);
+unsafe impl<T: Send> Send for FillTarget<T> {}
+unsafe impl<T: Send> Sync for FillTarget<T> {}
+
+/// The outcome of initializing a single chunk of the slice
+enum ChunkFill {
+    Filled,
+    /// Another chunk already panicked, so this one was never started
+    Skipped,
+    Panicked(Box<dyn Any + Send>),
+}
+
+impl<'a> DynamicArena<'a, Sendable> {
+    /// Allocate a slice of the specified length in this arena,
+    /// initializing the elements in parallel with the result of calling `func(index)`.
+    ///
+    /// The slice is allocated once, and then split into chunks that are filled on the rayon thread pool.
+    /// Once every element is initialized, a single drop is registered for the entire slice.
+    ///
+    /// If `func` panics, the remaining chunks are abandoned and every element
+    /// that was already initialized is dropped, before the first panic is resumed.
+    /// The memory for the slice isn't reclaimed until the arena is reset or dropped.
+    ///
+    /// ## Panics
+    /// If the total size of the slice overflows.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn par_alloc_slice_fill_with<T: Send + 'a>(
+        &self,
+        len: usize,
+        func: impl Fn(usize) -> T + Sync,
+    ) -> &mut [T] {
+        let ptr = self
+            .alloc_layout(array_layout::<T>(len))
+            .as_ptr()
+            .cast::<T>();
+        let target = FillTarget(ptr);
+        let chunk_len = (len / (rayon::current_num_threads() * 4)).max(MIN_FILL_CHUNK);
+        let chunk_range = |chunk: usize| chunk * chunk_len..len.min((chunk + 1) * chunk_len);
+        let aborted = AtomicBool::new(false);
+        let chunks: Vec<ChunkFill> = (0..len.div_ceil(chunk_len))
+            .into_par_iter()
+            .map(|chunk| {
+                if aborted.load(Ordering::Relaxed) {
+                    return ChunkFill::Skipped;
+                }
+                let range = chunk_range(chunk);
+                let start = range.start;
+                // A panic drops the elements of this chunk that were already initialized
+                let fill = AssertUnwindSafe(|| unsafe {
+                    init_slice_with(target.0.add(start), range.len(), |index| {
+                        func(start + index)
+                    })
+                });
+                match panic::catch_unwind(fill) {
+                    Ok(()) => ChunkFill::Filled,
+                    Err(cause) => {
+                        aborted.store(true, Ordering::Relaxed);
+                        ChunkFill::Panicked(cause)
+                    }
+                }
+            })
+            .collect();
+        if aborted.into_inner() {
+            let mut first_panic = None;
+            for (chunk, fill) in chunks.into_iter().enumerate() {
+                match fill {
+                    ChunkFill::Filled => {
+                        let range = chunk_range(chunk);
+                        let filled =
+                            unsafe { slice::from_raw_parts_mut(ptr.add(range.start), range.len()) };
+                        // Any panics from the drops are discarded in favor of the first one
+                        let _ = panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+                            ptr::drop_in_place(filled)
+                        }));
+                    }
+                    ChunkFill::Skipped => {}
+                    ChunkFill::Panicked(cause) => {
+                        first_panic.get_or_insert(cause);
+                    }
+                }
+            }
+            panic::resume_unwind(first_panic.unwrap());
+        }
+        unsafe {
+            let target = slice::from_raw_parts_mut(ptr, len);
+            self.dynamic_drop_slice(target);
+            target
+        }
+    }
+    /// Drop the arena, running its drop functions in parallel on the rayon thread pool.
+    ///
+    /// This is only available for `Sendable` arenas,
+    /// since every registered value is `Send` and can be dropped on any thread.
+    /// The drop functions run in an unspecified order,
+    /// so this should only be used when the values don't depend on each other.
+    ///
+    /// If any of the drop functions panic, the rest are still run,
+    /// and then the first panic is resumed once everything has been dropped.
+    pub fn drop_parallel(mut self) {
+        // The memory is only freed (when `self` is dropped) once all the drops are finished
+        self.run_drops_parallel();
+    }
+    /// Run the drop functions on the rayon thread pool, removing them from the list
+    ///
+    /// The list stays locked while the drops run, so destructors that try to register
+    /// more values with the arena panic instead of corrupting it (see `TEARDOWN_REGISTRATION`).
+    fn run_drops_parallel(&mut self) {
+        let lock = self.items.lock();
+        let items = self.items.split_off(0);
+        let first_panic: Mutex<Option<Box<dyn Any + Send>>> = Mutex::new(None);
+        items.into_par_iter().for_each(|item| {
+            if let Err(cause) = panic::catch_unwind(AssertUnwindSafe(|| drop(item))) {
+                let mut first_panic = first_panic.lock().unwrap_or_else(PoisonError::into_inner);
+                first_panic.get_or_insert(cause);
+            }
+        });
+        drop(lock);
+        let first_panic = first_panic
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(cause) = first_panic {
+            panic::resume_unwind(cause);
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use crate::test_util::PanicsOnDrop;
+    use crate::CollectIn;
+    use std::sync::atomic::AtomicUsize;
+
+    const COUNT: usize = 1_000_000;
+
+    #[test]
+    fn parallel_fill() {
+        let arena = DynamicArena::new_send();
+        let expected: &[String] = (0..100_000)
+            .map(|index| format!("element{}", index * 7))
+            .collect_in(&arena);
+        let filled =
+            arena.par_alloc_slice_fill_with(100_000, |index| format!("element{}", index * 7));
+        assert_eq!(filled, expected);
+        assert!(arena.par_alloc_slice_fill_with(0, |_| 0u8).is_empty());
+        // Both slices are registered with a single drop each
+        assert_eq!(arena.pending_drop_count(), 2);
+        arena.verify().unwrap();
+    }
+    #[test]
+    fn fill_panic() {
+        let counter = AtomicUsize::new(0);
+        let arena = DynamicArena::new_send();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            arena.par_alloc_slice_fill_with(COUNT, |index| {
+                if index == COUNT / 2 {
+                    panic!("Expected");
+                }
+                PanicsOnDrop(&counter, false)
+            });
+        }));
+        assert_eq!(*result.unwrap_err().downcast::<&str>().unwrap(), "Expected");
+        // Every element that was initialized has already been dropped
+        let created = counter.load(Ordering::SeqCst);
+        assert!(created < COUNT);
+        assert_eq!(arena.pending_drop_count(), 0);
+        drop(arena);
+        assert_eq!(counter.load(Ordering::SeqCst), created);
+    }
+
+    #[test]
+    fn exactly_once() {
+        let counter = AtomicUsize::new(0);
+        let arena = DynamicArena::new_send();
+        for _ in 0..COUNT {
+            arena.alloc(PanicsOnDrop(&counter, false));
+        }
+        arena.drop_parallel();
+        assert_eq!(counter.load(Ordering::SeqCst), COUNT);
+    }
+    #[test]
+    fn panics_collected() {
+        let counter = AtomicUsize::new(0);
+        let arena = DynamicArena::new_send();
+        for index in 0..10_000 {
+            arena.alloc(PanicsOnDrop(&counter, index % 1000 == 0));
+        }
+        let result = panic::catch_unwind(AssertUnwindSafe(|| arena.drop_parallel()));
+        assert_eq!(*result.unwrap_err().downcast::<&str>().unwrap(), "Expected");
+        assert_eq!(counter.load(Ordering::SeqCst), 10_000);
+    }
+    /// Tries to register a value in its own arena, on whichever thread drops it
+    struct Reentrant<'a> {
+        arena: *const DynamicArena<'a, Sendable>,
+        rejected: &'a AtomicUsize,
+    }
+    unsafe impl Send for Reentrant<'_> {}
+    impl Drop for Reentrant<'_> {
+        fn drop(&mut self) {
+            let arena = unsafe { &*self.arena };
+            let attempt = panic::catch_unwind(AssertUnwindSafe(|| {
+                arena.alloc(String::from("late"));
+            }));
+            let cause = attempt.unwrap_err();
+            assert_eq!(
+                *cause.downcast::<String>().unwrap(),
+                crate::TEARDOWN_REGISTRATION
+            );
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    #[test]
+    // The destructor reaches the arena through a pointer while it's mutably borrowed,
+    // which is what the lock guards against, but it's still an aliasing violation under Miri
+    #[cfg_attr(miri, ignore)]
+    fn registration_during_teardown() {
+        let (counter, rejected) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let mut arena = DynamicArena::new_send();
+        for _ in 0..1000 {
+            arena.alloc(PanicsOnDrop(&counter, false));
+        }
+        // Only a single destructor touches the arena, so they can't race with each other
+        let pointer: *const DynamicArena<'_, Sendable> = &arena;
+        arena.alloc(Reentrant {
+            arena: pointer,
+            rejected: &rejected,
+        });
+        arena.run_drops_parallel();
+        assert_eq!(rejected.load(Ordering::SeqCst), 1);
+        assert_eq!(counter.load(Ordering::SeqCst), 1000);
+        assert_eq!(arena.pending_drop_count(), 0);
+    }
+}
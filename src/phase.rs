@@ -0,0 +1,158 @@
+//! Ordered phases of teardown, so groups of values can be dropped before others.
+use crate::{DynamicArena, DynamicArenaItem, ItemBound};
+
+/// The phase that values are dropped in, unless another one is specified
+///
+/// This is in the middle, so values can be dropped either before or after
+/// everything allocated with the normal methods.
+pub const DEFAULT_DROP_PHASE: u8 = 128;
+
+/// Sort the specified items into the order they should be dropped
+///
+/// The items are reversed before the stable sort,
+/// so the items within a phase are in reverse registration order.
+#[inline]
+pub(crate) fn sorted_by_phase(mut items: Vec<DynamicArenaItem>) -> Vec<DynamicArenaItem> {
+    items.reverse();
+    items.sort_by_key(|item| item.phase);
+    items
+}
+/// Sort the specified items by phase, and then by ascending address within each phase
+///
+/// This is used for address-ordered teardown (see `DynamicArenaBuilder::address_ordered_teardown`).
+#[inline]
+pub(crate) fn sorted_by_address(mut items: Vec<DynamicArenaItem>) -> Vec<DynamicArenaItem> {
+    items.sort_unstable_by_key(|item| (item.phase, item.value as usize));
+    items
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Dynamically drop the specified value in the specified phase of teardown.
+    ///
+    /// ## Safety
+    /// This has the same requirements as `dynamic_drop`.
+    #[inline]
+    pub unsafe fn dynamic_drop_in_phase<T: ?Sized>(&self, phase: u8, value: *mut T) {
+        self.register_drop(value, None, phase);
+    }
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    unsafe fn alloc_in_phase_unchecked<T>(&self, phase: u8, value: T) -> &mut T {
+        let target = self.alloc_registered(value, None, phase);
+        &mut *target
+    }
+}
+impl<'a, S> DynamicArena<'a, S> {
+    /// Allocate the specified value, to be dropped in the specified phase of teardown.
+    ///
+    /// When the arena is dropped or reset (or rolled back to a checkpoint),
+    /// all the values in phase 0 are dropped first, then everything in phase 1, and so on.
+    /// Within each phase, values are dropped in reverse registration order
+    /// (or by address, see `DynamicArenaBuilder::address_ordered_teardown`).
+    /// The normal methods (like `alloc`) use `DEFAULT_DROP_PHASE`.
+    /// Incremental teardown with `drop_some` ignores the phases.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_in_phase<T: 'a>(&self, phase: u8, value: T) -> &mut T
+    where
+        S: ItemBound<T>,
+    {
+        unsafe { self.alloc_in_phase_unchecked(phase, value) }
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+
+    struct DropOrder<'a>(&'a RefCell<Vec<&'static str>>, &'static str);
+    impl<'a> Drop for DropOrder<'a> {
+        fn drop(&mut self) {
+            self.0.borrow_mut().push(self.1);
+        }
+    }
+
+    #[test]
+    fn interleaved() {
+        let order = RefCell::new(Vec::new());
+        let arena = DynamicArena::new_bounded();
+        arena.alloc_in_phase(200, DropOrder(&order, "ffi-1"));
+        arena.alloc(DropOrder(&order, "cache-1"));
+        arena.alloc_in_phase(0, DropOrder(&order, "user-1"));
+        arena.alloc_in_phase(200, DropOrder(&order, "ffi-2"));
+        arena.alloc_in_phase(0, DropOrder(&order, "user-2"));
+        arena.alloc_in_phase(DEFAULT_DROP_PHASE, DropOrder(&order, "cache-2"));
+        unsafe {
+            let late = arena.alloc_unchecked(DropOrder(&order, "ffi-3"));
+            arena.dynamic_drop_in_phase(u8::MAX, late);
+        }
+        arena.verify().unwrap();
+        drop(arena);
+        assert_eq!(
+            *order.borrow(),
+            vec!["user-2", "user-1", "cache-2", "cache-1", "ffi-2", "ffi-1", "ffi-3"]
+        );
+    }
+    #[test]
+    fn rollback_and_reset() {
+        let order = RefCell::new(Vec::new());
+        let mut arena = DynamicArena::new_bounded();
+        arena.alloc_in_phase(200, DropOrder(&order, "kept-late"));
+        arena.alloc(DropOrder(&order, "kept"));
+        let checkpoint = arena.checkpoint();
+        arena.alloc_in_phase(200, DropOrder(&order, "late"));
+        arena.alloc_in_phase(10, DropOrder(&order, "early"));
+        arena.rollback_to(checkpoint);
+        assert_eq!(*order.borrow(), vec!["early", "late"]);
+        arena.verify().unwrap();
+        arena.reset();
+        assert_eq!(*order.borrow(), vec!["early", "late", "kept", "kept-late"]);
+    }
+    struct Logged<'a, T>(&'a RefCell<Vec<(u8, usize)>>, u8, T);
+    impl<'a, T> Drop for Logged<'a, T> {
+        fn drop(&mut self) {
+            let address = self as *mut Self as usize;
+            self.0.borrow_mut().push((self.1, address));
+        }
+    }
+
+    #[test]
+    fn address_ordered() {
+        let count = if cfg!(miri) { 300 } else { 300_000 };
+        let log = RefCell::new(Vec::new());
+        let mut arena = DynamicArena::builder()
+            .address_ordered_teardown(true)
+            .build();
+        for round in 0..2 {
+            for index in 0..count {
+                let phase = if index % 100 == 0 {
+                    0
+                } else {
+                    DEFAULT_DROP_PHASE
+                };
+                match index % 3 {
+                    0 => drop(arena.alloc_in_phase(phase, Logged(&log, phase, index as u8))),
+                    1 => drop(arena.alloc_in_phase(phase, Logged(&log, phase, [index; 3]))),
+                    _ => drop(arena.alloc_in_phase(phase, Logged(&log, phase, index.to_string()))),
+                }
+            }
+            if round == 0 {
+                arena.reset();
+            }
+        }
+        drop(arena);
+        let log = log.into_inner();
+        assert_eq!(log.len(), count * 2);
+        // Each teardown drops every value exactly once, by phase and then by address
+        for teardown in log.chunks(count) {
+            let mut sorted = teardown.to_vec();
+            sorted.sort_unstable();
+            assert_eq!(teardown, &sorted[..]);
+            sorted.dedup();
+            assert_eq!(sorted.len(), count);
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Pools of reusable arenas.
+use std::fmt::{self, Debug};
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, MutexGuard, PoisonError, TryLockError};
+
+use crate::{DynamicArena, NonSend, SendAbility};
+
+/// A pool of pre-warmed arenas, which are reused across jobs (like requests).
+///
+/// An arena is checked out with `get`, and when the resulting guard is dropped
+/// the arena is reset (running all its registered drops) and returned to the pool.
+/// Since resetting keeps the arena's largest chunk of memory,
+/// the next checkout doesn't need to go back to the allocator.
+///
+/// To avoid retaining unbounded amounts of memory,
+/// the pool caps both the number of arenas and total bytes it holds onto.
+/// Arenas that would exceed these limits are simply dropped.
+///
+/// A pool of `Sendable` arenas is `Sync`, so it can be shared across worker threads.
+pub struct ArenaPool<'a, S = NonSend> {
+    arenas: Mutex<Vec<DynamicArena<'a, S>>>,
+    max_arenas: usize,
+    max_retained_bytes: usize,
+}
+impl<'a, S> ArenaPool<'a, S> {
+    /// Create an empty pool, which retains at most the specified number of arenas and bytes
+    pub fn new(max_arenas: usize, max_retained_bytes: usize) -> Self {
+        ArenaPool {
+            arenas: Mutex::new(Vec::new()),
+            max_arenas,
+            max_retained_bytes,
+        }
+    }
+    /// The number of idle arenas currently retained by the pool
+    #[inline]
+    pub fn retained_arenas(&self) -> usize {
+        self.lock().len()
+    }
+    /// The total number of bytes allocated by the idle arenas in the pool
+    #[inline]
+    pub fn retained_bytes(&self) -> usize {
+        retained_bytes(&self.lock())
+    }
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, Vec<DynamicArena<'a, S>>> {
+        // The arenas are always reset before they're returned, so poisoning is harmless
+        self.arenas.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    /// Return an already reset arena to the pool, unless it's full
+    pub(crate) fn release(&self, arena: DynamicArena<'a, S>) {
+        let mut arenas = self.lock();
+        let bytes = arena.as_bumpalo().allocated_bytes();
+        if arenas.len() < self.max_arenas
+            && retained_bytes(&arenas).saturating_add(bytes) <= self.max_retained_bytes
+        {
+            arenas.push(arena);
+        } else {
+            // Avoid freeing the memory while we hold the lock
+            drop(arenas);
+            drop(arena);
+        }
+    }
+}
+impl<'a, S: SendAbility> ArenaPool<'a, S> {
+    /// Check out an arena from the pool, creating a new one if the pool is empty
+    pub fn get(&self) -> PooledArena<'_, 'a, S> {
+        PooledArena {
+            pool: self,
+            arena: ManuallyDrop::new(self.take()),
+        }
+    }
+    /// Remove an arena from the pool, creating a new one if the pool is empty
+    pub(crate) fn take(&self) -> DynamicArena<'a, S> {
+        let existing = self.lock().pop();
+        existing.unwrap_or_else(S::create_arena)
+    }
+}
+impl<'a, S> Debug for ArenaPool<'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ArenaPool");
+        let arenas = match self.arenas.try_lock() {
+            Ok(arenas) => Some(arenas),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        };
+        match arenas {
+            Some(arenas) => debug.field("retained_arenas", &arenas.len()),
+            None => debug.field("retained_arenas", &format_args!("<locked>")),
+        };
+        debug
+            .field("max_arenas", &self.max_arenas)
+            .field("max_retained_bytes", &self.max_retained_bytes)
+            .finish()
+    }
+}
+#[inline]
+fn retained_bytes<S>(arenas: &[DynamicArena<'_, S>]) -> usize {
+    arenas
+        .iter()
+        .map(|arena| arena.as_bumpalo().allocated_bytes())
+        .sum()
+}
+
+/// An arena that has been checked out from an `ArenaPool`.
+///
+/// This dereferences to the arena itself.
+/// When it's dropped, the arena is reset and returned to the pool.
+pub struct PooledArena<'p, 'a, S> {
+    pool: &'p ArenaPool<'a, S>,
+    arena: ManuallyDrop<DynamicArena<'a, S>>,
+}
+impl<'p, 'a, S> Deref for PooledArena<'p, 'a, S> {
+    type Target = DynamicArena<'a, S>;
+    #[inline]
+    fn deref(&self) -> &DynamicArena<'a, S> {
+        &self.arena
+    }
+}
+impl<'p, 'a, S> DerefMut for PooledArena<'p, 'a, S> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut DynamicArena<'a, S> {
+        &mut self.arena
+    }
+}
+impl<'p, 'a, S> Debug for PooledArena<'p, 'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PooledArena").field(&*self.arena).finish()
+    }
+}
+impl<'p, 'a, S> Drop for PooledArena<'p, 'a, S> {
+    fn drop(&mut self) {
+        let mut arena = unsafe { ManuallyDrop::take(&mut self.arena) };
+        arena.reset();
+        self.pool.release(arena);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use crate::test_util::{current_chunk, DropCounted};
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use crate::Sendable;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::sync::Arc;
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn reused_chunks() {
+        let pool = ArenaPool::<NonSend>::new(4, 1 << 20);
+        let first = {
+            let arena = pool.get();
+            arena.alloc(String::from("request"));
+            current_chunk(&arena)
+        };
+        assert_eq!(pool.retained_arenas(), 1);
+        assert!(pool.retained_bytes() > 0);
+        let arena = pool.get();
+        assert_eq!(pool.retained_arenas(), 0);
+        assert_eq!(current_chunk(&arena), first);
+    }
+    #[test]
+    fn limits() {
+        let pool = ArenaPool::<NonSend>::new(2, 1 << 20);
+        let arenas = (0..3).map(|_| pool.get()).collect::<Vec<_>>();
+        for arena in &arenas {
+            arena.alloc_copy(0u8);
+        }
+        drop(arenas);
+        assert_eq!(pool.retained_arenas(), 2);
+        let small = ArenaPool::<NonSend>::new(8, 1024);
+        {
+            let arena = small.get();
+            arena.alloc_slice_fill_copy(4096, 0u8);
+        }
+        assert_eq!(small.retained_arenas(), 0);
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn shared_workers() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let pool = ArenaPool::<Sendable>::new(4, 1 << 20);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..25 {
+                        let arena = pool.get();
+                        arena.alloc(DropCounted(Arc::clone(&counter)));
+                    }
+                });
+            }
+        });
+        // Every arena was reset when it was returned
+        assert_eq!(counter.load(Ordering::SeqCst), 100);
+        assert!(pool.retained_arenas() <= 4);
+    }
+}
@@ -0,0 +1,184 @@
+//! Profiling the destructors run at teardown, enabled by the `drop-profile` feature.
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::os::raw::c_void;
+use std::time::{Duration, Instant};
+
+use crate::teardown::DropPanic;
+use crate::{phase, DynamicArena};
+
+/// The number of consecutive drops of the same type that share a single clock read
+const BATCH_SIZE: usize = 1024;
+
+/// The time spent dropping the values of a single type, as reported by `DropProfile`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DroppedType {
+    type_name: &'static str,
+    count: usize,
+    duration: Duration,
+}
+impl DroppedType {
+    /// The name of the type (from `std::any::type_name`)
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+    /// The number of values that were dropped
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+    /// The total time spent running their destructors
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+/// The time spent running destructors when an arena was dropped,
+/// as returned by `DynamicArena::drop_profiled`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DropProfile {
+    types: Vec<DroppedType>,
+    total: Duration,
+}
+impl DropProfile {
+    /// The dropped types, slowest first
+    #[inline]
+    pub fn types(&self) -> &[DroppedType] {
+        &self.types
+    }
+    /// The total time spent running destructors
+    #[inline]
+    pub fn total_duration(&self) -> Duration {
+        self.total
+    }
+}
+
+/// The names of the types registered to be dropped, keyed by their drop functions
+#[derive(Default)]
+pub(crate) struct DropNames {
+    names: RefCell<HashMap<usize, &'static str>>,
+    /// The most recently registered drop function, to skip the lookup for runs of a single type
+    last: Cell<usize>,
+}
+impl DropNames {
+    #[inline]
+    pub(crate) fn record<T: ?Sized>(&self, drop: unsafe fn(*mut c_void)) {
+        let key = drop as usize;
+        if self.last.get() != key {
+            self.last.set(key);
+            self.names
+                .borrow_mut()
+                .entry(key)
+                .or_insert_with(std::any::type_name::<T>);
+        }
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Drop the arena, measuring the time spent in the destructors of each type
+    ///
+    /// The values are dropped in the same order as normal,
+    /// with consecutive values of the same type timed in batches to keep the clock reads cheap.
+    /// Types are identified by their drop functions,
+    /// so types whose destructors were merged by the compiler share the first type's name.
+    ///
+    /// With the `tracing` feature, dropping an arena normally also profiles it,
+    /// emitting a `drop_profiled` event for each type.
+    pub fn drop_profiled(mut self) -> DropProfile {
+        // Items must be dropped before the arena
+        self.discard_finalizers();
+        self.run_profiled_drops()
+    }
+    pub(crate) fn run_profiled_drops(&mut self) -> DropProfile {
+        let items = self.items.split_off(0);
+        #[cfg(feature = "registry")]
+        self.publish_items(0);
+        // The list stays locked, so destructors can't register more values
+        let lock = self.items.lock();
+        let start = Instant::now();
+        let mut durations: HashMap<usize, (usize, Duration)> = HashMap::new();
+        // If a destructor panics, the rest are still dropped (and timed)
+        let mut first_panic = DropPanic::default();
+        let items = if self.address_ordered {
+            phase::sorted_by_address(items)
+        } else {
+            phase::sorted_by_phase(items)
+        };
+        let mut items = items.into_iter().peekable();
+        while let Some(first) = items.next() {
+            let key = first.drop as usize;
+            let batch_start = Instant::now();
+            // Entries can cover runs of values, which are all counted
+            let mut count = first.len();
+            first_panic.drop_item(first);
+            while count < BATCH_SIZE {
+                match items.next_if(|item| item.drop as usize == key) {
+                    Some(item) => {
+                        count += item.len();
+                        first_panic.drop_item(item);
+                    }
+                    None => break,
+                }
+            }
+            let entry = durations.entry(key).or_default();
+            entry.0 += count;
+            entry.1 += batch_start.elapsed();
+        }
+        drop(lock);
+        let names = self.drop_names.names.borrow();
+        let mut types: Vec<DroppedType> = durations
+            .into_iter()
+            .map(|(key, (count, duration))| DroppedType {
+                type_name: names.get(&key).copied().unwrap_or("<unknown>"),
+                count,
+                duration,
+            })
+            .collect();
+        types.sort_by_key(|dropped| Reverse(dropped.duration));
+        first_panic.resume();
+        DropProfile {
+            types,
+            total: start.elapsed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Slow;
+    impl Drop for Slow {
+        fn drop(&mut self) {
+            std::thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    #[test]
+    fn per_type() {
+        let arena = DynamicArena::new();
+        for i in 0..3000 {
+            arena.alloc(i.to_string());
+        }
+        for _ in 0..5 {
+            arena.alloc(Slow);
+            arena.alloc(vec![1u8]);
+        }
+        arena.alloc_copy(7u32);
+        let profile = arena.drop_profiled();
+        let counts: Vec<_> = profile
+            .types()
+            .iter()
+            .map(|dropped| (dropped.type_name(), dropped.count()))
+            .collect();
+        assert_eq!(counts[0], ("dynamic_arena::profile::test::Slow", 5));
+        assert_eq!(profile.types().len(), 3);
+        assert!(counts.contains(&("alloc::string::String", 3000)));
+        assert!(counts.contains(&("alloc::vec::Vec<u8>", 5)));
+        assert!(profile.types()[0].duration() >= Duration::from_millis(10));
+        assert!(profile.total_duration() >= profile.types()[0].duration());
+    }
+}
@@ -0,0 +1,123 @@
+//! Allocation that returns raw pointers, for object graphs that alias freely.
+use std::ptr::NonNull;
+
+use crate::{DynamicArena, ItemBound, DEFAULT_DROP_PHASE};
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Allocate the specified value in this arena, returning a raw pointer to it
+    ///
+    /// This has the same bounds as `alloc`, and the value is dropped along with the arena.
+    /// The pointer is never derived from a `&mut T` (unlike the result of `alloc`),
+    /// so it has the provenance of the whole allocation
+    /// and any number of copies of it can be used to read and write the value.
+    ///
+    /// ## Aliasing
+    /// The usual rules apply to references created from the pointer:
+    /// a `&mut T` can't be used once it's been invalidated by another pointer or reference,
+    /// and a `&T` can't be used after a write through any of them.
+    /// Mixing this with the reference-returning methods is fine,
+    /// since they never touch an existing allocation.
+    /// The pointer is valid until the arena is reset or dropped.
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_raw<T: 'a>(&self, value: T) -> NonNull<T>
+    where
+        S: ItemBound<T>,
+    {
+        unsafe {
+            let target = self.alloc_registered(value, None, DEFAULT_DROP_PHASE);
+            #[cfg(feature = "debug-dump")]
+            self.dump.record_opaque::<T>(target);
+            NonNull::new_unchecked(target)
+        }
+    }
+    /// Allocate the specified copyable value in this arena, returning a raw pointer to it
+    ///
+    /// See `alloc_raw` for the aliasing rules.
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_raw_copy<T: Copy>(&self, value: T) -> NonNull<T>
+    where
+        S: ItemBound<T>,
+    {
+        unsafe {
+            let target = self.alloc_value_raw(value);
+            #[cfg(feature = "debug-dump")]
+            self.dump.record_opaque::<T>(target);
+            NonNull::new_unchecked(target)
+        }
+    }
+    /// Register the drop function for the value behind the specified pointer,
+    /// invoking it when the arena is dropped
+    ///
+    /// This is the same as `dynamic_drop`, for values written through raw pointers.
+    ///
+    /// ## Safety
+    /// See `dynamic_drop`.
+    #[inline]
+    pub unsafe fn register_drop_raw<T: ?Sized>(&self, value: NonNull<T>) {
+        self.dynamic_drop(value.as_ptr())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::alloc::Layout;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::rc::Rc;
+
+    struct Node {
+        value: u32,
+        next: Option<NonNull<Node>>,
+    }
+
+    #[test]
+    fn aliased_pointers() {
+        let arena: DynamicArena = DynamicArena::new();
+        let first = arena.alloc_raw(Node {
+            value: 1,
+            next: None,
+        });
+        let second = arena.alloc_raw(Node {
+            value: 2,
+            next: Some(first),
+        });
+        unsafe {
+            // A cycle, with every node reachable from several pointers
+            (*first.as_ptr()).next = Some(second);
+            for _ in 0..3 {
+                let short: &Node = second.as_ref();
+                let next = short.next.unwrap();
+                assert_eq!(next.as_ref().value, 1);
+                // Writing through the other pointer invalidates `short`, which isn't used again
+                (*next.as_ptr()).next.unwrap().as_mut().value += 10;
+            }
+            assert_eq!(second.as_ref().value, 32);
+            let counter = arena.alloc_raw_copy(0u64);
+            let alias = counter;
+            *counter.as_ptr() += 1;
+            *alias.as_ptr() += 1;
+            assert_eq!(*counter.as_ref(), 2);
+        }
+    }
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn registered_raw() {
+        let shared = Rc::new(());
+        {
+            let arena: DynamicArena = DynamicArena::new();
+            let owned = arena.alloc_raw(Rc::clone(&shared));
+            unsafe {
+                assert!(Rc::ptr_eq(owned.as_ref(), &shared));
+                let raw = arena.alloc_layout(Layout::new::<Rc<()>>()).cast::<Rc<()>>();
+                raw.as_ptr().write(Rc::clone(&shared));
+                arena.register_drop_raw(raw);
+            }
+            assert_eq!(Rc::strong_count(&shared), 3);
+        }
+        assert_eq!(Rc::strong_count(&shared), 1);
+    }
+}
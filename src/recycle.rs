@@ -0,0 +1,186 @@
+//! Opt-in recycling of deallocated memory, using size-class free lists.
+use std::alloc::Layout;
+use std::cell::Cell;
+use std::mem;
+use std::ptr::{self, NonNull};
+
+use crate::DynamicArena;
+
+/// The size of each word, which is the granularity of the size classes
+const WORD: usize = mem::size_of::<usize>();
+/// The number of size classes, so the largest recycled block is 32 words
+const SIZE_CLASSES: usize = 32;
+
+/// A freed block, whose memory holds the link to the next free block of the same class
+struct FreeBlock {
+    next: *mut FreeBlock,
+}
+
+/// Singly-linked free lists for each size class of word-aligned blocks
+///
+/// The links are stored in the freed memory itself,
+/// so the only overhead is the head of each list.
+pub(crate) struct FreeLists {
+    heads: [Cell<*mut FreeBlock>; SIZE_CLASSES],
+}
+/// The blocks are only ever accessed through the arena,
+/// which is only `Send` if the values that used to be there were.
+unsafe impl Send for FreeLists {}
+impl FreeLists {
+    fn new() -> Self {
+        FreeLists {
+            heads: [(); SIZE_CLASSES].map(|()| Cell::new(ptr::null_mut())),
+        }
+    }
+    /// Add the specified block (with nothing registered to drop) to its free list
+    ///
+    /// Blocks that are too small or misaligned to hold a link are left alone.
+    unsafe fn push(&self, block: *mut u8, layout: Layout) {
+        let words = layout.size() / WORD;
+        if words == 0 || block as usize & (WORD - 1) != 0 {
+            return;
+        }
+        // Larger blocks are still useful for the largest class
+        let head = &self.heads[words.min(SIZE_CLASSES) - 1];
+        let block = block.cast::<FreeBlock>();
+        block.write(FreeBlock { next: head.get() });
+        head.set(block);
+    }
+    /// Take a block from the free list that fits the layout, if there's one available
+    #[inline]
+    fn pop(&self, layout: Layout) -> Option<NonNull<u8>> {
+        if layout.align() > WORD || layout.size() == 0 || layout.size() > SIZE_CLASSES * WORD {
+            return None;
+        }
+        let head = &self.heads[layout.size().div_ceil(WORD) - 1];
+        let block = NonNull::new(head.get())?;
+        head.set(unsafe { block.as_ref().next });
+        Some(block.cast())
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Enable or disable recycling of memory freed by `dealloc_last`.
+    ///
+    /// Normally, deallocating anything except the most recent allocation
+    /// wastes its bytes until the arena is reset.
+    /// While recycling is enabled, those blocks are instead added to a free list for their size,
+    /// and later allocations with a matching layout reuse them before bumping.
+    /// This helps long-lived arenas with lots of temporaries.
+    ///
+    /// Only blocks of up to 32 words (with at most word alignment) are recycled.
+    /// This is off by default, to keep the allocation fast path minimal.
+    /// Disabling it forgets any free blocks.
+    pub fn set_recycling(&mut self, enabled: bool) {
+        self.recycler = if enabled {
+            self.recycler
+                .take()
+                .or_else(|| Some(Box::new(FreeLists::new())))
+        } else {
+            None
+        };
+    }
+    /// Whether recycling of freed memory is enabled
+    #[inline]
+    pub fn is_recycling(&self) -> bool {
+        self.recycler.is_some()
+    }
+    /// Take a recycled block for the specified layout, if recycling is enabled
+    #[inline]
+    pub(crate) fn recycled_layout(&self, layout: Layout) -> Option<NonNull<u8>> {
+        match self.recycler {
+            Some(ref lists) => lists.pop(layout),
+            None => None,
+        }
+    }
+    /// Make the specified block available for reuse, if recycling is enabled
+    ///
+    /// ## Safety
+    /// The block must have been allocated in this arena with the specified layout,
+    /// and nothing in it can be registered to drop.
+    #[inline]
+    pub(crate) unsafe fn recycle_layout(&self, block: *mut u8, layout: Layout) {
+        if let Some(ref lists) = self.recycler {
+            lists.push(block, layout);
+        }
+    }
+    /// Forget all the free blocks, since the memory is about to be reset
+    #[inline]
+    pub(crate) fn clear_free_lists(&mut self) {
+        if self.recycler.is_some() {
+            self.recycler = Some(Box::new(FreeLists::new()));
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use std::cell::Cell;
+
+    struct Node<'a> {
+        _payload: [u64; 4],
+        drops: &'a Cell<u32>,
+    }
+    impl<'a> Drop for Node<'a> {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+        }
+    }
+
+    /// Repeatedly allocate two nodes, then free both (the first one isn't the most recent)
+    /// Miri is too slow for the full amount
+    const ROUNDS: u32 = if cfg!(miri) { 200 } else { 10_000 };
+
+    fn churn<'a>(arena: &DynamicArena<'a>, drops: &'a Cell<u32>) {
+        for _ in 0..ROUNDS {
+            let first: *mut _ = arena.alloc(Node {
+                _payload: [0; 4],
+                drops,
+            });
+            let second: *mut _ = arena.alloc(Node {
+                _payload: [0; 4],
+                drops,
+            });
+            unsafe {
+                arena.dealloc_last(first);
+                arena.dealloc_last(second);
+            }
+        }
+    }
+
+    #[test]
+    fn bounded_growth() {
+        let drops = Cell::new(0);
+        let mut arena = DynamicArena::new_bounded();
+        arena.set_recycling(true);
+        churn(&arena, &drops);
+        assert!(arena.as_bumpalo().allocated_bytes() < 4096);
+        assert_eq!(arena.pending_drop_count(), 0);
+        assert_eq!(drops.get(), 2 * ROUNDS);
+        drop(arena);
+        // No stale drop entries were run for the recycled memory
+        assert_eq!(drops.get(), 2 * ROUNDS);
+    }
+    #[test]
+    fn disabled_by_default() {
+        let drops = Cell::new(0);
+        let mut arena = DynamicArena::new_bounded();
+        assert!(!arena.is_recycling());
+        churn(&arena, &drops);
+        assert!(arena.as_bumpalo().allocated_bytes() > ROUNDS as usize * 32);
+        arena.set_recycling(true);
+        arena.reset();
+        assert!(arena.is_recycling());
+        // Misaligned or oversized requests just bump
+        let lists = FreeLists::new();
+        let block = arena.alloc_copy([0u64; 64]).as_mut_ptr().cast::<u8>();
+        unsafe { lists.push(block, Layout::new::<[u64; 64]>()) };
+        assert_eq!(lists.pop(Layout::new::<[u64; 64]>()), None);
+        assert_eq!(lists.pop(Layout::from_size_align(8, 16).unwrap()), None);
+        assert_eq!(
+            lists.pop(Layout::new::<[u64; 32]>()).unwrap().as_ptr(),
+            block
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! Selective teardown of tagged regions, without resetting the whole arena.
+use crate::phase::sorted_by_phase;
+use crate::teardown::DropPanic;
+use crate::{DynamicArena, ItemBound, DEFAULT_DROP_PHASE};
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Dynamically drop the specified value as part of the region with the specified tag,
+    /// invoking the drop function when the region or the arena is dropped.
+    ///
+    /// ## Safety
+    /// This has the same requirements as `dynamic_drop`.
+    #[inline]
+    pub unsafe fn dynamic_drop_tagged<T: ?Sized>(&self, tag: u32, value: *mut T) {
+        self.register_drop(value, Some(tag), DEFAULT_DROP_PHASE);
+    }
+    /// Run and unregister the drop functions of every value in the region with the specified tag.
+    ///
+    /// The drop functions run in the same order they would when the arena is dropped.
+    /// The memory itself isn't reclaimed (it's only freed along with the arena),
+    /// and untagged values (or values with other tags) aren't affected.
+    /// Dropping a region that doesn't have any values (like one that was already dropped) does nothing,
+    /// although new values can be allocated in that region again afterwards.
+    ///
+    /// Checkpoints are unaffected, and still roll back everything else registered after them.
+    ///
+    /// Since this requires `&mut self`,
+    /// it's statically impossible for any references into the arena to be alive.
+    pub fn drop_region(&mut self, tag: u32) {
+        let dropped = self.items.extract(|item| item.has_tag(tag));
+        if !dropped.is_empty() {
+            self.handle_epoch.invalidate();
+        }
+        // The list stays locked, so destructors can't register more values
+        let lock = self.items.lock();
+        let mut first_panic = DropPanic::default();
+        first_panic.drop_all(sorted_by_phase(dropped));
+        drop(lock);
+        first_panic.resume();
+    }
+    /// The number of values that are registered to be dropped in the region with the specified tag
+    pub fn region_drop_count(&self, tag: u32) -> usize {
+        self.items
+            .iter()
+            .filter(|item| item.has_tag(tag))
+            .map(|item| item.len())
+            .sum()
+    }
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    unsafe fn alloc_tagged_unchecked<T>(&self, tag: u32, value: T) -> &mut T {
+        let target = self.alloc_registered(value, Some(tag), DEFAULT_DROP_PHASE);
+        &mut *target
+    }
+}
+impl<'a, S> DynamicArena<'a, S> {
+    /// Allocate the specified value in the region with the specified tag,
+    /// so it can be dropped early by `drop_region`.
+    ///
+    /// Otherwise, this is the same as `alloc`.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_tagged<T: 'a>(&self, tag: u32, value: T) -> &mut T
+    where
+        S: ItemBound<T>,
+    {
+        unsafe { self.alloc_tagged_unchecked(tag, value) }
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+
+    struct DropOrder<'a>(&'a RefCell<Vec<u32>>, u32);
+    impl<'a> Drop for DropOrder<'a> {
+        fn drop(&mut self) {
+            self.0.borrow_mut().push(self.1);
+        }
+    }
+
+    #[test]
+    fn selective() {
+        let order = RefCell::new(Vec::new());
+        let mut arena = DynamicArena::new_bounded();
+        arena.alloc(DropOrder(&order, 0));
+        arena.alloc_tagged(7, DropOrder(&order, 1));
+        arena.alloc_tagged(8, DropOrder(&order, 2));
+        arena.alloc_tagged(7, DropOrder(&order, 3));
+        arena.alloc(DropOrder(&order, 4));
+        assert_eq!(arena.region_drop_count(7), 2);
+        arena.drop_region(7);
+        assert_eq!(*order.borrow(), vec![3, 1]);
+        assert_eq!(arena.region_drop_count(7), 0);
+        // Dropping it again (or an unknown region) does nothing
+        arena.drop_region(7);
+        arena.drop_region(42);
+        assert_eq!(*order.borrow(), vec![3, 1]);
+        assert_eq!(arena.pending_drop_count(), 3);
+        arena.verify().unwrap();
+        drop(arena);
+        assert_eq!(*order.borrow(), vec![3, 1, 4, 2, 0]);
+    }
+    #[test]
+    fn reused_tag() {
+        let order = RefCell::new(Vec::new());
+        let mut arena = DynamicArena::new_bounded();
+        let checkpoint = arena.checkpoint();
+        for function in 0..3 {
+            arena.alloc_tagged(1, DropOrder(&order, function * 10));
+            arena.alloc_tagged(1, DropOrder(&order, function * 10 + 1));
+            arena.drop_region(1);
+        }
+        assert_eq!(*order.borrow(), vec![1, 0, 11, 10, 21, 20]);
+        assert_eq!(arena.pending_drop_count(), 0);
+        arena.alloc_tagged(1, DropOrder(&order, 100));
+        arena.rollback_to(checkpoint);
+        assert_eq!(order.borrow().last(), Some(&100));
+        arena.verify().unwrap();
+    }
+}
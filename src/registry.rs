@@ -0,0 +1,187 @@
+//! A process-wide registry of named arenas, enabled by the `registry` feature.
+//!
+//! Arenas are registered by naming them with `DynamicArenaBuilder::name`,
+//! and unregistered when they're dropped.
+//! Unnamed arenas aren't registered, so they don't pay for any of this.
+//!
+//! ````
+//! use dynamic_arena::{registry, DynamicArena};
+//! let arena = DynamicArena::builder().name("ast").build();
+//! arena.alloc(String::from("node"));
+//! let report = registry::snapshot()
+//!     .into_iter()
+//!     .find(|report| report.name() == "ast")
+//!     .unwrap();
+//! assert_eq!(report.item_count(), 1);
+//! ````
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use crate::DynamicArena;
+
+/// The usage of a named arena, as reported by `snapshot`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArenaReport {
+    name: &'static str,
+    allocated_bytes: usize,
+    chunk_count: usize,
+    item_count: usize,
+}
+impl ArenaReport {
+    /// The name the arena was built with
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+    /// The number of bytes allocated since the arena was last reset
+    ///
+    /// This counts the requested sizes, so it excludes any padding.
+    #[inline]
+    pub fn allocated_bytes(&self) -> usize {
+        self.allocated_bytes
+    }
+    /// The number of chunks the arena owns
+    #[inline]
+    pub fn chunk_count(&self) -> usize {
+        self.chunk_count
+    }
+    /// The number of values registered to be dropped
+    ///
+    /// This is updated when values are registered, and when the arena is reset or rolled back.
+    #[inline]
+    pub fn item_count(&self) -> usize {
+        self.item_count
+    }
+}
+
+/// Take a snapshot of the usage of every live named arena, in the order they were created
+pub fn snapshot() -> Vec<ArenaReport> {
+    lock()
+        .iter()
+        .filter_map(Weak::upgrade)
+        .map(|entry| ArenaReport {
+            name: entry.name,
+            allocated_bytes: entry.bytes.load(Ordering::Relaxed),
+            chunk_count: entry.chunks.load(Ordering::Relaxed),
+            item_count: entry.items.load(Ordering::Relaxed),
+        })
+        .collect()
+}
+
+static REGISTRY: Mutex<Vec<Weak<Entry>>> = Mutex::new(Vec::new());
+
+#[inline]
+fn lock() -> std::sync::MutexGuard<'static, Vec<Weak<Entry>>> {
+    // The entries are always consistent, so poisoning is harmless
+    REGISTRY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The usage published by a registered arena
+struct Entry {
+    name: &'static str,
+    bytes: AtomicUsize,
+    chunks: AtomicUsize,
+    items: AtomicUsize,
+}
+
+/// An arena's membership in the registry, which is removed when it's dropped
+pub(crate) struct Registration(Arc<Entry>);
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let entry = Arc::downgrade(&self.0);
+        lock().retain(|existing| !existing.ptr_eq(&entry));
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Add the arena to the registry under the specified name
+    pub(crate) fn register(&mut self, name: &'static str) {
+        let entry = Arc::new(Entry {
+            name,
+            bytes: AtomicUsize::new(0),
+            chunks: AtomicUsize::new(0),
+            items: AtomicUsize::new(0),
+        });
+        lock().push(Arc::downgrade(&entry));
+        self.registration = Some(Registration(entry));
+        self.publish_reset();
+    }
+    #[inline]
+    pub(crate) fn is_registered(&self) -> bool {
+        self.registration.is_some()
+    }
+    #[inline]
+    pub(crate) fn publish_bytes(&self, bytes: usize) {
+        if let Some(Registration(ref entry)) = self.registration {
+            entry.bytes.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+    #[inline]
+    pub(crate) fn publish_items(&self, items: usize) {
+        if let Some(Registration(ref entry)) = self.registration {
+            entry.items.store(items, Ordering::Relaxed);
+        }
+    }
+    pub(crate) fn publish_chunks(&self) {
+        if let Some(Registration(ref entry)) = self.registration {
+            let chunks = crate::stats::chunk_count(&self.handle);
+            entry.chunks.store(chunks, Ordering::Relaxed);
+        }
+    }
+    /// Publish the usage of a freshly reset arena
+    pub(crate) fn publish_reset(&self) {
+        if let Some(Registration(ref entry)) = self.registration {
+            entry.bytes.store(0, Ordering::Relaxed);
+            entry.items.store(0, Ordering::Relaxed);
+            self.publish_chunks();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn report(name: &str) -> Option<ArenaReport> {
+        snapshot().into_iter().find(|report| report.name() == name)
+    }
+
+    #[test]
+    fn three_arenas() {
+        let mut parser = DynamicArena::builder()
+            .name("test-parser")
+            .item_capacity(10)
+            .byte_capacity(1024)
+            .build();
+        let types = DynamicArena::builder().name("test-types").send().build();
+        let codegen = DynamicArena::builder().name("test-codegen").build();
+        let unnamed = DynamicArena::new();
+        for i in 0..10 {
+            parser.alloc(i.to_string());
+        }
+        types.alloc_slice_fill_copy(100_000, 0u8);
+        codegen.alloc_copy(5u64);
+        unnamed.alloc(String::from("unregistered"));
+        let names: Vec<_> = snapshot()
+            .into_iter()
+            .map(|report| report.name())
+            .filter(|name| name.starts_with("test-"))
+            .collect();
+        assert_eq!(names, vec!["test-parser", "test-types", "test-codegen"]);
+        let parsed = report("test-parser").unwrap();
+        assert_eq!(parsed.item_count(), 10);
+        assert_eq!(parsed.chunk_count(), 1);
+        assert!(parsed.allocated_bytes() >= 10 * std::mem::size_of::<String>());
+        let typed = report("test-types").unwrap();
+        assert!(typed.allocated_bytes() >= 100_000);
+        assert_eq!(typed.chunk_count(), 1);
+        assert_eq!(typed.item_count(), 0);
+        assert_eq!(report("test-codegen").unwrap().allocated_bytes(), 8);
+        parser.reset();
+        let reset = report("test-parser").unwrap();
+        assert_eq!((reset.allocated_bytes(), reset.item_count()), (0, 0));
+        drop(types);
+        assert!(report("test-types").is_none());
+        assert!(report("test-codegen").is_some());
+    }
+}
@@ -0,0 +1,148 @@
+//! Checkpoints for dropping everything allocated after a certain point.
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::DynamicArena;
+
+/// A point in the history of an arena's drop list,
+/// which can be rolled back to with `DynamicArena::rollback_to`.
+///
+/// This is created by `DynamicArena::checkpoint`.
+/// It refers to the registration order rather than the position in the list,
+/// so values that are removed from the middle of the list (by `dealloc_last` or `drop_region`)
+/// don't change which values it covers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    serial: u64,
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Record the current state of the arena, so it can be rolled back later
+    #[inline]
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            serial: self.items.next_serial(),
+        }
+    }
+    /// Drop everything that was allocated after the specified checkpoint.
+    ///
+    /// The drop functions registered after the checkpoint run in the same order
+    /// they would when the arena is dropped, and are removed from the list.
+    /// The memory itself isn't reclaimed (it's only freed along with the arena).
+    ///
+    /// Checkpoints can be nested, and rolling back to an earlier checkpoint
+    /// also drops everything after any later ones.
+    /// Rolling back to a checkpoint whose values were already dropped
+    /// (because an earlier checkpoint was rolled back, or the arena was reset)
+    /// only drops whatever was registered after it was taken.
+    ///
+    /// Since this requires `&mut self`,
+    /// it's statically impossible for any references into the arena to be alive.
+    #[inline]
+    pub fn rollback_to(&mut self, checkpoint: Checkpoint) {
+        let newer = self.items.count_since(checkpoint.serial);
+        self.truncate_drops(self.items.len() - newer);
+    }
+    /// Run the specified function,
+    /// then drop everything that it allocated in the arena.
+    ///
+    /// This is equivalent to taking a checkpoint, running the function,
+    /// and then rolling back to the checkpoint (even if the function panics).
+    /// Scopes can be nested, since the function has exclusive access to the arena.
+    /// None of the references the function obtains can escape from it,
+    /// since the result can't borrow from the arena.
+    pub fn scope<R>(&mut self, func: impl FnOnce(&mut Self) -> R) -> R {
+        let checkpoint = self.checkpoint();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| func(&mut *self)));
+        self.rollback_to(checkpoint);
+        match result {
+            Ok(value) => value,
+            Err(cause) => panic::resume_unwind(cause),
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+
+    struct Logged<'a>(u32, &'a RefCell<Vec<u32>>);
+    impl<'a> Drop for Logged<'a> {
+        fn drop(&mut self) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
+    #[test]
+    fn nested_rollback() {
+        let log = RefCell::new(Vec::new());
+        let mut arena = DynamicArena::new_bounded();
+        arena.alloc(Logged(0, &log));
+        let outer = arena.checkpoint();
+        arena.alloc(Logged(1, &log));
+        arena.alloc(Logged(2, &log));
+        let inner = arena.checkpoint();
+        arena.alloc(Logged(3, &log));
+        arena.rollback_to(inner);
+        assert_eq!(*log.borrow(), vec![3]);
+        arena.alloc(Logged(4, &log));
+        arena.rollback_to(outer);
+        assert_eq!(*log.borrow(), vec![3, 4, 2, 1]);
+        // Stale checkpoints do nothing
+        arena.rollback_to(inner);
+        assert_eq!(log.borrow().len(), 4);
+        arena.verify().unwrap();
+        drop(arena);
+        assert_eq!(*log.borrow(), vec![3, 4, 2, 1, 0]);
+    }
+    #[test]
+    fn nested_scopes() {
+        let log = RefCell::new(Vec::new());
+        let mut arena = DynamicArena::new_bounded();
+        let result = arena.scope(|arena| {
+            arena.alloc(Logged(1, &log));
+            let inner = arena.scope(|arena| arena.alloc(Logged(2, &log)).0 * 10);
+            assert_eq!(*log.borrow(), vec![2]);
+            inner + arena.alloc(Logged(3, &log)).0
+        });
+        assert_eq!(result, 23);
+        assert_eq!(*log.borrow(), vec![2, 3, 1]);
+        assert_eq!(arena.pending_drop_count(), 0);
+    }
+    #[test]
+    fn scope_panic() {
+        let log = RefCell::new(Vec::new());
+        let mut arena = DynamicArena::new_bounded();
+        arena.alloc(Logged(0, &log));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            arena.scope(|arena| {
+                arena.alloc(Logged(1, &log));
+                panic!("Speculation failed");
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(*log.borrow(), vec![1]);
+        arena.verify().unwrap();
+        drop(arena);
+        assert_eq!(*log.borrow(), vec![1, 0]);
+    }
+    #[test]
+    fn removed_before_checkpoint() {
+        let log = RefCell::new(Vec::new());
+        let mut arena = DynamicArena::new_bounded();
+        let removed: *mut _ = arena.alloc(Logged(0, &log));
+        arena.alloc_tagged(7, Logged(1, &log));
+        arena.alloc(Logged(2, &log));
+        let checkpoint = arena.checkpoint();
+        arena.alloc(Logged(3, &log));
+        arena.alloc(Logged(4, &log));
+        // Removing older values doesn't make the rollback skip any of the newer ones
+        unsafe { arena.dealloc_last(removed) };
+        arena.drop_region(7);
+        arena.rollback_to(checkpoint);
+        assert_eq!(*log.borrow(), vec![0, 1, 4, 3]);
+        arena.verify().unwrap();
+        drop(arena);
+        assert_eq!(*log.borrow(), vec![0, 1, 4, 3, 2]);
+    }
+}
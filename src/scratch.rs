@@ -0,0 +1,125 @@
+//! Thread-local scratch arenas.
+use std::cell::RefCell;
+use std::mem::ManuallyDrop;
+
+use crate::DynamicArena;
+
+/// The maximum number of idle scratch arenas kept per thread
+///
+/// This is the nesting depth that can be reached without allocating new arenas.
+const MAX_RETAINED: usize = 8;
+
+thread_local! {
+    static SCRATCH: RefCell<Vec<DynamicArena<'static>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Run the specified function with a temporary arena,
+/// which is reset once the function returns (or panics).
+///
+/// The arenas are cached in a thread-local,
+/// so repeated calls reuse the same memory instead of creating and destroying an arena each time.
+/// References can't escape the closure, since the arena is only borrowed for the duration of the call.
+///
+/// Nested calls are allowed, and each level gets its own arena from a small per-thread stack.
+/// Only the outermost few levels are retained for reuse,
+/// so deeply nested calls fall back to creating an ordinary arena.
+///
+/// ## Example
+/// ````
+/// let total = dynamic_arena::with_scratch(|arena| {
+///     let numbers = arena.alloc_slice_copy(&[1, 2, 3]);
+///     numbers.iter().sum::<u32>()
+/// });
+/// assert_eq!(total, 6);
+/// ````
+pub fn with_scratch<'a, R>(func: impl FnOnce(&DynamicArena<'a>) -> R) -> R {
+    let arena = SCRATCH
+        .try_with(|scratch| scratch.borrow_mut().pop())
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    let guard = ScratchGuard {
+        arena: ManuallyDrop::new(arena),
+    };
+    /*
+     * The arena is always empty when it's taken from the stack,
+     * and the guard runs all its drops before this function returns.
+     * Since the caller's lifetime `'a` outlives this call,
+     * everything in the arena is dropped while it's still valid.
+     */
+    let arena: *const DynamicArena<'static> = &*guard.arena;
+    let arena = unsafe { &*arena.cast::<DynamicArena<'a>>() };
+    func(arena)
+}
+
+struct ScratchGuard {
+    arena: ManuallyDrop<DynamicArena<'static>>,
+}
+impl Drop for ScratchGuard {
+    fn drop(&mut self) {
+        let mut arena = unsafe { ManuallyDrop::take(&mut self.arena) };
+        arena.reset();
+        // If the thread-local is being destroyed, the arena is just dropped
+        let _ = SCRATCH.try_with(|scratch| {
+            if let Ok(mut scratch) = scratch.try_borrow_mut() {
+                if scratch.len() < MAX_RETAINED {
+                    scratch.push(arena);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use crate::test_util::{current_chunk, DropCounted};
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn reset_between_calls() {
+        let counter = Cell::new(0);
+        for expected in 1..=3 {
+            let first = with_scratch(|arena| {
+                arena.alloc(DropCounted(&counter));
+                assert_eq!(counter.get(), expected - 1);
+                current_chunk(arena)
+            });
+            assert_eq!(counter.get(), expected);
+            // The same arena is reused each time
+            let second = with_scratch(|arena| current_chunk(arena));
+            assert_eq!(first, second);
+        }
+    }
+    #[test]
+    fn nested() {
+        let counter = Cell::new(0);
+        with_scratch(|outer| {
+            let value = outer.alloc_copy(7u32);
+            outer.alloc(DropCounted(&counter));
+            with_scratch(|inner| {
+                assert!(!std::ptr::eq(inner, outer));
+                inner.alloc(DropCounted(&counter));
+                inner.alloc_copy(0u32);
+            });
+            // Only the inner arena was reset
+            assert_eq!(counter.get(), 1);
+            assert_eq!(*value, 7);
+        });
+        assert_eq!(counter.get(), 2);
+    }
+    #[test]
+    fn panicking() {
+        let counter = Cell::new(0);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            with_scratch(|arena| {
+                arena.alloc(DropCounted(&counter));
+                panic!("Expected");
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(counter.get(), 1);
+        with_scratch(|arena| assert!(arena.alloc_copy(1u8) == &1));
+    }
+}
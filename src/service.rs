@@ -0,0 +1,250 @@
+//! Tower middleware that gives each request its own pooled arena.
+use std::future::Future;
+use std::mem::ManuallyDrop;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+
+use http::Request;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{ArenaPool, DynamicArena, Sendable};
+
+/// A tower `Layer` which checks out an arena from a pool for each request.
+///
+/// The arena is stored in the request's extensions as a `RequestArena`,
+/// and is reset and returned to the pool once the request is finished.
+/// Since futures can migrate across threads, this uses `Sendable` arenas.
+#[derive(Clone)]
+pub struct ArenaLayer {
+    pool: Arc<ArenaPool<'static, Sendable>>,
+}
+impl ArenaLayer {
+    /// Create a layer which takes its arenas from the specified pool
+    #[inline]
+    pub fn new(pool: Arc<ArenaPool<'static, Sendable>>) -> Self {
+        ArenaLayer { pool }
+    }
+    /// The pool the arenas are taken from
+    #[inline]
+    pub fn pool(&self) -> &Arc<ArenaPool<'static, Sendable>> {
+        &self.pool
+    }
+}
+impl<S> Layer<S> for ArenaLayer {
+    type Service = ArenaService<S>;
+    #[inline]
+    fn layer(&self, inner: S) -> ArenaService<S> {
+        ArenaService {
+            inner,
+            pool: Arc::clone(&self.pool),
+        }
+    }
+}
+
+/// A service which gives each request a `RequestArena`, created by `ArenaLayer`
+#[derive(Clone)]
+pub struct ArenaService<S> {
+    inner: S,
+    pool: Arc<ArenaPool<'static, Sendable>>,
+}
+impl<S, B> Service<Request<B>> for ArenaService<S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ArenaFuture<S::Future>;
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, mut request: Request<B>) -> ArenaFuture<S::Future> {
+        let arena = RequestArena::new(&self.pool);
+        request.extensions_mut().insert(arena.clone());
+        ArenaFuture {
+            inner: self.inner.call(request),
+            arena: Some(arena),
+        }
+    }
+}
+
+/// The response future of an `ArenaService`
+///
+/// This releases its handle to the request's arena once the response is ready.
+/// If it's dropped early (for example, if the request is cancelled),
+/// the handle is released along with it.
+pub struct ArenaFuture<F> {
+    inner: F,
+    arena: Option<RequestArena>,
+}
+impl<F: Future> Future for ArenaFuture<F> {
+    type Output = F::Output;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // The inner future is structurally pinned, but the arena isn't
+        let this = unsafe { self.get_unchecked_mut() };
+        let result = unsafe { Pin::new_unchecked(&mut this.inner) }.poll(cx);
+        if result.is_ready() {
+            this.arena = None;
+        }
+        result
+    }
+}
+
+/// A shared handle to the arena for a single request.
+///
+/// Clones refer to the same arena, which is only reset and returned to its pool
+/// once every handle has been dropped.
+/// This guarantees the arena is reset exactly once per request,
+/// even if the handler panics or its future is cancelled.
+///
+/// Allocations are synchronized with a lock, so the handle can be shared by the request's task.
+#[derive(Clone)]
+pub struct RequestArena {
+    inner: Arc<RequestArenaInner>,
+}
+impl RequestArena {
+    /// Check out a new arena from the specified pool
+    pub fn new(pool: &Arc<ArenaPool<'static, Sendable>>) -> Self {
+        RequestArena {
+            inner: Arc::new(RequestArenaInner {
+                arena: Mutex::new(ManuallyDrop::new(pool.take())),
+                pool: Arc::clone(pool),
+            }),
+        }
+    }
+    /// Allocate the specified value in the request's arena
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T: Send + 'static>(&self, value: T) -> &mut T {
+        self.with_arena(|arena| arena.alloc(value) as *mut T)
+    }
+    /// Allocate the specified copyable value in the request's arena
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_copy<T: Copy + Send>(&self, value: T) -> &mut T {
+        self.with_arena(|arena| arena.alloc_copy(value) as *mut T)
+    }
+    /// Allocate a copy of the specified string in the request's arena
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_str(&self, s: &str) -> &mut str {
+        self.with_arena(|arena| arena.alloc_str(s) as *mut str)
+    }
+    /// Allocate a copy of the specified slice in the request's arena
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy<T: Copy + Send>(&self, src: &[T]) -> &mut [T] {
+        self.with_arena(|arena| arena.alloc_slice_copy(src) as *mut [T])
+    }
+    /// Allocate with the locked arena, giving the result the lifetime of this handle.
+    ///
+    /// This is safe because allocations never overlap,
+    /// and the arena is only reset once every handle is gone.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    fn with_arena<T: ?Sized>(
+        &self,
+        func: impl FnOnce(&DynamicArena<'static, Sendable>) -> *mut T,
+    ) -> &mut T {
+        let arena = self
+            .inner
+            .arena
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        unsafe { &mut *func(&arena) }
+    }
+}
+
+struct RequestArenaInner {
+    pool: Arc<ArenaPool<'static, Sendable>>,
+    arena: Mutex<ManuallyDrop<DynamicArena<'static, Sendable>>>,
+}
+impl Drop for RequestArenaInner {
+    fn drop(&mut self) {
+        let arena = self.arena.get_mut().unwrap_or_else(PoisonError::into_inner);
+        let mut arena = unsafe { ManuallyDrop::take(arena) };
+        arena.reset();
+        self.pool.release(arena);
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use crate::test_util::DropCounted;
+    use std::convert::Infallible;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Waker;
+
+    enum Mode {
+        Respond,
+        Panic,
+        Pending,
+    }
+    struct Handler(Arc<AtomicUsize>);
+    impl Service<Request<Mode>> for Handler {
+        type Response = usize;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<usize, Infallible>> + Send>>;
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, request: Request<Mode>) -> Self::Future {
+            let counter = Arc::clone(&self.0);
+            Box::pin(async move {
+                let arena = request.extensions().get::<RequestArena>().unwrap().clone();
+                arena.alloc(DropCounted(counter));
+                let name = arena.alloc_str("handler");
+                match request.body() {
+                    Mode::Respond => Ok(name.len()),
+                    Mode::Panic => panic!("Expected"),
+                    Mode::Pending => std::future::pending().await,
+                }
+            })
+        }
+    }
+    fn setup() -> (
+        Arc<AtomicUsize>,
+        Arc<ArenaPool<'static, Sendable>>,
+        ArenaService<Handler>,
+    ) {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let pool = Arc::new(ArenaPool::new(4, 1 << 20));
+        let service = ArenaLayer::new(Arc::clone(&pool)).layer(Handler(Arc::clone(&counter)));
+        (counter, pool, service)
+    }
+    fn poll_once<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn reset_per_request() {
+        let (counter, pool, mut service) = setup();
+        for expected in 1..=3 {
+            let mut future = Box::pin(service.call(Request::new(Mode::Respond)));
+            assert!(matches!(poll_once(future.as_mut()), Poll::Ready(Ok(7))));
+            assert_eq!(counter.load(Ordering::SeqCst), expected);
+            assert_eq!(pool.retained_arenas(), 1);
+        }
+    }
+    #[test]
+    fn panic_and_cancel() {
+        let (counter, pool, mut service) = setup();
+        let mut future = Box::pin(service.call(Request::new(Mode::Panic)));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| poll_once(future.as_mut())));
+        assert!(result.is_err());
+        drop(future);
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.retained_arenas(), 1);
+        let mut future = Box::pin(service.call(Request::new(Mode::Pending)));
+        assert!(poll_once(future.as_mut()).is_pending());
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.retained_arenas(), 0);
+        drop(future);
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.retained_arenas(), 1);
+    }
+}
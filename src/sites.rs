@@ -0,0 +1,128 @@
+//! Allocation-site tracking, enabled by the `track-callers` feature.
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::panic::Location;
+
+use crate::DynamicArena;
+
+/// The allocations made from a single call site, as reported by `DynamicArena::allocation_sites`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AllocationSite {
+    location: &'static Location<'static>,
+    bytes: usize,
+    count: usize,
+    unchecked: bool,
+}
+impl AllocationSite {
+    /// The source location of the call to the allocation method
+    #[inline]
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+    /// The total number of bytes allocated from this site
+    #[inline]
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+    /// The number of allocations
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+    /// Whether this site called `alloc_unchecked`,
+    /// so its values are intentionally leaked instead of dropped
+    #[inline]
+    pub fn is_unchecked(&self) -> bool {
+        self.unchecked
+    }
+}
+
+/// The accounting for each call site
+#[derive(Default)]
+pub(crate) struct AllocationSites {
+    sites: RefCell<HashMap<Location<'static>, AllocationSite>>,
+}
+impl AllocationSites {
+    #[inline]
+    pub(crate) fn record(&self, location: &'static Location<'static>, bytes: usize) {
+        let mut sites = self.sites.borrow_mut();
+        let site = sites.entry(*location).or_insert(AllocationSite {
+            location,
+            bytes: 0,
+            count: 0,
+            unchecked: false,
+        });
+        site.bytes += bytes;
+        site.count += 1;
+    }
+    /// Flag a site that was already recorded as calling `alloc_unchecked`
+    #[inline]
+    pub(crate) fn mark_unchecked(&self, location: &'static Location<'static>) {
+        if let Some(site) = self.sites.borrow_mut().get_mut(location) {
+            site.unchecked = true;
+        }
+    }
+    pub(crate) fn clear(&self) {
+        self.sites.borrow_mut().clear();
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// The call sites that allocated from this arena, sorted with the most bytes first
+    ///
+    /// Every method that allocates from the arena's chunks is tracked
+    /// (but not memory reused by `set_recycling`).
+    /// The counts are cumulative across resets, until `reset_peaks` is called.
+    pub fn allocation_sites(&self) -> Vec<AllocationSite> {
+        let mut sites: Vec<_> = self
+            .allocation_sites
+            .sites
+            .borrow()
+            .values()
+            .copied()
+            .collect();
+        sites.sort_by_key(|site| Reverse(site.bytes));
+        sites
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::CollectIn;
+
+    #[test]
+    fn caller_locations() {
+        let mut arena = DynamicArena::new();
+        let line = line!() + 2;
+        for _ in 0..4 {
+            arena.alloc([0u64; 16]);
+        }
+        arena.alloc_str("abc");
+        let _: &[u32] = (0..10).filter(|&i| i > 2).collect_in(&arena);
+        arena.reset();
+        let sites = arena.allocation_sites();
+        assert_eq!(sites.len(), 3);
+        assert_eq!(sites[0].location().file(), file!());
+        assert_eq!(sites[0].location().line(), line);
+        assert_eq!(sites[0].bytes(), 4 * 128);
+        assert_eq!(sites[0].count(), 4);
+        assert_eq!(sites[1].location().line(), line + 3);
+        assert_eq!(sites[1].bytes(), 7 * 4);
+        assert_eq!(sites[2].location().line(), line + 2);
+        assert!(sites.iter().all(|site| !site.is_unchecked()));
+        arena.reset_peaks();
+        assert!(arena.allocation_sites().is_empty());
+    }
+    #[test]
+    fn unchecked_flagged() {
+        let arena = DynamicArena::new();
+        arena.alloc_copy(1u32);
+        unsafe { arena.alloc_unchecked([0u64; 4]) };
+        let sites = arena.allocation_sites();
+        assert_eq!(sites.len(), 2);
+        assert!(sites[0].is_unchecked());
+        assert!(!sites[1].is_unchecked());
+    }
+}
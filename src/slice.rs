@@ -0,0 +1,574 @@
+//! Allocation of slices in a `DynamicArena`.
+use std::alloc::Layout;
+use std::mem;
+use std::ptr;
+use std::slice;
+use std::str;
+
+use crate::{DynamicArena, ItemBound};
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Allocate a copy of the specified slice in this arena.
+    ///
+    /// Just like `alloc_copy`, this requires `T: Copy`
+    /// to ensure there's no drop function that needs to be invoked.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_slice_copy<T: Copy>(&self, src: &[T]) -> &mut [T]
+    where
+        S: ItemBound<T>,
+    {
+        unsafe {
+            let ptr = self
+                .alloc_layout(Layout::for_value(src))
+                .as_ptr()
+                .cast::<T>();
+            ptr::copy_nonoverlapping(src.as_ptr(), ptr, src.len());
+            slice::from_raw_parts_mut(ptr, src.len())
+        }
+    }
+    /// Allocate a slice of the specified length in this arena,
+    /// with every element initialized to a copy of `value`.
+    ///
+    /// ## Panics
+    /// If the total size of the slice overflows.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_slice_fill_copy<T: Copy>(&self, len: usize, value: T) -> &mut [T]
+    where
+        S: ItemBound<T>,
+    {
+        self.alloc_slice_fill_copy_value(len, value)
+    }
+    /// Allocate a filled slice regardless of the `ItemBound`, for plain data used by the arena itself
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub(crate) fn alloc_slice_fill_copy_value<T: Copy>(&self, len: usize, value: T) -> &mut [T] {
+        unsafe {
+            let ptr = self
+                .alloc_layout(array_layout::<T>(len))
+                .as_ptr()
+                .cast::<T>();
+            for index in 0..len {
+                ptr.add(index).write(value);
+            }
+            slice::from_raw_parts_mut(ptr, len)
+        }
+    }
+    /// Allocate a single slice in this arena,
+    /// containing the concatenation of all the specified parts (in order).
+    ///
+    /// The parts are free to be slices that were allocated in this arena,
+    /// since the result is always a fresh allocation.
+    ///
+    /// ## Panics
+    /// If the total size of the parts overflows.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_slice_concat<T: Copy>(&self, parts: &[&[T]]) -> &mut [T]
+    where
+        S: ItemBound<T>,
+    {
+        let total_len = total_len(parts, |part| part);
+        unsafe {
+            let ptr = self
+                .alloc_layout(array_layout::<T>(total_len))
+                .as_ptr()
+                .cast::<T>();
+            let mut offset = 0;
+            for part in parts {
+                ptr::copy_nonoverlapping(part.as_ptr(), ptr.add(offset), part.len());
+                offset += part.len();
+            }
+            slice::from_raw_parts_mut(ptr, total_len)
+        }
+    }
+    /// Allocate a copy of each of the specified slices in this arena,
+    /// along with a slice of references to the copies.
+    ///
+    /// This performs a two-level "deep" copy,
+    /// which is needed to move a borrowed structure like `&[&[T]]` into the arena.
+    /// The inner slices are copied first (into a single contiguous allocation),
+    /// followed by the outer slice of references.
+    /// Nothing needs to be dropped, so this is usable on any arena.
+    ///
+    /// ## Panics
+    /// If the total size of the slices overflows.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_slices<T: Copy>(&self, items: &[&[T]]) -> &mut [&[T]]
+    where
+        S: ItemBound<T>,
+    {
+        self.alloc_nested(items, |item| item, |copied| copied)
+    }
+    /// Allocate a copy of each of the specified strings in this arena,
+    /// along with a slice of references to the copies.
+    ///
+    /// This is the string equivalent of `alloc_slices`.
+    ///
+    /// ## Panics
+    /// If the total size of the strings overflows.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_strs(&self, items: &[&str]) -> &mut [&str] {
+        self.alloc_nested(
+            items,
+            |item| item.as_bytes(),
+            // They were all copied from valid strings
+            |copied| unsafe { str::from_utf8_unchecked(copied) },
+        )
+    }
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn alloc_nested<'s, P, T: Copy + 's, R>(
+        &'s self,
+        items: &[P],
+        as_slice: impl Fn(&P) -> &[T],
+        convert: impl Fn(&'s [T]) -> R,
+    ) -> &'s mut [R] {
+        let total_len = total_len(items, &as_slice);
+        unsafe {
+            let inner = self
+                .alloc_layout(array_layout::<T>(total_len))
+                .as_ptr()
+                .cast::<T>();
+            let outer = self
+                .alloc_layout(array_layout::<R>(items.len()))
+                .as_ptr()
+                .cast::<R>();
+            let mut offset = 0;
+            for (index, item) in items.iter().enumerate() {
+                let src = as_slice(item);
+                let dest = inner.add(offset);
+                ptr::copy_nonoverlapping(src.as_ptr(), dest, src.len());
+                outer
+                    .add(index)
+                    .write(convert(slice::from_raw_parts(dest, src.len())));
+                offset += src.len();
+            }
+            slice::from_raw_parts_mut(outer, items.len())
+        }
+    }
+    /// Allocate a slice containing exactly `len` elements from the specified iterator.
+    ///
+    /// This doesn't register anything to be dropped,
+    /// so the caller is responsible for doing that.
+    ///
+    /// If the iterator panics (or yields too few elements),
+    /// the elements that have already been initialized are dropped.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub(crate) unsafe fn alloc_slice_from_exact_iter<T>(
+        &self,
+        len: usize,
+        mut iter: impl Iterator<Item = T>,
+    ) -> &mut [T] {
+        let ptr = self
+            .alloc_layout(array_layout::<T>(len))
+            .as_ptr()
+            .cast::<T>();
+        init_slice_with(ptr, len, |index| match iter.next() {
+            Some(value) => value,
+            None => panic!("Expected {} elements, but only got {}", len, index),
+        });
+        slice::from_raw_parts_mut(ptr, len)
+    }
+    /// Allocate a slice containing all the elements of the specified iterator,
+    /// registering the slice to be dropped along with the arena.
+    ///
+    /// If the iterator's exact size is known, this is allocated directly.
+    /// Otherwise, the elements are collected into a growable buffer in the arena.
+    ///
+    /// ## Safety
+    /// This has the same requirements as `dynamic_drop`,
+    /// which is normally ensured by the bounds on `alloc`.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub(crate) unsafe fn alloc_iter_unchecked<T>(
+        &self,
+        iter: impl IntoIterator<Item = T>,
+    ) -> &mut [T] {
+        let iter = iter.into_iter();
+        let target: *mut [T] = match iter.size_hint() {
+            (lower, Some(upper)) if lower == upper => self.alloc_slice_from_exact_iter(lower, iter),
+            _ => {
+                let buffer = bumpalo::collections::Vec::from_iter_in(iter, &self.handle);
+                self.record_bytes(buffer.len() * mem::size_of::<T>());
+                self.realigned(buffer.into_bump_slice_mut())
+            }
+        };
+        self.dynamic_drop_slice(target);
+        &mut *target
+    }
+    /// Dynamically drop the specified slice, just like `dynamic_drop`.
+    ///
+    /// Since the drop list can only hold thin pointers,
+    /// this allocates a small header in the arena recording the slice's length.
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub(crate) unsafe fn dynamic_drop_slice<T>(&self, target: *mut [T]) {
+        // The header always needs to be dropped, so only the elements are checked
+        #[cfg(feature = "no-drop-tracking")]
+        {
+            crate::untracked::assert_no_drop::<T>();
+            let _ = target;
+        }
+        #[cfg(not(feature = "no-drop-tracking"))]
+        if mem::needs_drop::<T>() {
+            let header = self.alloc_value(OwnedSlice(target));
+            self.dynamic_drop::<OwnedSlice<T>>(header);
+        }
+    }
+    /// Collect the elements of the specified iterator into a buffer in the arena,
+    /// then sort the buffer before registering it to be dropped.
+    ///
+    /// The buffer owns the elements until they're registered,
+    /// so if sorting panics they're still dropped.
+    ///
+    /// ## Safety
+    /// This has the same requirements as `dynamic_drop`.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    unsafe fn alloc_sorted_unchecked<T>(
+        &self,
+        iter: impl IntoIterator<Item = T>,
+        sort: impl FnOnce(&mut bumpalo::collections::Vec<T>),
+    ) -> &mut [T] {
+        let mut buffer = bumpalo::collections::Vec::from_iter_in(iter, &self.handle);
+        sort(&mut buffer);
+        self.record_bytes(buffer.len() * mem::size_of::<T>());
+        let target: *mut [T] = self.realigned(buffer.into_bump_slice_mut());
+        self.dynamic_drop_slice(target);
+        &mut *target
+    }
+    /// Allocate an array, initializing each element in place with `func(index)`.
+    ///
+    /// This doesn't register anything to be dropped,
+    /// so the caller is responsible for doing that.
+    ///
+    /// If `func` panics, the elements that have already been initialized are dropped.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    unsafe fn alloc_array_with_unchecked<T, const N: usize>(
+        &self,
+        func: impl FnMut(usize) -> T,
+    ) -> &mut [T; N] {
+        let ptr = self
+            .alloc_layout(Layout::new::<[T; N]>())
+            .as_ptr()
+            .cast::<T>();
+        init_slice_with(ptr, N, func);
+        &mut *ptr.cast::<[T; N]>()
+    }
+}
+impl<'a, S> DynamicArena<'a, S> {
+    /// Collect the elements of the specified iterator into a slice in this arena,
+    /// sorted by the specified key.
+    ///
+    /// The sort is stable, so elements with equal keys keep the order of the iterator.
+    /// A single drop is registered for the entire slice,
+    /// after it's been sorted.
+    /// If the key function panics, all the elements are still dropped.
+    /// Just like `alloc`, the elements must satisfy the arena's `ItemBound`.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_slice_sorted_by_key<T: 'a, K: Ord>(
+        &self,
+        iter: impl IntoIterator<Item = T>,
+        key: impl FnMut(&T) -> K,
+    ) -> &mut [T]
+    where
+        S: ItemBound<T>,
+    {
+        unsafe { self.alloc_sorted_unchecked(iter, |buffer| buffer.sort_by_key(key)) }
+    }
+    /// Collect the elements of the specified iterator into a slice in this arena,
+    /// sorted by the specified key (without preserving the order of equal elements).
+    ///
+    /// See `alloc_slice_sorted_by_key` for details.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_slice_sorted_unstable_by_key<T: 'a, K: Ord>(
+        &self,
+        iter: impl IntoIterator<Item = T>,
+        key: impl FnMut(&T) -> K,
+    ) -> &mut [T]
+    where
+        S: ItemBound<T>,
+    {
+        unsafe { self.alloc_sorted_unchecked(iter, |buffer| buffer.sort_unstable_by_key(key)) }
+    }
+    /// Collect the elements of the specified iterator into a slice in this arena,
+    /// sorted by the specified key, and removing elements with duplicate keys.
+    ///
+    /// Since the sort is stable, the first element (from the iterator) for each key is kept,
+    /// and the duplicates are dropped immediately.
+    /// See `alloc_slice_sorted_by_key` for details.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_slice_sorted_dedup_by_key<T: 'a, K: Ord>(
+        &self,
+        iter: impl IntoIterator<Item = T>,
+        mut key: impl FnMut(&T) -> K,
+    ) -> &mut [T]
+    where
+        S: ItemBound<T>,
+    {
+        unsafe {
+            self.alloc_sorted_unchecked(iter, |buffer| {
+                buffer.sort_by_key(&mut key);
+                buffer.dedup_by(|second, first| key(first) == key(second));
+            })
+        }
+    }
+    /// Allocate a fixed-size array in this arena,
+    /// initializing each element in place with the result of calling `func(index)`.
+    ///
+    /// The array is never constructed on the stack, so this works for very large arrays.
+    /// If `T` needs to be dropped, a single drop is registered for the entire array.
+    /// If `func` panics, the elements that have already been initialized are dropped.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_array_with<T: 'a, const N: usize>(
+        &self,
+        func: impl FnMut(usize) -> T,
+    ) -> &mut [T; N]
+    where
+        S: ItemBound<T>,
+    {
+        unsafe {
+            let target: *mut [T; N] = self.alloc_array_with_unchecked(func);
+            self.dynamic_drop(target);
+            &mut *target
+        }
+    }
+}
+
+/// Initialize the elements of the specified (uninitialized) memory with `func(index)`.
+///
+/// If `func` panics, the elements that have already been initialized are dropped.
+pub(crate) unsafe fn init_slice_with<T>(ptr: *mut T, len: usize, mut func: impl FnMut(usize) -> T) {
+    let mut guard = PartialInit {
+        ptr,
+        initialized: 0,
+    };
+    while guard.initialized < len {
+        ptr.add(guard.initialized).write(func(guard.initialized));
+        guard.initialized += 1;
+    }
+    mem::forget(guard);
+}
+
+/// Owns the elements of a slice allocated in the arena, dropping them when it is dropped.
+#[cfg(not(feature = "no-drop-tracking"))]
+struct OwnedSlice<T>(*mut [T]);
+#[cfg(not(feature = "no-drop-tracking"))]
+impl<T> Drop for OwnedSlice<T> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.0) }
+    }
+}
+
+/// Drops the initialized prefix of a slice, if initialization panics.
+struct PartialInit<T> {
+    ptr: *mut T,
+    initialized: usize,
+}
+impl<T> Drop for PartialInit<T> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.ptr, self.initialized));
+        }
+    }
+}
+
+/// The total length of the specified slices,
+/// panicking if it overflows.
+#[inline]
+fn total_len<P, T>(items: &[P], as_slice: impl Fn(&P) -> &[T]) -> usize {
+    items
+        .iter()
+        .try_fold(0usize, |total, item| {
+            total.checked_add(as_slice(item).len())
+        })
+        .unwrap_or_else(|| panic!("Capacity overflow for {} slices", items.len()))
+}
+
+/// The layout of an array with the specified length,
+/// panicking if the total size overflows.
+#[inline]
+pub(crate) fn array_layout<T>(len: usize) -> Layout {
+    match Layout::array::<T>(len) {
+        Ok(layout) => layout,
+        Err(_) => panic!(
+            "Capacity overflow for [{}; {}]",
+            std::any::type_name::<T>(),
+            len
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use crate::test_util::DropCounted;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::cell::Cell;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::panic::{self, AssertUnwindSafe};
+    use std::ptr::NonNull;
+
+    #[test]
+    fn copied_slices() {
+        let arena = DynamicArena::new();
+        let original = [1u32, 2, 3];
+        let copied = arena.alloc_slice_copy(&original);
+        copied[0] = 7;
+        assert_eq!(copied, &[7, 2, 3]);
+        assert_eq!(arena.alloc_slice_fill_copy(4, 'x'), &['x'; 4]);
+        assert!(arena.alloc_slice_copy::<u64>(&[]).is_empty());
+    }
+    #[test]
+    fn nested() {
+        let arena = DynamicArena::new();
+        let copied = {
+            let owned = [String::from("foo"), String::new(), String::from("bar")];
+            let borrowed = owned.iter().map(String::as_str).collect::<Vec<_>>();
+            arena.alloc_strs(&borrowed)
+        };
+        assert_eq!(copied, &["foo", "", "bar"]);
+        assert!(arena.alloc_strs(&[]).is_empty());
+        let slices = {
+            let first = vec![1u32, 2];
+            let second = vec![3u32];
+            arena.alloc_slices(&[&first, &[], &second])
+        };
+        assert_eq!(slices, &[&[1, 2][..], &[], &[3]]);
+        assert_eq!(arena.alloc_slices::<u8>(&[&[], &[]]), &[&[][..], &[]]);
+        arena.verify().unwrap();
+    }
+    #[test]
+    fn concat() {
+        let arena = DynamicArena::new();
+        let first = arena.alloc_slice_copy(&[1u16, 2, 3]);
+        let second = arena.alloc_slice_copy(&[4u16]);
+        let combined = arena.alloc_slice_concat(&[first, &[], second, &[5, 6]]);
+        assert_eq!(combined, &[1, 2, 3, 4, 5, 6]);
+        combined[0] = 0;
+        assert_eq!(first, &[1, 2, 3]);
+        let again = arena.alloc_slice_concat(&[combined, combined]);
+        assert_eq!(again.len(), 12);
+        assert!(arena.alloc_slice_concat::<u8>(&[]).is_empty());
+    }
+    #[test]
+    #[should_panic(expected = "Capacity overflow")]
+    fn concat_overflow() {
+        let arena = DynamicArena::new();
+        // Zero-sized types never actually need to be copied
+        let huge = unsafe { slice::from_raw_parts(NonNull::<()>::dangling().as_ptr(), usize::MAX) };
+        arena.alloc_slice_concat(&[huge, &[()]]);
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn arrays() {
+        let arena = DynamicArena::new();
+        let table: &mut [u8; 256] = arena.alloc_array_with(|index| (index as u8).wrapping_mul(3));
+        assert_eq!(table[255], 255u8.wrapping_mul(3));
+        let strings = arena.alloc_array_with::<_, 3>(|index| index.to_string());
+        assert_eq!(strings, &["0", "1", "2"]);
+        let send_arena = DynamicArena::new_send();
+        let empty: &mut [String; 0] = send_arena.alloc_array_with(|_| unreachable!());
+        assert!(empty.is_empty());
+        arena.verify().unwrap();
+        send_arena.verify().unwrap();
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn array_drops() {
+        let counter = Cell::new(0);
+        let arena = DynamicArena::new_bounded();
+        arena.alloc_array_with::<_, 17>(|_| DropCounted(&counter));
+        // A single entry is registered for the entire array
+        assert_eq!(arena.items.len(), 1);
+        arena.verify().unwrap();
+        drop(arena);
+        assert_eq!(counter.get(), 17);
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn array_panic() {
+        let counter = Cell::new(0);
+        let arena = DynamicArena::new_bounded();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            arena.alloc_array_with::<_, 10>(|index| {
+                if index == 6 {
+                    panic!("Failed to initialize");
+                }
+                DropCounted(&counter)
+            });
+        }));
+        assert!(result.is_err());
+        assert_eq!(counter.get(), 6);
+        assert!(arena.items.is_empty());
+        drop(arena);
+        assert_eq!(counter.get(), 6);
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn sorted() {
+        let arena = DynamicArena::new();
+        let words = ["pear", "fig", "apple", "kiwi", "plum", "date"];
+        let by_len =
+            arena.alloc_slice_sorted_by_key(words.iter().map(|w| w.to_string()), |w| w.len());
+        // Stable, so equal lengths keep their original order
+        assert_eq!(by_len, &["fig", "pear", "kiwi", "plum", "date", "apple"]);
+        let unstable = arena.alloc_slice_sorted_unstable_by_key(words.iter().copied(), |w| *w);
+        assert_eq!(unstable, &["apple", "date", "fig", "kiwi", "pear", "plum"]);
+        let deduped = arena.alloc_slice_sorted_dedup_by_key(words.iter().copied(), |w| w.len());
+        assert_eq!(deduped, &["fig", "pear", "apple"]);
+        let send_arena = DynamicArena::new_send();
+        let empty = send_arena.alloc_slice_sorted_dedup_by_key(Vec::<String>::new(), |w| w.len());
+        assert!(empty.is_empty());
+    }
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn sorted_drops() {
+        let counter = Cell::new(0);
+        let arena = DynamicArena::new_bounded();
+        let deduped = arena.alloc_slice_sorted_dedup_by_key(
+            (0..20u32).map(|i| (i % 5, DropCounted(&counter))),
+            |(key, _)| *key,
+        );
+        assert_eq!(deduped.len(), 5);
+        // The duplicates are dropped immediately
+        assert_eq!(counter.get(), 15);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut calls = 0;
+            arena.alloc_slice_sorted_by_key((0..10).map(|_| DropCounted(&counter)), |_| {
+                calls += 1;
+                if calls == 5 {
+                    panic!("Comparison failed");
+                }
+                calls
+            });
+        }));
+        assert!(result.is_err());
+        assert_eq!(counter.get(), 25);
+        drop(arena);
+        assert_eq!(counter.get(), 30);
+    }
+    #[test]
+    #[should_panic(expected = "Capacity overflow")]
+    fn fill_overflow() {
+        let arena = DynamicArena::new();
+        arena.alloc_slice_fill_copy(usize::MAX / 2, 0u32);
+    }
+}
@@ -0,0 +1,222 @@
+//! Small arenas that serve their first allocations from storage inside the arena itself.
+use std::alloc::Layout;
+use std::cell::{Cell, UnsafeCell};
+use std::fmt::{self, Debug};
+use std::marker::PhantomPinned;
+use std::mem::{self, MaybeUninit};
+use std::pin::Pin;
+use std::ptr::{self, NonNull};
+
+use crate::droplist::DropList;
+use crate::{DynamicArena, ItemBound, NonSend, SendAbility};
+
+/// The inline storage, which is aligned enough for most values
+#[repr(C, align(16))]
+struct InlineStorage<const N: usize>(UnsafeCell<[MaybeUninit<u8>; N]>);
+
+/// An arena that serves the first `N` bytes from storage inside the arena itself,
+/// and only falls back to heap chunks once that's exhausted.
+///
+/// This avoids a heap allocation for each of "thousands of tiny arenas".
+/// Moving the arena would invalidate everything in the inline storage,
+/// so it has to be pinned before it can allocate (usually with `std::pin::pin!`).
+/// Values that need to be dropped keep their drop entries in the inline storage too,
+/// and they're dropped along with the values on the heap, in the usual order.
+///
+/// ```
+/// use std::pin::pin;
+/// # #[cfg(not(feature = "no-drop-tracking"))] {
+/// use dynamic_arena::SmallDynamicArena;
+///
+/// let mut arena = pin!(SmallDynamicArena::<256>::new());
+/// let name = arena.as_ref().alloc(String::from("tiny"));
+/// assert_eq!(name, "tiny");
+/// assert_eq!(arena.heap_allocated_bytes(), 0);
+/// arena.as_mut().reset();
+/// # }
+/// ```
+pub struct SmallDynamicArena<'a, const N: usize, S = NonSend> {
+    storage: InlineStorage<N>,
+    /// The number of bytes used at the start of the inline storage
+    used: Cell<usize>,
+    /// Owns the drop list (including the entries in the inline storage) and the overflow chunks
+    heap: DynamicArena<'a, S>,
+    _pinned: PhantomPinned,
+}
+impl<'a, const N: usize, S: SendAbility> SmallDynamicArena<'a, N, S> {
+    /// Create an empty arena, which doesn't allocate anything on the heap until it needs to
+    #[inline]
+    pub fn new() -> Self {
+        SmallDynamicArena {
+            storage: InlineStorage(UnsafeCell::new([MaybeUninit::uninit(); N])),
+            used: Cell::new(0),
+            heap: S::create_arena(),
+            _pinned: PhantomPinned,
+        }
+    }
+}
+impl<'a, const N: usize, S: SendAbility> Default for SmallDynamicArena<'a, N, S> {
+    #[inline]
+    fn default() -> Self {
+        SmallDynamicArena::new()
+    }
+}
+impl<'a, const N: usize, S> Debug for SmallDynamicArena<'a, N, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SmallDynamicArena")
+            .field("inline_used", &self.used.get())
+            .field("inline_capacity", &N)
+            .field("heap", &self.heap)
+            .finish()
+    }
+}
+impl<'a, const N: usize, S> SmallDynamicArena<'a, N, S> {
+    /// The number of bytes at the start of the inline storage that have been used
+    #[inline]
+    pub fn inline_used(&self) -> usize {
+        self.used.get()
+    }
+    /// The number of bytes allocated in heap chunks, once the inline storage was exhausted
+    #[inline]
+    pub fn heap_allocated_bytes(&self) -> usize {
+        self.heap.as_bumpalo().allocated_bytes()
+    }
+    /// The number of values that are registered to be dropped, wherever they're stored
+    #[inline]
+    pub fn pending_drop_count(&self) -> usize {
+        self.heap.pending_drop_count()
+    }
+    /// Allocate the specified value, returning a reference valid for as long as the arena is pinned
+    ///
+    /// This has the same bounds as `DynamicArena::alloc`.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc<T: 'a>(self: Pin<&Self>, value: T) -> &mut T
+    where
+        S: ItemBound<T>,
+    {
+        let this = self.get_ref();
+        if !mem::needs_drop::<T>() {
+            return match this.alloc_inline(Layout::new::<T>()) {
+                Some(ptr) => unsafe { write(ptr, value) },
+                None => unsafe { this.heap.alloc_value(value) },
+            };
+        }
+        // The entry goes right after the value, just like in a heap chunk
+        if let Ok((layout, offset)) = Layout::new::<T>().extend(DropList::NODE_LAYOUT) {
+            if let Some(block) = this.alloc_inline(layout) {
+                unsafe {
+                    let node = NonNull::new_unchecked(block.as_ptr().add(offset));
+                    // The returned reference is derived from the registered pointer
+                    let target = block.as_ptr().cast::<T>();
+                    target.write(value);
+                    this.heap.register_drop_at(target, node);
+                    return &mut *target;
+                }
+            }
+        }
+        this.heap.alloc(value)
+    }
+    /// Allocate the specified copyable value,
+    /// returning a reference valid for as long as the arena is pinned
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_copy<T: Copy>(self: Pin<&Self>, value: T) -> &mut T
+    where
+        S: ItemBound<T>,
+    {
+        let this = self.get_ref();
+        match this.alloc_inline(Layout::new::<T>()) {
+            Some(ptr) => unsafe { write(ptr, value) },
+            None => this.heap.alloc_copy(value),
+        }
+    }
+    /// Allocate a copy of the specified string,
+    /// returning a reference valid for as long as the arena is pinned
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_str<'s>(self: Pin<&'s Self>, s: &str) -> &'s mut str {
+        let this = self.get_ref();
+        match this.alloc_inline(Layout::for_value(s)) {
+            Some(ptr) => unsafe {
+                ptr::copy_nonoverlapping(s.as_ptr(), ptr.as_ptr(), s.len());
+                std::str::from_utf8_unchecked_mut(std::slice::from_raw_parts_mut(
+                    ptr.as_ptr(),
+                    s.len(),
+                ))
+            },
+            None => this.heap.alloc_str(s),
+        }
+    }
+    /// Drop everything in the arena, leaving it empty (and still pinned) for reuse
+    ///
+    /// The inline storage is reused from the start, and the heap arena keeps its largest chunk.
+    pub fn reset(self: Pin<&mut Self>) {
+        // Nothing is moved out of the arena
+        let this = unsafe { self.get_unchecked_mut() };
+        this.heap.reset();
+        this.used.set(0);
+    }
+    /// Bump-allocate memory with the specified layout from the inline storage, if it fits
+    #[inline]
+    fn alloc_inline(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let base = self.storage.0.get().cast::<u8>();
+        let used = self.used.get();
+        let padding = (base as usize + used).wrapping_neg() & (layout.align() - 1);
+        let start = used.checked_add(padding)?;
+        let end = start.checked_add(layout.size())?;
+        if end > N {
+            return None;
+        }
+        self.used.set(end);
+        // The storage is only borrowed through the cell, so values can be written while it's shared
+        unsafe { Some(NonNull::new_unchecked(base.add(start))) }
+    }
+}
+
+/// Write the value to the allocated memory, returning a reference to it
+#[inline]
+unsafe fn write<'s, T>(ptr: NonNull<u8>, value: T) -> &'s mut T {
+    let target = ptr.as_ptr().cast::<T>();
+    target.write(value);
+    &mut *target
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+    use std::pin::pin;
+
+    struct DropOrder<'a>(&'a RefCell<Vec<u32>>, u32);
+    impl<'a> Drop for DropOrder<'a> {
+        fn drop(&mut self) {
+            self.0.borrow_mut().push(self.1);
+        }
+    }
+
+    #[test]
+    fn inline_then_heap() {
+        let order = RefCell::new(Vec::new());
+        {
+            let mut arena = pin!(SmallDynamicArena::<256>::new());
+            for id in 0..3 {
+                arena.as_ref().alloc(DropOrder(&order, id));
+            }
+            let aligned = arena.as_ref().alloc_copy(7u128);
+            assert_eq!(aligned as *mut u128 as usize % mem::align_of::<u128>(), 0);
+            assert_eq!(arena.as_ref().alloc_str("inline"), "inline");
+            assert_eq!(arena.heap_allocated_bytes(), 0);
+            // Once the inline storage runs out, everything goes to the heap
+            arena.as_ref().alloc_copy([0u8; 256]);
+            arena.as_ref().alloc(DropOrder(&order, 3));
+            assert!(arena.heap_allocated_bytes() > 0);
+            assert_eq!(arena.pending_drop_count(), 4);
+            arena.as_mut().reset();
+            assert_eq!(*order.borrow(), vec![3, 2, 1, 0]);
+            assert_eq!(arena.inline_used(), 0);
+            arena.as_ref().alloc(DropOrder(&order, 4));
+        }
+        assert_eq!(*order.borrow(), vec![3, 2, 1, 0, 4]);
+    }
+}
@@ -0,0 +1,206 @@
+//! Snapshots of an arena's memory usage.
+use std::cell::Cell;
+
+use bumpalo::Bump;
+
+use crate::DynamicArena;
+
+/// A snapshot of the memory used by an arena
+///
+/// This is created by `DynamicArena::stats`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ArenaStats {
+    /// The number of bytes that have been allocated (including any padding),
+    /// excluding the unused part of the current chunk
+    pub allocated_bytes: usize,
+    /// The total capacity of all the chunks, in bytes
+    pub capacity_bytes: usize,
+    /// The number of chunks the arena owns
+    pub chunk_count: usize,
+    /// The number of values that are registered to be dropped
+    pub registered_drop_count: usize,
+    /// The number of values that can be registered without requesting a new chunk (see `item_capacity`)
+    pub drop_list_capacity: usize,
+    /// The total number of bytes freed back to the allocator by `reset_and_decommit`
+    pub freed_bytes: usize,
+    /// The total number of bytes decommitted by `reset_and_decommit`
+    pub decommitted_bytes: usize,
+    /// The largest value of `allocated_bytes` since the peaks were last reset
+    pub peak_allocated_bytes: usize,
+    /// The largest value of `registered_drop_count` since the peaks were last reset
+    pub peak_registered_drop_count: usize,
+}
+
+/// The high-water marks of an arena, which survive resets
+#[derive(Default)]
+pub(crate) struct Peaks {
+    bytes: Cell<usize>,
+    pub(crate) items: Cell<usize>,
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Take a snapshot of the arena's memory usage
+    ///
+    /// This includes the chunks of any committed forks.
+    pub fn stats(&self) -> ArenaStats {
+        let mut stats = ArenaStats {
+            allocated_bytes: self.used_bytes(),
+            capacity_bytes: self.handle.allocated_bytes(),
+            chunk_count: chunk_count(&self.handle),
+            registered_drop_count: self.items.len(),
+            drop_list_capacity: self.item_capacity(),
+            freed_bytes: self.released.freed,
+            decommitted_bytes: self.released.decommitted,
+            peak_allocated_bytes: 0,
+            peak_registered_drop_count: 0,
+        };
+        for adopted in self.adopted.borrow().iter() {
+            let capacity = adopted.allocated_bytes();
+            stats.allocated_bytes += capacity - adopted.chunk_capacity();
+            stats.capacity_bytes += capacity;
+            stats.chunk_count += chunk_count(adopted);
+        }
+        self.record_peak_items(stats.registered_drop_count);
+        stats.peak_allocated_bytes = self.record_peak_bytes(stats.allocated_bytes);
+        stats.peak_registered_drop_count = self.peaks.items.get();
+        stats
+    }
+    /// The largest number of bytes that have been allocated at once,
+    /// since the arena was created or `reset_peaks` was called
+    ///
+    /// This survives resets, so it's useful for sizing `with_capacity`.
+    #[inline]
+    pub fn peak_allocated_bytes(&self) -> usize {
+        self.stats().peak_allocated_bytes
+    }
+    /// The largest number of values that have been registered to be dropped at once,
+    /// since the arena was created or `reset_peaks` was called
+    #[inline]
+    pub fn peak_registered_drop_count(&self) -> usize {
+        self.stats().peak_registered_drop_count
+    }
+    /// Start a new measurement window for the peaks, beginning at the current usage
+    ///
+    /// This also clears any other cumulative statistics (like `type_stats`, `size_histogram` and `allocation_sites`).
+    pub fn reset_peaks(&self) {
+        self.peaks.bytes.set(0);
+        self.peaks.items.set(0);
+        #[cfg(feature = "type-stats")]
+        self.type_stats.clear();
+        #[cfg(feature = "size-histogram")]
+        self.size_counters.clear();
+        #[cfg(feature = "track-callers")]
+        self.allocation_sites.clear();
+        // This records the current usage
+        self.stats();
+    }
+    /// Record the number of registered items, if it's a new peak
+    #[inline]
+    pub(crate) fn record_peak_items(&self, items: usize) {
+        if items > self.peaks.items.get() {
+            self.peaks.items.set(items);
+        }
+    }
+    /// Record the current usage before anything is reclaimed
+    ///
+    /// Bytes are only ever reclaimed by resetting (or `dealloc_last`),
+    /// so sampling them then is enough to find the peak,
+    /// without slowing down the allocation path.
+    #[inline]
+    pub(crate) fn sample_peaks(&self) {
+        let adopted: usize = self
+            .adopted
+            .borrow()
+            .iter()
+            .map(|adopted| adopted.allocated_bytes() - adopted.chunk_capacity())
+            .sum();
+        self.record_peak_bytes(self.used_bytes() + adopted);
+    }
+    #[inline]
+    fn record_peak_bytes(&self, bytes: usize) -> usize {
+        let peak = self.peaks.bytes.get().max(bytes);
+        self.peaks.bytes.set(peak);
+        peak
+    }
+}
+
+#[inline]
+pub(crate) fn chunk_count(handle: &Bump) -> usize {
+    // The raw chunks are only counted, never accessed
+    unsafe { handle.iter_allocated_chunks_raw() }.count()
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn counters() {
+        let arena = DynamicArena::new();
+        let empty = arena.stats();
+        assert_eq!(empty.allocated_bytes, 0);
+        assert_eq!(empty.registered_drop_count, 0);
+        arena.alloc_copy(5u64);
+        let copied = arena.stats();
+        assert!(copied.allocated_bytes >= 8);
+        assert_eq!(copied.registered_drop_count, 0);
+        assert!(copied.chunk_count >= 1);
+        arena.alloc(String::from("dropped"));
+        let dropped = arena.stats();
+        assert_eq!(dropped.registered_drop_count, 1);
+        assert!(dropped.drop_list_capacity >= 1);
+        assert!(dropped.allocated_bytes > copied.allocated_bytes);
+        let chunks = dropped.chunk_count;
+        arena.alloc_slice_fill_copy(dropped.capacity_bytes * 2, 0u8);
+        let grown = arena.stats();
+        assert_eq!(grown.chunk_count, chunks + 1);
+        assert!(grown.capacity_bytes > dropped.capacity_bytes);
+        assert!(grown.allocated_bytes <= grown.capacity_bytes);
+    }
+    #[test]
+    fn peaks() {
+        let mut arena = DynamicArena::new();
+        for &count in &[10, 100, 30] {
+            for _ in 0..count {
+                arena.alloc(vec![0u64; 4]);
+            }
+            arena.reset();
+        }
+        assert_eq!(arena.peak_registered_drop_count(), 100);
+        let peak = arena.peak_allocated_bytes();
+        assert!(peak >= 100 * std::mem::size_of::<Vec<u64>>());
+        assert_eq!(arena.stats().allocated_bytes, 0);
+        // Rolling back doesn't lower the peak either
+        let checkpoint = arena.checkpoint();
+        for _ in 0..120 {
+            arena.alloc(String::new());
+        }
+        arena.rollback_to(checkpoint);
+        assert_eq!(arena.peak_registered_drop_count(), 120);
+        assert!(arena.peak_allocated_bytes() > peak);
+        arena.reset();
+        arena.reset_peaks();
+        assert_eq!(arena.peak_registered_drop_count(), 0);
+        assert_eq!(arena.peak_allocated_bytes(), 0);
+    }
+    #[test]
+    fn reset_and_forks() {
+        let mut arena = DynamicArena::new();
+        arena.alloc(vec![1, 2, 3]);
+        let fork = arena.fork();
+        fork.alloc_slice_copy(&[0u8; 1000]);
+        fork.commit();
+        let stats = arena.stats();
+        assert!(stats.allocated_bytes >= 1000);
+        assert_eq!(stats.chunk_count, 2);
+        arena.reset();
+        let reset = arena.stats();
+        assert_eq!(reset.allocated_bytes, 0);
+        assert_eq!(reset.registered_drop_count, 0);
+        assert_eq!(reset.chunk_count, 1);
+        // The kept chunk is empty again, so there's room for at least as many entries
+        assert!(reset.drop_list_capacity >= stats.drop_list_capacity);
+        assert!(format!("{:?}", reset).contains("chunk_count: 1"));
+    }
+}
@@ -0,0 +1,262 @@
+//! Allocation of strings in a `DynamicArena`.
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::iter;
+use std::str::{self, Utf8Error};
+
+use crate::DynamicArena;
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Allocate a string in this arena, by encoding each of the specified characters
+    /// directly into arena memory.
+    ///
+    /// Since the final length isn't known in advance, the buffer grows as needed.
+    /// In the common case the buffer is the last allocation in the arena,
+    /// so it can grow in place without copying.
+    /// Otherwise, it grows geometrically (so the total work is still linear).
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_str_from_chars(&self, chars: impl IntoIterator<Item = char>) -> &mut str {
+        let chars = chars.into_iter();
+        let mut buffer =
+            bumpalo::collections::Vec::with_capacity_in(chars.size_hint().0, &self.handle);
+        let mut encoded = [0u8; 4];
+        for c in chars {
+            buffer.extend_from_slice_copy(c.encode_utf8(&mut encoded).as_bytes());
+        }
+        self.record_bytes(buffer.len());
+        // The buffer only contains complete UTF8 characters
+        unsafe { str::from_utf8_unchecked_mut(self.realigned(buffer.into_bump_slice_mut())) }
+    }
+    /// Validate that the specified bytes are UTF8,
+    /// then allocate a copy of the resulting string in this arena.
+    ///
+    /// Nothing is allocated if validation fails,
+    /// and the error is exactly what `str::from_utf8` would return.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_str_from_utf8(&self, bytes: &[u8]) -> Result<&mut str, Utf8Error> {
+        Ok(self.alloc_str(str::from_utf8(bytes)?))
+    }
+    /// Allocate a string in this arena from the specified bytes,
+    /// replacing any invalid UTF8 sequences with `U+FFFD REPLACEMENT CHARACTER`.
+    ///
+    /// This is the arena equivalent of `String::from_utf8_lossy`.
+    /// Valid input is copied only once, and the replacements are written directly
+    /// into the arena without allocating an intermediate `String`.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_str_from_utf8_lossy(&self, bytes: &[u8]) -> &mut str {
+        if let Ok(valid) = str::from_utf8(bytes) {
+            return self.alloc_str(valid);
+        }
+        let mut buffer = bumpalo::collections::Vec::with_capacity_in(bytes.len(), &self.handle);
+        for chunk in bytes.utf8_chunks() {
+            buffer.extend_from_slice_copy(chunk.valid().as_bytes());
+            if !chunk.invalid().is_empty() {
+                buffer.extend_from_slice_copy(
+                    char::REPLACEMENT_CHARACTER
+                        .encode_utf8(&mut [0; 4])
+                        .as_bytes(),
+                );
+            }
+        }
+        self.record_bytes(buffer.len());
+        // Invalid sequences have been replaced
+        unsafe { str::from_utf8_unchecked_mut(self.realigned(buffer.into_bump_slice_mut())) }
+    }
+    /// Split the specified text by a separator,
+    /// allocating the resulting slice of pieces in this arena.
+    ///
+    /// The pieces are exactly those returned by `str::split`,
+    /// and they borrow from the original text (only the slice of references is allocated).
+    /// This makes two passes over the text (first counting the pieces),
+    /// so no temporary heap allocation is needed.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_split<'t>(&self, text: &'t str, sep: char) -> &mut [&'t str] {
+        let count = text.split(sep).count();
+        unsafe { self.alloc_slice_from_exact_iter(count, text.split(sep)) }
+    }
+    /// Split the specified text into lines,
+    /// allocating the resulting slice of lines in this arena.
+    ///
+    /// The lines are exactly those returned by `str::lines`,
+    /// so they can end with either `\n` or `\r\n`, and a trailing line ending is ignored.
+    /// Just like `alloc_split`, the lines borrow from the original text.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_lines<'t>(&self, text: &'t str) -> &mut [&'t str] {
+        let count = text.lines().count();
+        unsafe { self.alloc_slice_from_exact_iter(count, text.lines()) }
+    }
+    /// Encode the specified string as UTF16 in this arena,
+    /// with a trailing NUL terminator (as expected by Windows APIs).
+    ///
+    /// The returned slice includes the terminator,
+    /// so its pointer can be passed directly to functions expecting a `*const u16`.
+    /// Interior NUL characters are encoded as-is (which would truncate the string in C),
+    /// see `alloc_wide_str_checked` to reject them instead.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_wide_str(&self, s: &str) -> &mut [u16] {
+        self.alloc_wide_units(s.encode_utf16())
+    }
+    /// Encode the specified string as NUL-terminated UTF16 in this arena,
+    /// failing if the string contains an interior NUL character.
+    ///
+    /// Nothing is allocated if the string is rejected.
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_wide_str_checked(&self, s: &str) -> Result<&mut [u16], InteriorNulError> {
+        match s.find('\0') {
+            Some(position) => Err(InteriorNulError { position }),
+            None => Ok(self.alloc_wide_str(s)),
+        }
+    }
+    /// Encode the specified OS string as NUL-terminated UTF16 in this arena,
+    /// for passing to Windows APIs.
+    ///
+    /// Just like `alloc_wide_str`, the returned slice includes the terminator.
+    #[cfg(windows)]
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn alloc_wide_os_str(&self, s: &std::ffi::OsStr) -> &mut [u16] {
+        use std::os::windows::ffi::OsStrExt;
+        self.alloc_wide_units(s.encode_wide())
+    }
+    #[allow(clippy::mut_from_ref)]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    fn alloc_wide_units<I: Iterator<Item = u16> + Clone>(&self, units: I) -> &mut [u16] {
+        let len = units.clone().count() + 1;
+        unsafe { self.alloc_slice_from_exact_iter(len, units.chain(iter::once(0))) }
+    }
+}
+
+/// An error indicating that a string couldn't be encoded
+/// because it contained an interior NUL character.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InteriorNulError {
+    position: usize,
+}
+impl InteriorNulError {
+    /// The byte position of the first NUL character in the original string
+    #[inline]
+    pub fn nul_position(&self) -> usize {
+        self.position
+    }
+}
+impl Display for InteriorNulError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unexpected NUL character at byte {}", self.position)
+    }
+}
+impl Error for InteriorNulError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_chars() {
+        let arena = DynamicArena::new();
+        let unescaped = "a\\tb\\\\c".replace("\\t", "\t").replace("\\\\", "\\");
+        assert_eq!(arena.alloc_str_from_chars(unescaped.chars()), "a\tb\\c");
+        assert_eq!(arena.alloc_str_from_chars(std::iter::empty()), "");
+    }
+    #[test]
+    fn wide_characters() {
+        let arena = DynamicArena::new();
+        // Shift the four-byte characters across every possible growth boundary
+        for prefix in 0..70 {
+            let expected = "x".repeat(prefix) + &"🦀é𝄞".repeat(prefix);
+            // `filter` hides the exact size from the implementation
+            let actual = arena.alloc_str_from_chars(expected.chars().filter(|_| true));
+            assert_eq!(&*actual, expected);
+        }
+    }
+    #[test]
+    fn interleaved_allocation() {
+        let arena = DynamicArena::new();
+        let mut others = Vec::new();
+        let actual = arena.alloc_str_from_chars((0..200).map(|i| {
+            // Prevents the buffer from growing in place
+            others.push(&*arena.alloc_copy(i));
+            char::from_u32(0x1F600 + i).unwrap()
+        }));
+        let expected = (0..200)
+            .map(|i| char::from_u32(0x1F600 + i).unwrap())
+            .collect::<String>();
+        assert_eq!(&*actual, expected);
+        assert!(others.into_iter().copied().eq(0..200));
+    }
+    const INVALID: &[&[u8]] = &[
+        b"\xff",
+        b"abc\xe2\x82",
+        b"\xf0\x9f\xa6abc",
+        b"ok\xc3\x28\xa0\xa1 fine",
+        b"\xed\xa0\x80",
+        b"trailing \xf0\x9f\xa6",
+    ];
+    #[test]
+    fn from_utf8() {
+        let arena = DynamicArena::new();
+        assert_eq!(
+            arena.alloc_str_from_utf8("héllo".as_bytes()).unwrap(),
+            "héllo"
+        );
+        for &bytes in INVALID {
+            let before = arena.used_bytes();
+            let expected = str::from_utf8(bytes).unwrap_err();
+            assert_eq!(arena.alloc_str_from_utf8(bytes).unwrap_err(), expected);
+            assert_eq!(arena.used_bytes(), before);
+        }
+    }
+    #[test]
+    fn from_utf8_lossy() {
+        let arena = DynamicArena::new();
+        assert_eq!(arena.alloc_str_from_utf8_lossy(b"valid"), "valid");
+        assert_eq!(arena.alloc_str_from_utf8_lossy(b""), "");
+        for &bytes in INVALID {
+            assert_eq!(
+                &*arena.alloc_str_from_utf8_lossy(bytes),
+                String::from_utf8_lossy(bytes)
+            );
+        }
+    }
+    #[test]
+    fn split() {
+        let arena = DynamicArena::new();
+        let text = String::from("a,b,,c,");
+        assert_eq!(arena.alloc_split(&text, ','), &["a", "b", "", "c", ""]);
+        assert_eq!(arena.alloc_split("", ','), &[""]);
+        assert_eq!(arena.alloc_split("single", ','), &["single"]);
+        let pieces = arena.alloc_split(&text, ',');
+        // The pieces borrow the original text
+        assert_eq!(pieces[1].as_ptr(), text[2..].as_ptr());
+    }
+    #[test]
+    fn lines() {
+        let arena = DynamicArena::new();
+        assert_eq!(
+            arena.alloc_lines("first\r\nsecond\n\nlast\n"),
+            &["first", "second", "", "last"]
+        );
+        assert!(arena.alloc_lines("").is_empty());
+        assert_eq!(arena.alloc_lines("no newline"), &["no newline"]);
+        assert_eq!(arena.alloc_lines("\r\n"), &[""]);
+    }
+    #[test]
+    fn wide_str() {
+        let arena = DynamicArena::new();
+        let expected = "pa🦀th".encode_utf16().chain(Some(0)).collect::<Vec<_>>();
+        assert_eq!(arena.alloc_wide_str("pa🦀th"), &*expected);
+        assert_eq!(arena.alloc_wide_str(""), &[0]);
+        assert_eq!(arena.alloc_wide_str("a\0b"), &[97, 0, 98, 0]);
+        assert_eq!(arena.alloc_wide_str_checked("ok").unwrap(), &[111, 107, 0]);
+        let err = arena.alloc_wide_str_checked("🦀\0b").unwrap_err();
+        assert_eq!(err.nul_position(), 4);
+        assert_eq!(err.to_string(), "Unexpected NUL character at byte 4");
+    }
+}
@@ -0,0 +1,191 @@
+//! An arena that can be shared between threads, allocating through a lock.
+use std::alloc::Layout;
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use bumpalo::Bump;
+
+use crate::teardown::DropPanic;
+use crate::{drop_glue, DynamicArenaItem, DEFAULT_DROP_PHASE};
+
+/// The state behind the lock
+struct Shared {
+    handle: Bump,
+    items: Vec<DynamicArenaItem>,
+}
+
+/// An arena allocator that's `Sync`, so multiple threads can allocate into it at once.
+///
+/// Unlike a `Sendable` arena (which can only be moved between threads),
+/// this can be allocated into from behind a shared reference, like from several rayon workers.
+/// Every allocation takes a lock, so this is slower than a `DynamicArena`
+/// and is best used when the arena really needs to be shared.
+///
+/// Since other threads could be reading the values, everything must be `Send + Sync`,
+/// and references to them are shared (`&T` instead of `&mut T`).
+/// Just like `DynamicArena`, the values must outlive the bound `'a`,
+/// and they're dropped when the arena is dropped.
+pub struct DynamicSyncArena<'a> {
+    shared: Mutex<Shared>,
+    /// Invariance over `'a`, just like `DynamicArena` (while remaining `Send + Sync`)
+    marker: PhantomData<fn(&'a ()) -> &'a ()>,
+}
+impl<'a> DynamicSyncArena<'a> {
+    /// Create a new empty arena, bounded by the inferred lifetime for this type `'a`
+    pub fn new_bounded() -> Self {
+        DynamicSyncArena {
+            shared: Mutex::new(Shared {
+                handle: Bump::new(),
+                items: Vec::new(),
+            }),
+            marker: PhantomData,
+        }
+    }
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        // Allocating never panics while the lock is held, so the state is always consistent
+        self.shared.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    #[inline]
+    fn alloc_value<T>(&self, value: T, register: bool) -> &T {
+        let mut shared = self.lock();
+        let target = shared
+            .handle
+            .alloc_layout(Layout::new::<T>())
+            .as_ptr()
+            .cast::<T>();
+        unsafe {
+            target.write(value);
+            if register && mem::needs_drop::<T>() {
+                shared.items.push(DynamicArenaItem::single(
+                    drop_glue::<T>,
+                    target.cast(),
+                    mem::size_of::<T>(),
+                    None,
+                    DEFAULT_DROP_PHASE,
+                ));
+            }
+            // The chunks never move, so the value lives as long as the arena
+            &*target
+        }
+    }
+    /// Allocate the specified value in this arena,
+    /// returning a shared reference which will be valid for the lifetime of the entire arena.
+    ///
+    /// The bound on this item requires that `T: Send + Sync + 'a`,
+    /// since other threads can access it and it could be dropped on any thread.
+    #[inline]
+    pub fn alloc<T: Send + Sync + 'a>(&self, value: T) -> &T {
+        self.alloc_value(value, true)
+    }
+    /// Allocate the specified copyable value in this arena.
+    ///
+    /// Since `Copy` values never need to be dropped, they aren't registered.
+    #[inline]
+    pub fn alloc_copy<T: Copy + Send + Sync>(&self, value: T) -> &T {
+        self.alloc_value(value, false)
+    }
+    /// Allocate a copy of the specified string in this arena.
+    #[inline]
+    pub fn alloc_str(&self, s: &str) -> &str {
+        let shared = self.lock();
+        let copied: &str = shared.handle.alloc_str(s);
+        // The chunks never move, so the string lives as long as the arena
+        unsafe { &*(copied as *const str) }
+    }
+    /// The number of bytes in the arena's chunks
+    #[inline]
+    pub fn allocated_bytes(&self) -> usize {
+        self.lock().handle.allocated_bytes()
+    }
+    /// The number of values that are registered to be dropped
+    #[inline]
+    pub fn pending_drop_count(&self) -> usize {
+        self.lock().items.len()
+    }
+}
+impl DynamicSyncArena<'static> {
+    /// Create a new empty arena, accepting only values that are `'static`
+    #[inline]
+    pub fn new() -> Self {
+        DynamicSyncArena::new_bounded()
+    }
+}
+impl Default for DynamicSyncArena<'static> {
+    #[inline]
+    fn default() -> Self {
+        DynamicSyncArena::new()
+    }
+}
+impl<'a> Drop for DynamicSyncArena<'a> {
+    #[inline]
+    fn drop(&mut self) {
+        let shared = self
+            .shared
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        // Items must be dropped before the arena, in reverse registration order
+        let mut first_panic = DropPanic::default();
+        first_panic.drop_all(mem::take(&mut shared.items).into_iter().rev());
+        first_panic.resume();
+    }
+}
+/// A summary of the arena's usage, which doesn't block if the arena is locked
+impl<'a> Debug for DynamicSyncArena<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("DynamicSyncArena");
+        match self.shared.try_lock() {
+            Ok(shared) => debug
+                .field("allocated_bytes", &shared.handle.allocated_bytes())
+                .field("registered_drop_count", &shared.items.len()),
+            Err(_) => debug.field("allocated_bytes", &format_args!("<locked>")),
+        };
+        debug.finish()
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use crate::test_util::DropCounted;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[test]
+    fn stress() {
+        const THREADS: usize = 8;
+        const PER_THREAD: usize = 10_000;
+        let counter = AtomicUsize::new(0);
+        let arena = DynamicSyncArena::new_bounded();
+        thread::scope(|scope| {
+            for thread in 0..THREADS {
+                let (arena, counter) = (&arena, &counter);
+                scope.spawn(move || {
+                    let mut values = Vec::with_capacity(PER_THREAD);
+                    for i in 0..PER_THREAD {
+                        values.push(arena.alloc((DropCounted(counter), thread * PER_THREAD + i)));
+                        arena.alloc_copy(i);
+                    }
+                    // Nothing was overwritten by the other threads
+                    for (i, value) in values.iter().enumerate() {
+                        assert_eq!(value.1, thread * PER_THREAD + i);
+                    }
+                });
+            }
+        });
+        assert_eq!(arena.pending_drop_count(), THREADS * PER_THREAD);
+        assert_eq!(counter.load(Ordering::Relaxed), 0);
+        drop(arena);
+        assert_eq!(counter.load(Ordering::Relaxed), THREADS * PER_THREAD);
+    }
+    #[test]
+    fn strings() {
+        let arena = DynamicSyncArena::new();
+        let name = arena.alloc_str("shared");
+        let owned = arena.alloc(String::from("owned"));
+        assert_eq!((name, owned.as_str()), ("shared", "owned"));
+        assert!(format!("{:?}", arena).contains("registered_drop_count: 1"));
+    }
+}
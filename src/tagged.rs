@@ -0,0 +1,189 @@
+//! References with a small tag packed into their low bits, which are always zero given enough alignment.
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::NonNull;
+
+/// A reference to an arena value, packed into a single word along with a `BITS`-wide tag
+///
+/// The tag is stored in the low bits of the address, which are always zero as long as
+/// the value is aligned to `1 << BITS` bytes.
+/// Tags that fit in the natural alignment of `T` always work,
+/// and that check is done at compile time.
+/// Wider tags need more alignment than the type provides,
+/// usually from `DynamicArenaBuilder::min_align` or `DynamicArena::alloc_with_align`,
+/// so the address is checked when the pointer is created.
+///
+/// Equality and hashing compare the packed word (the address and the tag), not the value.
+///
+/// ```
+/// use dynamic_arena::{DynamicArena, TaggedPtr};
+///
+/// let arena: DynamicArena = DynamicArena::builder().min_align(8).build();
+/// let mut ptr = TaggedPtr::<_, 3>::new(arena.alloc_copy(7u8), 5);
+/// assert_eq!((*ptr.get(), ptr.tag()), (7, 5));
+/// ptr.set_tag(2);
+/// assert_eq!(ptr.tag(), 2);
+/// ```
+pub struct TaggedPtr<'arena, T, const BITS: u32> {
+    /// The address plus the tag, derived from the original reference so it keeps its provenance
+    packed: NonNull<u8>,
+    marker: PhantomData<&'arena T>,
+}
+impl<'arena, T, const BITS: u32> TaggedPtr<'arena, T, BITS> {
+    /// The mask of the bits used by the tag
+    ///
+    /// Using this (or creating a pointer) fails to compile if the tag is wider than 16 bits,
+    /// since no arena guarantees that much alignment.
+    pub const TAG_MASK: usize = {
+        assert!(BITS <= 16, "Tags can't be wider than 16 bits");
+        (1 << BITS) - 1
+    };
+    /// Whether the natural alignment of `T` already leaves room for the tag
+    const NATURALLY_ALIGNED: bool = mem::align_of::<T>() > Self::TAG_MASK;
+    /// Pack the specified reference and tag together
+    ///
+    /// ## Panics
+    /// If the tag doesn't fit in `BITS` bits,
+    /// or the reference isn't aligned enough to leave room for it.
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn new(value: &'arena T, tag: u32) -> Self {
+        match Self::try_new(value, tag) {
+            Some(ptr) => ptr,
+            None => panic!(
+                "Unable to pack tag {:#x} into {:p}, since {} bits are needed",
+                tag, value, BITS
+            ),
+        }
+    }
+    /// Pack the specified reference and tag together,
+    /// or return `None` if either the tag or the alignment is too small
+    #[inline]
+    pub fn try_new(value: &'arena T, tag: u32) -> Option<Self> {
+        let address = value as *const T as usize;
+        // Only checked at runtime when the type doesn't guarantee it
+        if !Self::NATURALLY_ALIGNED && address & Self::TAG_MASK != 0 {
+            return None;
+        }
+        let tag = Self::checked_tag(tag)?;
+        let ptr = (value as *const T).cast::<u8>().wrapping_add(tag);
+        Some(TaggedPtr {
+            packed: unsafe { NonNull::new_unchecked(ptr as *mut u8) },
+            marker: PhantomData,
+        })
+    }
+    /// The reference, without the tag
+    #[inline]
+    pub fn get(self) -> &'arena T {
+        let ptr = self.packed.as_ptr().wrapping_sub(self.tag() as usize);
+        // Created from a reference that lives for 'arena
+        unsafe { &*ptr.cast::<T>() }
+    }
+    /// The tag
+    #[inline]
+    pub fn tag(self) -> u32 {
+        (self.packed.as_ptr() as usize & Self::TAG_MASK) as u32
+    }
+    /// Replace the tag, keeping the same reference
+    ///
+    /// ## Panics
+    /// If the tag doesn't fit in `BITS` bits.
+    #[inline]
+    #[cfg_attr(feature = "track-callers", track_caller)]
+    pub fn set_tag(&mut self, tag: u32) {
+        *self = Self::new(self.get(), tag);
+    }
+    /// The packed word, with the address in the high bits and the tag in the low bits
+    #[inline]
+    pub fn to_bits(self) -> usize {
+        self.packed.as_ptr() as usize
+    }
+    #[inline]
+    fn checked_tag(tag: u32) -> Option<usize> {
+        let tag = tag as usize;
+        if tag & !Self::TAG_MASK == 0 {
+            Some(tag)
+        } else {
+            None
+        }
+    }
+}
+impl<'arena, T, const BITS: u32> Clone for TaggedPtr<'arena, T, BITS> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'arena, T, const BITS: u32> Copy for TaggedPtr<'arena, T, BITS> {}
+impl<'arena, T, const BITS: u32> PartialEq for TaggedPtr<'arena, T, BITS> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.packed == other.packed
+    }
+}
+impl<'arena, T, const BITS: u32> Eq for TaggedPtr<'arena, T, BITS> {}
+impl<'arena, T, const BITS: u32> Hash for TaggedPtr<'arena, T, BITS> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_bits().hash(state)
+    }
+}
+impl<'arena, T: Debug, const BITS: u32> Debug for TaggedPtr<'arena, T, BITS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaggedPtr")
+            .field("value", self.get())
+            .field("tag", &self.tag())
+            .finish()
+    }
+}
+/// Just like a shared reference
+unsafe impl<'arena, T: Sync, const BITS: u32> Send for TaggedPtr<'arena, T, BITS> {}
+unsafe impl<'arena, T: Sync, const BITS: u32> Sync for TaggedPtr<'arena, T, BITS> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DynamicArena;
+    #[cfg(not(feature = "no-drop-tracking"))]
+    use std::collections::HashSet;
+
+    #[cfg(not(feature = "no-drop-tracking"))]
+    #[test]
+    fn round_trip() {
+        let arena: DynamicArena = DynamicArena::builder().min_align(16).build();
+        let mut seen = HashSet::new();
+        for index in 0..64u32 {
+            let value = &*arena.alloc(index.to_string());
+            let mut ptr = TaggedPtr::<_, 4>::new(value, index % 16);
+            assert!(std::ptr::eq(ptr.get(), value));
+            assert_eq!(ptr.tag(), index % 16);
+            assert!(seen.insert(ptr));
+            ptr.set_tag(15 - index % 16);
+            assert_eq!(
+                (ptr.get().as_str(), ptr.tag()),
+                (&*index.to_string(), 15 - index % 16)
+            );
+            assert_ne!(ptr, TaggedPtr::new(value, index % 16));
+        }
+        // Natural alignment is enough for narrow tags, regardless of the arena
+        let arena: DynamicArena = DynamicArena::new();
+        let natural = TaggedPtr::<u64, 3>::new(arena.alloc_copy(9), 7);
+        assert_eq!((*natural.get(), natural.tag()), (9, 7));
+    }
+
+    #[test]
+    fn rejects_wide_tags() {
+        let arena: DynamicArena = DynamicArena::builder().min_align(4).build();
+        let value = arena.alloc_copy(1u8);
+        assert!(TaggedPtr::<_, 2>::try_new(value, 4).is_none());
+        assert!(TaggedPtr::<_, 2>::try_new(value, 3).is_some());
+        // The arena doesn't guarantee an eight byte alignment, so this only works at some addresses
+        let unaligned = (0..8)
+            .map(|_| &*arena.alloc_copy(0u8))
+            .find(|value| *value as *const u8 as usize & 7 != 0)
+            .unwrap();
+        assert!(TaggedPtr::<_, 3>::try_new(unaligned, 0).is_none());
+    }
+}
@@ -0,0 +1,251 @@
+//! Panic-safe and incremental teardown, for bounding the pause of dropping a huge arena.
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+
+use crate::{DynamicArena, DynamicArenaItem, Sendable};
+
+/// The first panic from running the drop functions, which is resumed once teardown is finished
+///
+/// Just like the elements of a `Vec`, the remaining items are still dropped if one of them panics.
+/// Unlike a `Vec`, later panics are discarded instead of aborting.
+#[derive(Default)]
+pub(crate) struct DropPanic(Option<Box<dyn Any + Send>>);
+impl DropPanic {
+    /// Drop the specified item, recording its panic if it's the first one
+    #[inline]
+    pub(crate) fn drop_item(&mut self, item: DynamicArenaItem) {
+        if let Err(cause) = panic::catch_unwind(AssertUnwindSafe(|| drop(item))) {
+            self.0.get_or_insert(cause);
+        }
+    }
+    /// Drop all the specified items in order
+    #[inline]
+    pub(crate) fn drop_all(&mut self, items: impl IntoIterator<Item = DynamicArenaItem>) {
+        for item in items {
+            self.drop_item(item);
+        }
+    }
+    /// Resume the first panic, if any
+    ///
+    /// If the thread is already unwinding (like when an arena is dropped by a panic),
+    /// resuming would abort, so the panic is discarded in favor of the current one.
+    #[inline]
+    pub(crate) fn resume(self) {
+        if let Some(cause) = self.0 {
+            if !thread::panicking() {
+                panic::resume_unwind(cause);
+            }
+        }
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// The number of values that are registered to be dropped
+    #[inline]
+    pub fn pending_drop_count(&self) -> usize {
+        self.items.len()
+    }
+    /// Run up to `max_items` of the registered drop functions,
+    /// returning the number of drops that remain.
+    ///
+    /// This allows the teardown of a huge arena to be amortized across idle time,
+    /// and dropping the arena runs whatever is left.
+    /// The arena stays usable between batches, although the memory isn't reclaimed.
+    ///
+    /// Each batch drops the most recently registered values first (in reverse order).
+    /// Values that are allocated between batches are newer than all the remaining ones,
+    /// so they're the first to be dropped by the next batch.
+    /// Since only the end of the drop list is removed, checkpoints taken
+    /// before the dropped values still roll back correctly.
+    ///
+    /// Since this requires `&mut self`,
+    /// it's statically impossible for any references into the arena to be alive.
+    pub fn drop_some(&mut self, max_items: usize) -> usize {
+        if max_items > 0 && !self.items.is_empty() {
+            self.handle_epoch.invalidate();
+        }
+        // The list stays locked, so destructors can't register more values
+        let _lock = self.items.lock();
+        let target = self.items.len().saturating_sub(max_items);
+        // Each item is removed before it's dropped, so a panic leaves the list consistent
+        while let Some(item) = self.items.pop_at_most(self.items.len() - target) {
+            drop(item);
+        }
+        self.items.len()
+    }
+}
+impl DynamicArena<'static, Sendable> {
+    /// Package the arena into a task that drops it,
+    /// so teardown can be moved off a latency-sensitive thread.
+    ///
+    /// Nothing runs on the current thread, since the arena is simply moved into the task.
+    /// The task can be given to a background thread (or something like `spawn_blocking`).
+    ///
+    /// This is only available for `'static` arenas,
+    /// since the task could run after any shorter lifetime has ended.
+    #[inline]
+    pub fn into_drop_task(self) -> impl FnOnce() + Send + 'static {
+        move || drop(self)
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use crate::test_util::PanicsOnDrop;
+    use std::cell::{Cell, RefCell};
+    use std::sync::{Arc, Mutex};
+    use std::thread::{self, ThreadId};
+
+    struct DropOrder<'a>(&'a RefCell<Vec<u32>>, u32);
+    impl<'a> Drop for DropOrder<'a> {
+        fn drop(&mut self) {
+            self.0.borrow_mut().push(self.1);
+        }
+    }
+
+    #[test]
+    fn batches() {
+        let order = RefCell::new(Vec::new());
+        let mut arena = DynamicArena::new_bounded();
+        let mut checkpoint = None;
+        for id in 0..10 {
+            if id == 4 {
+                checkpoint = Some(arena.checkpoint());
+            }
+            arena.alloc(DropOrder(&order, id));
+        }
+        arena.alloc_copy(0u64);
+        assert_eq!(arena.pending_drop_count(), 10);
+        assert_eq!(arena.drop_some(3), 7);
+        assert_eq!(*order.borrow(), vec![9, 8, 7]);
+        // New values are dropped first by the next batch
+        arena.alloc(DropOrder(&order, 100));
+        arena.alloc(DropOrder(&order, 101));
+        assert_eq!(arena.drop_some(3), 6);
+        assert_eq!(*order.borrow(), vec![9, 8, 7, 101, 100, 6]);
+        // The earlier checkpoint is unaffected
+        arena.rollback_to(checkpoint.unwrap());
+        assert_eq!(arena.pending_drop_count(), 4);
+        assert_eq!(*order.borrow(), vec![9, 8, 7, 101, 100, 6, 5, 4]);
+        assert_eq!(arena.drop_some(0), 4);
+        drop(arena);
+        assert_eq!(
+            *order.borrow(),
+            vec![9, 8, 7, 101, 100, 6, 5, 4, 3, 2, 1, 0]
+        );
+    }
+    #[test]
+    fn exhausted() {
+        let order = RefCell::new(Vec::new());
+        let mut arena = DynamicArena::new_bounded();
+        arena.alloc(DropOrder(&order, 0));
+        assert_eq!(arena.drop_some(usize::MAX), 0);
+        assert_eq!(arena.drop_some(10), 0);
+        assert_eq!(*order.borrow(), vec![0]);
+    }
+    struct DropThread(Arc<Mutex<Vec<ThreadId>>>);
+    impl Drop for DropThread {
+        fn drop(&mut self) {
+            self.0.lock().unwrap().push(thread::current().id());
+        }
+    }
+    #[test]
+    fn panic_isolated() {
+        let counter = Cell::new(0);
+        let arena = DynamicArena::new_bounded();
+        for index in 0..10_000 {
+            arena.alloc(PanicsOnDrop(&counter, index % 2500 == 1234));
+        }
+        arena.alloc_in_phase(0, PanicsOnDrop(&counter, false));
+        let cause = panic::catch_unwind(AssertUnwindSafe(|| drop(arena))).unwrap_err();
+        assert_eq!(*cause.downcast::<&str>().unwrap(), "Expected");
+        // Every other destructor still ran, and the later panics were discarded
+        assert_eq!(counter.get(), 10_001);
+        let mut arena = DynamicArena::new_bounded();
+        arena.alloc(PanicsOnDrop(&counter, false));
+        arena.alloc(PanicsOnDrop(&counter, true));
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| arena.reset())).is_err());
+        assert_eq!((counter.get(), arena.pending_drop_count()), (10_003, 0));
+    }
+    #[test]
+    fn panic_while_unwinding() {
+        let counter = Cell::new(0);
+        let cause = panic::catch_unwind(AssertUnwindSafe(|| {
+            let arena = DynamicArena::new_bounded();
+            arena.alloc(PanicsOnDrop(&counter, true));
+            arena.alloc(PanicsOnDrop(&counter, false));
+            // Dropping the arena while unwinding doesn't abort
+            panic!("Outer");
+        }))
+        .unwrap_err();
+        assert_eq!(*cause.downcast::<&str>().unwrap(), "Outer");
+        assert_eq!(counter.get(), 2);
+    }
+    /// Tries to allocate in its own arena when it's dropped, recording whether that panicked
+    struct Reentrant<'a> {
+        arena: *const DynamicArena<'a>,
+        counter: &'a Cell<u32>,
+        rejected: &'a Cell<usize>,
+    }
+    impl<'a> Drop for Reentrant<'a> {
+        fn drop(&mut self) {
+            let arena = unsafe { &*self.arena };
+            let counter = self.counter;
+            let attempt = panic::catch_unwind(AssertUnwindSafe(|| {
+                arena.alloc(PanicsOnDrop(counter, false));
+            }));
+            let cause = attempt.unwrap_err();
+            assert_eq!(
+                *cause.downcast::<String>().unwrap(),
+                crate::TEARDOWN_REGISTRATION
+            );
+            self.rejected.set(self.rejected.get() + 1);
+        }
+    }
+    fn alloc_reentrant<'a>(
+        arena: &DynamicArena<'a>,
+        counter: &'a Cell<u32>,
+        rejected: &'a Cell<usize>,
+    ) {
+        arena.alloc(Reentrant {
+            arena,
+            counter,
+            rejected,
+        });
+    }
+    #[test]
+    // The destructors reach the arena through a pointer while it's mutably borrowed,
+    // which is what the lock guards against, but it's still an aliasing violation under Miri
+    #[cfg_attr(miri, ignore)]
+    fn registration_during_teardown() {
+        let (counter, rejected) = (Cell::new(0), Cell::new(0));
+        // The arena is boxed, so it doesn't move when it's dropped
+        let mut arena = Box::new(DynamicArena::new_bounded());
+        alloc_reentrant(&arena, &counter, &rejected);
+        arena.reset();
+        alloc_reentrant(&arena, &counter, &rejected);
+        arena.drop_some(1);
+        alloc_reentrant(&arena, &counter, &rejected);
+        drop(arena);
+        // Every attempt panicked, and the values were dropped instead of leaked
+        assert_eq!((rejected.get(), counter.get()), (3, 3));
+    }
+    #[test]
+    fn drop_task() {
+        let threads = Arc::new(Mutex::new(Vec::new()));
+        let arena = DynamicArena::new_send();
+        for _ in 0..100 {
+            arena.alloc(DropThread(Arc::clone(&threads)));
+        }
+        let task = arena.into_drop_task();
+        assert!(threads.lock().unwrap().is_empty());
+        let background = thread::spawn(task);
+        let expected = background.thread().id();
+        background.join().unwrap();
+        let threads = threads.lock().unwrap();
+        assert_eq!(threads.len(), 100);
+        assert!(threads.iter().all(|&id| id == expected));
+    }
+}
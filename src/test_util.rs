@@ -0,0 +1,80 @@
+//! Helpers shared by the unit tests.
+#[cfg(not(feature = "no-drop-tracking"))]
+use std::cell::Cell;
+#[cfg(not(feature = "no-drop-tracking"))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(not(feature = "no-drop-tracking"))]
+use std::sync::Arc;
+
+use crate::DynamicArena;
+
+/// Counts the values that were dropped
+#[cfg(not(feature = "no-drop-tracking"))]
+pub(crate) trait DropCounter {
+    fn increment(&self);
+}
+#[cfg(not(feature = "no-drop-tracking"))]
+impl DropCounter for Cell<u32> {
+    #[inline]
+    fn increment(&self) {
+        self.set(self.get() + 1);
+    }
+}
+#[cfg(not(feature = "no-drop-tracking"))]
+impl DropCounter for AtomicUsize {
+    #[inline]
+    fn increment(&self) {
+        self.fetch_add(1, Ordering::SeqCst);
+    }
+}
+#[cfg(not(feature = "no-drop-tracking"))]
+impl<C: DropCounter + ?Sized> DropCounter for &C {
+    #[inline]
+    fn increment(&self) {
+        (**self).increment();
+    }
+}
+#[cfg(not(feature = "no-drop-tracking"))]
+impl<C: DropCounter + ?Sized> DropCounter for Arc<C> {
+    #[inline]
+    fn increment(&self) {
+        (**self).increment();
+    }
+}
+
+/// Increments its counter when it's dropped
+#[cfg(not(feature = "no-drop-tracking"))]
+pub(crate) struct DropCounted<C: DropCounter>(pub(crate) C);
+#[cfg(not(feature = "no-drop-tracking"))]
+impl<C: DropCounter> Drop for DropCounted<C> {
+    fn drop(&mut self) {
+        self.0.increment();
+    }
+}
+
+/// Increments its counter when it's dropped, then panics if it's been marked
+#[cfg(not(feature = "no-drop-tracking"))]
+pub(crate) struct PanicsOnDrop<C: DropCounter>(pub(crate) C, pub(crate) bool);
+#[cfg(not(feature = "no-drop-tracking"))]
+impl<C: DropCounter> Drop for PanicsOnDrop<C> {
+    fn drop(&mut self) {
+        self.0.increment();
+        if self.1 {
+            panic!("Expected");
+        }
+    }
+}
+
+/// The end of the arena's current chunk, which doesn't change as it's allocated into
+#[cfg(not(feature = "no-drop-tracking"))]
+pub(crate) fn current_chunk<S>(arena: &DynamicArena<'_, S>) -> usize {
+    let (ptr, len) = unsafe { arena.as_bumpalo().iter_allocated_chunks_raw() }
+        .next()
+        .unwrap();
+    ptr as usize + len
+}
+
+/// The number of chunks in the arena's own bump allocator
+pub(crate) fn chunk_count(arena: &DynamicArena) -> usize {
+    crate::stats::chunk_count(arena.as_bumpalo())
+}
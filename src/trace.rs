@@ -0,0 +1,66 @@
+//! Instrumentation with the `tracing` crate, enabled by the `tracing` feature.
+//!
+//! The event names and fields are documented at the crate root, since they're stable.
+use std::time::Instant;
+
+use crate::hook::Usage;
+
+/// The target of every event
+const TARGET: &str = "dynamic_arena";
+
+pub(crate) fn chunk_allocated(size: usize, capacity: usize) {
+    tracing::debug!(
+        target: TARGET,
+        chunk_bytes = size,
+        capacity_bytes = capacity,
+        "chunk_allocated"
+    );
+}
+pub(crate) fn limit_exceeded(requested: usize, usage: usize, limit: Option<usize>) {
+    tracing::warn!(
+        target: TARGET,
+        requested_bytes = requested,
+        usage_bytes = usage,
+        limit_bytes = limit,
+        "limit_exceeded"
+    );
+}
+pub(crate) fn reset(usage: &Usage) {
+    tracing::debug!(
+        target: TARGET,
+        reclaimed_bytes = usage.bytes,
+        dropped_items = usage.items,
+        "reset"
+    );
+}
+pub(crate) fn dropped(usage: &Usage, start: Instant) {
+    tracing::debug!(
+        target: TARGET,
+        allocated_bytes = usage.bytes,
+        dropped_items = usage.items,
+        drop_micros = start.elapsed().as_micros() as u64,
+        "dropped"
+    );
+}
+#[cfg(feature = "leak-audit")]
+pub(crate) fn leaked(type_name: &'static str, size: usize, count: usize) {
+    tracing::warn!(
+        target: TARGET,
+        type_name,
+        size_bytes = size,
+        count,
+        "leaked"
+    );
+}
+#[cfg(feature = "drop-profile")]
+pub(crate) fn drop_profiled(profile: &crate::DropProfile) {
+    for dropped in profile.types() {
+        tracing::debug!(
+            target: TARGET,
+            type_name = dropped.type_name(),
+            dropped_items = dropped.count(),
+            drop_micros = dropped.duration().as_micros() as u64,
+            "drop_profiled"
+        );
+    }
+}
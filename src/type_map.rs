@@ -0,0 +1,218 @@
+//! Per-arena extension data, keyed by type.
+use std::any::TypeId;
+use std::cell::Cell;
+use std::ffi::c_void;
+use std::mem;
+use std::ptr::{self, NonNull};
+
+use crate::{DynamicArena, ItemBound, NonSend};
+
+/// A value in the type map, chained to the ones inserted before it
+struct TypeEntry {
+    type_id: TypeId,
+    value: NonNull<()>,
+    drop_fn: Option<unsafe fn(*mut c_void)>,
+    next: Option<NonNull<TypeEntry>>,
+}
+
+/// The values of the arena's type map, newest first
+///
+/// The entries live in the arena, along with the values.
+/// The values are owned by the table rather than the drop list, and only dropped by `clear`,
+/// since rolling back a checkpoint (or dropping a batch) mustn't leave the map pointing at a dropped value.
+#[derive(Default)]
+pub(crate) struct TypeSlots {
+    head: Cell<Option<NonNull<TypeEntry>>>,
+    /// The number of distinct types
+    len: Cell<usize>,
+}
+/// The values are only `Send` in a `Sendable` arena
+unsafe impl Send for TypeSlots {}
+impl TypeSlots {
+    /// The most recent value with the specified type
+    #[inline]
+    fn find(&self, type_id: TypeId) -> Option<NonNull<()>> {
+        let mut next = self.head.get();
+        while let Some(entry) = next {
+            let entry = unsafe { entry.as_ref() };
+            if entry.type_id == type_id {
+                return Some(entry.value);
+            }
+            next = entry.next;
+        }
+        None
+    }
+    /// Drop every value, newest first, and forget them
+    ///
+    /// This must happen before the arena's chunks are reset or freed.
+    /// If a destructor panics, the older values are leaked.
+    pub(crate) fn clear(&self) {
+        let mut next = self.head.take();
+        self.len.set(0);
+        while let Some(entry) = next {
+            let entry = unsafe { ptr::read(entry.as_ptr()) };
+            next = entry.next;
+            if let Some(drop_fn) = entry.drop_fn {
+                unsafe { drop_fn(entry.value.as_ptr().cast()) };
+            }
+        }
+    }
+}
+
+/// A map holding at most one arena-allocated value of each type.
+///
+/// The values themselves are allocated in the arena, along with a small index from `TypeId` to the allocation.
+/// Every view returned by `DynamicArena::type_map` shares the same values,
+/// which are dropped when the arena is reset or dropped (but not by `rollback_to`, `drop_some` or `drop_region`).
+/// Since it's keyed by `TypeId`, the values must be `'static`.
+///
+/// This is created by `DynamicArena::type_map`.
+pub struct ArenaTypeMap<'arena, 'a, S = NonSend> {
+    arena: &'arena DynamicArena<'a, S>,
+}
+impl<'arena, 'a, S> ArenaTypeMap<'arena, 'a, S> {
+    /// Get the value with the specified type, if present
+    #[inline]
+    pub fn get<T: 'static>(&self) -> Option<&'arena T> {
+        self.arena
+            .type_slots
+            .find(TypeId::of::<T>())
+            // The values are immutable and owned by the arena
+            .map(|ptr| unsafe { &*ptr.cast::<T>().as_ptr() })
+    }
+    /// Check if there's a value with the specified type
+    #[inline]
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.arena.type_slots.find(TypeId::of::<T>()).is_some()
+    }
+    /// The number of types that have values
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.arena.type_slots.len.get()
+    }
+    /// Check if the map has no values
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+impl<'arena, 'a, S> ArenaTypeMap<'arena, 'a, S> {
+    /// Allocate the specified value in the arena, making it the value for its type
+    ///
+    /// If there was already a value of this type, it is replaced in the map.
+    /// However, since it's owned by the arena, it won't be dropped until the arena is reset or dropped.
+    /// Just like `DynamicArena::alloc`, the value must satisfy the arena's `ItemBound`.
+    pub fn insert<T: 'static>(&mut self, value: T) -> &'arena T
+    where
+        S: ItemBound<T>,
+    {
+        #[cfg(feature = "no-drop-tracking")]
+        crate::untracked::assert_no_drop::<T>();
+        let arena = self.arena;
+        let slots = &arena.type_slots;
+        let type_id = TypeId::of::<T>();
+        if slots.find(type_id).is_none() {
+            slots.len.set(slots.len.get() + 1);
+        }
+        unsafe {
+            let value = arena.alloc_value_raw(value);
+            let drop_fn: Option<unsafe fn(*mut c_void)> = if mem::needs_drop::<T>() {
+                Some(crate::drop_glue::<T>)
+            } else {
+                None
+            };
+            let entry = arena.handle.alloc(TypeEntry {
+                type_id,
+                value: NonNull::new_unchecked(value).cast(),
+                drop_fn,
+                next: slots.head.get(),
+            });
+            slots.head.set(Some(NonNull::from(entry)));
+            &*value
+        }
+    }
+    /// Get the value with the specified type,
+    /// allocating it in the arena if it's not already present.
+    pub fn get_or_insert_with<T: 'static>(&mut self, func: impl FnOnce() -> T) -> &'arena T
+    where
+        S: ItemBound<T>,
+    {
+        match self.get::<T>() {
+            Some(existing) => existing,
+            None => self.insert(func()),
+        }
+    }
+}
+impl<'a, S> DynamicArena<'a, S> {
+    /// A view of the arena's `ArenaTypeMap`, whose values are allocated in this arena
+    #[inline]
+    pub fn type_map(&self) -> ArenaTypeMap<'_, 'a, S> {
+        ArenaTypeMap { arena: self }
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use std::rc::Rc;
+
+    #[derive(Debug, PartialEq)]
+    struct Config(&'static str);
+
+    #[test]
+    fn typed_slots() {
+        let tracker = Rc::new(());
+        let arena = DynamicArena::new();
+        {
+            let mut map = arena.type_map();
+            assert!(map.get::<Config>().is_none());
+            let config = map.insert(Config("first"));
+            map.insert(42u32);
+            assert_eq!(map.get::<u32>(), Some(&42));
+            assert_eq!(map.get::<Config>(), Some(config));
+            let replacement = map.insert(Config("second"));
+            // The old value is still valid, since it's owned by the arena
+            assert_eq!(*config, Config("first"));
+            assert!(std::ptr::eq(map.get::<Config>().unwrap(), replacement));
+            let cloned = Rc::clone(&tracker);
+            assert!(Rc::ptr_eq(map.get_or_insert_with(|| cloned), &tracker));
+            assert!(Rc::ptr_eq(
+                map.get_or_insert_with::<Rc<()>>(|| unreachable!()),
+                &tracker
+            ));
+            assert_eq!(map.len(), 3);
+            assert!(map.contains::<u32>());
+            assert!(!map.contains::<u64>());
+            assert_eq!(Rc::strong_count(&tracker), 2);
+        }
+        drop(arena);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+    #[test]
+    fn shared_views() {
+        let tracker = Rc::new(());
+        let mut arena = DynamicArena::new();
+        arena.type_map().insert(Config("shared"));
+        let checkpoint = arena.checkpoint();
+        arena.type_map().insert(Rc::clone(&tracker));
+        assert_eq!(arena.type_map().get::<Config>(), Some(&Config("shared")));
+        assert_eq!(arena.type_map().len(), 2);
+        // The values belong to the map, so they outlive the checkpoint
+        arena.rollback_to(checkpoint);
+        assert_eq!(Rc::strong_count(&tracker), 2);
+        assert!(Rc::ptr_eq(
+            arena.type_map().get::<Rc<()>>().unwrap(),
+            &tracker
+        ));
+        arena.reset();
+        assert_eq!(Rc::strong_count(&tracker), 1);
+        assert!(arena.type_map().is_empty());
+    }
+    #[test]
+    fn sendable() {
+        let arena = DynamicArena::new_send();
+        let mut map = arena.type_map();
+        let name = map.get_or_insert_with(|| String::from("extension"));
+        assert_eq!(map.get::<String>(), Some(name));
+    }
+}
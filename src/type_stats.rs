@@ -0,0 +1,137 @@
+//! Per-type allocation accounting, enabled by the `type-stats` feature.
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::DynamicArena;
+
+/// The allocations of a single type, as reported by `DynamicArena::type_stats`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TypeStat {
+    type_name: Option<&'static str>,
+    count: usize,
+    bytes: usize,
+}
+impl TypeStat {
+    /// The name of the type (from `std::any::type_name`),
+    /// or `None` for allocations made through the untyped APIs
+    ///
+    /// The untyped APIs include `alloc_layout`, along with the slice and string methods.
+    #[inline]
+    pub fn type_name(&self) -> Option<&'static str> {
+        self.type_name
+    }
+    /// The number of allocations
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+    /// The total number of bytes allocated
+    #[inline]
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+/// The accounting for each type
+///
+/// Types are identified by their names, since `TypeId` requires `'static` types.
+/// The most recently used type is cached, so repeatedly allocating the same type
+/// doesn't need to hash its name.
+#[derive(Default)]
+pub(crate) struct TypeStats {
+    entries: RefCell<Vec<TypeStat>>,
+    indexes: RefCell<HashMap<Option<&'static str>, usize>>,
+    last: Cell<Option<(Option<&'static str>, usize)>>,
+}
+impl TypeStats {
+    #[inline]
+    pub(crate) fn record(&self, type_name: Option<&'static str>, bytes: usize) {
+        let index = match self.last.get() {
+            Some((last, index)) if same_name(last, type_name) => index,
+            _ => self.lookup(type_name),
+        };
+        let mut entries = self.entries.borrow_mut();
+        let entry = &mut entries[index];
+        entry.count += 1;
+        entry.bytes += bytes;
+    }
+    #[cold]
+    fn lookup(&self, type_name: Option<&'static str>) -> usize {
+        let mut entries = self.entries.borrow_mut();
+        let index = *self
+            .indexes
+            .borrow_mut()
+            .entry(type_name)
+            .or_insert_with(|| {
+                entries.push(TypeStat {
+                    type_name,
+                    count: 0,
+                    bytes: 0,
+                });
+                entries.len() - 1
+            });
+        self.last.set(Some((type_name, index)));
+        index
+    }
+    pub(crate) fn clear(&self) {
+        self.entries.borrow_mut().clear();
+        self.indexes.borrow_mut().clear();
+        self.last.set(None);
+    }
+}
+/// Compare the names by address, which is enough for the cache
+#[inline]
+fn same_name(first: Option<&'static str>, second: Option<&'static str>) -> bool {
+    match (first, second) {
+        (Some(first), Some(second)) => std::ptr::eq(first, second),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// The allocations of each type, sorted with the most bytes first
+    ///
+    /// These are recorded for the typed allocation methods (like `alloc` and `alloc_copy`),
+    /// while everything else is grouped together as untyped.
+    /// The counts are cumulative across resets, until `reset_peaks` is called.
+    /// Memory reused by `set_recycling` isn't counted again.
+    pub fn type_stats(&self) -> impl Iterator<Item = TypeStat> {
+        let mut stats = self.type_stats.entries.borrow().clone();
+        stats.sort_by_key(|stat| Reverse(stat.bytes));
+        stats.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn three_types() {
+        let mut arena = DynamicArena::new();
+        for _ in 0..10 {
+            arena.alloc([0u64; 8]);
+        }
+        arena.alloc_copy(1u8);
+        arena.alloc_copy(2u8);
+        arena.alloc(String::from("owned"));
+        arena.alloc_str("untyped");
+        let stats: Vec<_> = arena.type_stats().collect();
+        assert_eq!(stats.len(), 4);
+        assert_eq!(stats[0].type_name(), Some("[u64; 8]"));
+        assert_eq!(stats[0].count(), 10);
+        assert_eq!(stats[0].bytes(), 640);
+        assert_eq!(stats[1].type_name(), Some(std::any::type_name::<String>()));
+        assert_eq!(stats[1].bytes(), std::mem::size_of::<String>());
+        assert_eq!(stats[2].type_name(), None);
+        assert_eq!(stats[2].bytes(), 7);
+        assert_eq!(stats[3].type_name(), Some("u8"));
+        assert_eq!(stats[3].count(), 2);
+        arena.reset();
+        assert_eq!(arena.type_stats().count(), 4);
+        arena.reset_peaks();
+        assert_eq!(arena.type_stats().count(), 0);
+    }
+}
@@ -0,0 +1,122 @@
+//! The stand-in for the drop list when drop tracking is compiled out by the `no-drop-tracking` feature.
+//!
+//! Nothing can ever be registered, so the list has no state at all,
+//! and every method that would touch an entry is unreachable.
+use std::alloc::Layout;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr::NonNull;
+
+use crate::DynamicArenaItem;
+
+/// The message for the compile-time error when a value that needs to be dropped is registered
+const TRACKING_DISABLED: &str =
+    "Dropping values isn't supported, since the `no-drop-tracking` feature is enabled";
+
+/// Rejects types that need to be dropped, when it's evaluated
+struct NoDrop<T: ?Sized>(PhantomData<T>);
+impl<T: ?Sized> NoDrop<T> {
+    const ASSERT: () = assert!(!mem::needs_drop::<T>(), "{}", TRACKING_DISABLED);
+}
+
+/// Fail to compile if the type needs to be dropped
+#[inline(always)]
+pub(crate) fn assert_no_drop<T: ?Sized>() {
+    #[allow(clippy::let_unit_value)]
+    let () = NoDrop::<T>::ASSERT;
+}
+
+/// A drop list that's always empty
+#[derive(Default)]
+pub(crate) struct DropList;
+impl DropList {
+    /// Entries don't exist, but capacities are still measured as if they did
+    pub(crate) const NODE_LAYOUT: Layout = Layout::new::<(DynamicArenaItem, Option<NonNull<u8>>)>();
+    pub(crate) const NODE_SIZE: usize = DropList::NODE_LAYOUT.size();
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        0
+    }
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        true
+    }
+    #[inline]
+    pub(crate) fn next_serial(&self) -> u64 {
+        0
+    }
+    #[inline]
+    pub(crate) fn count_since(&self, _serial: u64) -> usize {
+        0
+    }
+    #[inline]
+    pub(crate) unsafe fn push(&self, _node: NonNull<u8>, _item: DynamicArenaItem) -> usize {
+        unreachable!("{}", TRACKING_DISABLED)
+    }
+    #[inline]
+    pub(crate) fn head_matches(
+        &self,
+        _drop: unsafe fn(*mut c_void),
+        _size: usize,
+        _tag: Option<u32>,
+        _phase: u8,
+    ) -> bool {
+        false
+    }
+    #[inline]
+    pub(crate) fn precedes_head(&self, _value: *mut c_void) -> bool {
+        false
+    }
+    #[inline]
+    pub(crate) unsafe fn extend_head(&self, _value: *mut c_void) -> usize {
+        unreachable!("{}", TRACKING_DISABLED)
+    }
+    #[inline]
+    pub(crate) fn pop_at_most(&self, _max: usize) -> Option<DynamicArenaItem> {
+        None
+    }
+    #[inline]
+    pub(crate) fn split_off(&self, _len: usize) -> Vec<DynamicArenaItem> {
+        Vec::new()
+    }
+    #[inline]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &DynamicArenaItem> + '_ {
+        std::iter::empty()
+    }
+    #[inline]
+    pub(crate) fn extract(
+        &self,
+        _pred: impl FnMut(&DynamicArenaItem) -> bool,
+    ) -> Vec<DynamicArenaItem> {
+        Vec::new()
+    }
+    #[inline]
+    pub(crate) unsafe fn remove_value(
+        &self,
+        _address: usize,
+        _spare: impl FnOnce() -> NonNull<u8>,
+    ) -> Option<(DynamicArenaItem, Option<NonNull<u8>>)> {
+        None
+    }
+    #[inline]
+    pub(crate) fn forget_all(&self) {}
+    #[inline]
+    pub(crate) fn append(&self, _other: &DropList) {}
+    #[inline]
+    pub(crate) fn is_dropping(&self) -> bool {
+        false
+    }
+    #[inline]
+    pub(crate) fn lock(&self) -> DroppingGuard<'_> {
+        DroppingGuard(PhantomData)
+    }
+}
+
+/// Nothing is ever dropped, so this doesn't need to mark anything
+pub(crate) struct DroppingGuard<'l>(PhantomData<&'l DropList>);
+/// Released explicitly, just like the real guard
+impl Drop for DroppingGuard<'_> {
+    #[inline]
+    fn drop(&mut self) {}
+}
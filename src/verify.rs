@@ -0,0 +1,167 @@
+//! Checking the arena's internal invariants.
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::DynamicArena;
+
+/// The invariant that was violated
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Invariant {
+    /// A registered value doesn't live in any of the arena's chunks
+    ForeignValue,
+    /// Two registered values have the same address
+    AliasedValue,
+    /// There are more registered values than allocations
+    UncountedValues,
+    /// The peak number of registered values is lower than the current number
+    StalePeak,
+}
+
+/// An error indicating that one of the arena's internal invariants doesn't hold
+///
+/// This usually means that an unsafe method (like `dynamic_drop`) was misused.
+///
+/// This is returned by `DynamicArena::verify`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VerifyError {
+    invariant: Invariant,
+    index: Option<usize>,
+    address: Option<usize>,
+}
+impl VerifyError {
+    /// The index in the drop list of the value that violated the invariant (if any)
+    #[inline]
+    pub fn drop_index(&self) -> Option<usize> {
+        self.index
+    }
+    /// The address of the value that violated the invariant (if any)
+    #[inline]
+    pub fn address(&self) -> Option<usize> {
+        self.address
+    }
+}
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.invariant {
+            Invariant::ForeignValue => f.write_str("Registered value isn't in the arena")?,
+            Invariant::AliasedValue => {
+                f.write_str("Registered value has the same address as another")?
+            }
+            Invariant::UncountedValues => {
+                f.write_str("More values are registered than were allocated")?
+            }
+            Invariant::StalePeak => {
+                f.write_str("Peak registered drop count is below the current count")?
+            }
+        }
+        if let Some(index) = self.index {
+            write!(f, " (drop list index {}", index)?;
+            if let Some(address) = self.address {
+                write!(f, ", address {:#x}", address)?;
+            }
+            f.write_str(")")?;
+        }
+        Ok(())
+    }
+}
+impl Error for VerifyError {}
+
+impl<'a, S> DynamicArena<'a, S> {
+    /// Check the arena's internal invariants, returning an error for the first one that doesn't hold
+    ///
+    /// This checks that every value registered to be dropped lives in one of the arena's chunks,
+    /// that no two registered values have the same address,
+    /// and that the counters are consistent with each other.
+    /// It's meant for catching misuse of the unsafe methods (like `dynamic_drop`) early,
+    /// so it takes time proportional to the number of registered values times the number of chunks.
+    ///
+    /// Zero-sized values don't occupy any memory, so their addresses aren't checked.
+    pub fn verify(&self) -> Result<(), VerifyError> {
+        let items = &self.items;
+        let mut addresses = HashSet::with_capacity(items.len());
+        // The list starts from the most recent value, but the indices are in registration order
+        let values = items
+            .iter()
+            .flat_map(|item| item.addresses().map(move |address| (item, address)));
+        for (index, (item, address)) in (0..items.len()).rev().zip(values) {
+            if item.zero_sized() {
+                continue;
+            }
+            let error = |invariant| VerifyError {
+                invariant,
+                index: Some(index),
+                address: Some(address),
+            };
+            if !self.contains_address(address) {
+                return Err(error(Invariant::ForeignValue));
+            }
+            if !addresses.insert(address) {
+                return Err(error(Invariant::AliasedValue));
+            }
+        }
+        let error = |invariant| VerifyError {
+            invariant,
+            index: None,
+            address: None,
+        };
+        if items.len() > self.len() {
+            return Err(error(Invariant::UncountedValues));
+        }
+        if items.len() > self.peaks.items.get() {
+            return Err(error(Invariant::StalePeak));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(feature = "no-drop-tracking")))]
+mod test {
+    use super::*;
+    use crate::CollectIn;
+
+    struct Marker;
+    impl Drop for Marker {
+        fn drop(&mut self) {}
+    }
+
+    #[test]
+    fn consistent() {
+        let mut arena = DynamicArena::new();
+        arena.verify().unwrap();
+        for i in 0..100 {
+            arena.alloc(i.to_string());
+            arena.alloc(Marker);
+        }
+        let _: &[String] = (0..10).map(|i| i.to_string()).collect_in(&arena);
+        arena.on_drop(|| {});
+        arena.verify().unwrap();
+        arena.reset();
+        arena.verify().unwrap();
+    }
+    #[test]
+    fn misuse_caught() {
+        let arena = DynamicArena::new();
+        let heap = Box::into_raw(Box::new(String::from("heap")));
+        unsafe { arena.dynamic_drop(heap) };
+        let error = arena.verify().unwrap_err();
+        assert_eq!(error.invariant, Invariant::ForeignValue);
+        assert_eq!(error.drop_index(), Some(0));
+        assert!(error
+            .to_string()
+            .starts_with("Registered value isn't in the arena"));
+        // The arena will drop the value, so the allocation needs to be freed separately
+        let mut arena = arena;
+        arena.forget_all();
+        drop(unsafe { Box::from_raw(heap) });
+        let value = arena.alloc(String::new());
+        // Debug builds reject the duplicate registration immediately
+        if !cfg!(debug_assertions) {
+            unsafe { arena.dynamic_drop(value as *mut String) };
+            let error = arena.verify().unwrap_err();
+            assert_eq!(error.invariant, Invariant::AliasedValue);
+            assert_eq!(error.drop_index(), Some(1));
+            arena.forget_all();
+        }
+    }
+}
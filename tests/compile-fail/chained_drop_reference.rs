@@ -0,0 +1,23 @@
+extern crate dynamic_arena;
+
+use dynamic_arena::DynamicArena;
+
+pub struct Holder<'a>(&'a str);
+impl<'a> Drop for Holder<'a> {
+    fn drop(&mut self) {
+        println!("{}", self.0);
+    }
+}
+
+fn main() {
+    let primary = DynamicArena::new_bounded();
+    let overflow = DynamicArena::new_bounded();
+    let chain = primary.with_overflow(&overflow);
+    let text = chain.alloc_str("in either arena");
+    /*
+     * If the holder were dropped by one arena after the other was freed,
+     * its drop function would read a dangling string.
+     * Since both arenas share the bound `'a`, this must be statically prevented.
+     */
+    chain.alloc(Holder(text));
+}
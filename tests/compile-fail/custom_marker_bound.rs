@@ -0,0 +1,21 @@
+extern crate dynamic_arena;
+
+use dynamic_arena::{DynamicArena, ItemBound, SendAbility};
+use std::cell::Cell;
+
+pub struct SyncOnly;
+impl SendAbility for SyncOnly {
+    fn create_arena<'a>() -> DynamicArena<'a, Self> {
+        DynamicArena::builder().marker().build()
+    }
+}
+unsafe impl<T: ?Sized + Send + Sync> ItemBound<T> for SyncOnly {}
+
+fn main() {
+    let arena = DynamicArena::<SyncOnly>::default();
+    /*
+     * A `Cell` is `Send` but not `Sync`,
+     * so it's rejected by the custom marker even though `Sendable` would accept it.
+     */
+    arena.alloc(Cell::new(5u32));
+}
@@ -0,0 +1,26 @@
+extern crate dynamic_arena;
+
+use dynamic_arena::{DropSafe, DynamicArena};
+
+pub struct Node<'n> {
+    pub parent: Option<&'n Node<'n>>,
+    pub labels: Vec<String>,
+}
+unsafe impl DropSafe for Node<'_> {}
+
+fn main() {
+    let arena = DynamicArena::new_bounded();
+    let root = arena.alloc_drop_safe(Node {
+        parent: None,
+        labels: vec![String::from("root")],
+    });
+    /*
+     * Implementing `DropSafe` only relaxes `alloc_drop_safe`.
+     * Plain `alloc` still requires the value to outlive the arena,
+     * which a reference into the arena can't.
+     */
+    arena.alloc(Node {
+        parent: Some(root),
+        labels: Vec::new(),
+    });
+}
@@ -0,0 +1,11 @@
+extern crate dynamic_arena;
+
+use dynamic_arena::DynamicArena;
+
+fn main() {
+    let mut arena = DynamicArena::new();
+    let name = arena.as_exclusive().alloc_str("name");
+    // The arena is still borrowed by the view's references
+    arena.reset();
+    println!("{}", name);
+}
@@ -0,0 +1,23 @@
+extern crate dynamic_arena;
+
+use dynamic_arena::SendableHerd;
+
+pub struct Holder<'a>(&'a str);
+impl<'a> Drop for Holder<'a> {
+    fn drop(&mut self) {
+        println!("{}", self.0);
+    }
+}
+
+fn main() {
+    let herd = SendableHerd::new_bounded();
+    let first = herd.get();
+    let second = herd.get();
+    let text = first.alloc_str("in another member");
+    /*
+     * If the holder were dropped after the other member was freed,
+     * its drop function would read a dangling string.
+     * References into the herd can only be stored in values without drop functions.
+     */
+    second.alloc(Holder(text));
+}
@@ -0,0 +1,15 @@
+extern crate dynamic_arena;
+
+use dynamic_arena::DynamicArena;
+
+fn assert_send<T: Send>(_value: T) {}
+
+fn main() {
+    let arena = DynamicArena::new_send();
+    arena.alloc(String::from("sendable"));
+    /*
+     * Once it's been converted, the arena can hold values that aren't `Send`,
+     * so it must not be `Send` itself anymore.
+     */
+    assert_send(arena.into_non_send());
+}
@@ -0,0 +1,14 @@
+extern crate dynamic_arena;
+
+use dynamic_arena::DynamicArena;
+use std::cell::Cell;
+use std::rc::Rc;
+
+fn main() {
+    let mut arena = DynamicArena::new_send();
+    let crossings = Rc::new(Cell::new(0));
+    /*
+     * The callback would be sent along with the arena, so it has to be `Send`.
+     */
+    arena.set_cycle_budget_with(16, move |_bytes| crossings.set(crossings.get() + 1));
+}
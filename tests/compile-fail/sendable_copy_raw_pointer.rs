@@ -0,0 +1,22 @@
+extern crate dynamic_arena;
+
+use dynamic_arena::DynamicArena;
+
+#[derive(Copy, Clone)]
+pub struct RawSpan {
+    start: *const u8,
+    len: usize,
+}
+
+fn main() {
+    let arena = DynamicArena::new_send();
+    let text = arena.alloc_str("raw pointers");
+    /*
+     * The `NonSend` arena accepts this, but a `Sendable` arena could be
+     * moved to another thread along with the pointer.
+     */
+    arena.alloc_copy(RawSpan {
+        start: text.as_ptr(),
+        len: text.len(),
+    });
+}
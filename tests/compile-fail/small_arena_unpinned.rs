@@ -0,0 +1,14 @@
+extern crate dynamic_arena;
+
+use dynamic_arena::SmallDynamicArena;
+
+fn main() {
+    let arena = SmallDynamicArena::<64>::new();
+    /*
+     * Moving the arena would invalidate values in its inline storage,
+     * so it can only allocate once it's pinned.
+     */
+    let value = arena.alloc(String::from("moved"));
+    let moved = arena;
+    println!("{} {:?}", value, moved);
+}
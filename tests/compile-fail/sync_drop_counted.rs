@@ -0,0 +1,21 @@
+extern crate dynamic_arena;
+
+use dynamic_arena::DynamicSyncArena;
+use std::sync::atomic::AtomicU32;
+
+pub struct DropCounted<'a>(&'a AtomicU32);
+
+fn main() {
+    let arena = DynamicSyncArena::new_bounded();
+    {
+        let counter = Box::new(AtomicU32::new(0));
+        for _i in 0..5 {
+            arena.alloc(DropCounted(&counter));
+        }
+    }
+    /*
+     * Just like `DynamicArena`, running this drop would reference the freed counter,
+     * so sharing the arena between threads must not weaken the lifetime bound.
+     */
+    drop(arena);
+}
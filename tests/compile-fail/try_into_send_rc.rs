@@ -0,0 +1,18 @@
+extern crate dynamic_arena;
+
+use dynamic_arena::DynamicArena;
+use std::rc::Rc;
+
+fn assert_send<T: Send>(_value: T) {}
+
+fn main() {
+    let arena = DynamicArena::new();
+    arena.alloc(Rc::new(5));
+    /*
+     * A failed conversion gives back the original arena, which is still `NonSend`.
+     */
+    match arena.try_into_send() {
+        Ok(arena) => assert_send(arena),
+        Err(arena) => assert_send(arena),
+    }
+}
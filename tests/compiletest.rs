@@ -2,4 +2,15 @@
 fn compile_test() {
     let tests = trybuild::TestCases::new();
     tests.compile_fail("tests/compile-fail/invalid_drop_counted.rs");
+    tests.compile_fail("tests/compile-fail/chained_drop_reference.rs");
+    tests.compile_fail("tests/compile-fail/sync_drop_counted.rs");
+    tests.compile_fail("tests/compile-fail/herd_drop_reference.rs");
+    tests.compile_fail("tests/compile-fail/into_non_send.rs");
+    tests.compile_fail("tests/compile-fail/try_into_send_rc.rs");
+    tests.compile_fail("tests/compile-fail/sendable_copy_raw_pointer.rs");
+    tests.compile_fail("tests/compile-fail/custom_marker_bound.rs");
+    tests.compile_fail("tests/compile-fail/drop_safe_alloc.rs");
+    tests.compile_fail("tests/compile-fail/exclusive_reset.rs");
+    tests.compile_fail("tests/compile-fail/small_arena_unpinned.rs");
+    tests.compile_fail("tests/compile-fail/sendable_budget_rc.rs");
 }
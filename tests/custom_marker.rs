@@ -0,0 +1,79 @@
+//! Markers defined outside the crate, using the `SendAbility` and `ItemBound` extension point.
+#![cfg(not(feature = "no-drop-tracking"))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use dynamic_arena::{CollectIn, DynamicArena, ItemBound, SendAbility};
+
+/// A marker that requires every item to be `Send + Sync`, so references can be shared freely
+#[derive(Debug)]
+struct SyncOnly;
+impl SendAbility for SyncOnly {
+    fn create_arena<'a>() -> DynamicArena<'a, Self> {
+        DynamicArena::builder().marker().build()
+    }
+}
+unsafe impl<T: ?Sized + Send + Sync> ItemBound<T> for SyncOnly {}
+
+/// A trait that's local to this crate, which a marker can require of every item
+trait Traced {
+    fn trace(&self) -> usize;
+}
+impl Traced for u32 {
+    fn trace(&self) -> usize {
+        1
+    }
+}
+impl Traced for Vec<u32> {
+    fn trace(&self) -> usize {
+        self.len()
+    }
+}
+/// A marker that only accepts `Traced` values, and isn't `Send`
+struct TracedOnly {
+    _marker: std::rc::Rc<()>,
+}
+impl SendAbility for TracedOnly {
+    fn create_arena<'a>() -> DynamicArena<'a, Self> {
+        DynamicArena::builder().marker().build()
+    }
+}
+unsafe impl<T: ?Sized + Traced> ItemBound<T> for TracedOnly {}
+
+struct DropCounted(Arc<AtomicUsize>);
+impl Drop for DropCounted {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn sync_marker() {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let arena = DynamicArena::<SyncOnly>::default();
+    arena.alloc(DropCounted(Arc::clone(&counter)));
+    let shared = arena.alloc(Arc::new(String::from("shared")));
+    assert_eq!(**shared, "shared");
+    let numbers: &[u32] = (0..4).collect_in(&arena);
+    assert_eq!(numbers, &[0, 1, 2, 3]);
+    arena.alloc_copy(7u64);
+    let finalized = Arc::clone(&counter);
+    arena.on_drop(move || {
+        finalized.fetch_add(1, Ordering::Relaxed);
+    });
+    // The marker is `Send`, so the whole arena is too
+    thread::spawn(move || drop(arena)).join().unwrap();
+    assert_eq!(counter.load(Ordering::Relaxed), 2);
+}
+#[test]
+fn local_trait_marker() {
+    let arena = DynamicArena::<TracedOnly>::default();
+    let single = arena.alloc(5u32);
+    let list = arena.alloc_tagged(1, vec![1u32, 2, 3]);
+    assert_eq!(single.trace() + list.trace(), 4);
+    assert_eq!(arena.pending_drop_count(), 1);
+    // The default implementation still pre-allocates the capacity
+    let arena = DynamicArena::<TracedOnly>::default_with_capacity(16, 4096);
+    assert!(arena.item_capacity() >= 16 && arena.byte_capacity() >= 4096);
+}
@@ -0,0 +1,42 @@
+//! Arenas with drop tracking compiled out, which only hold values without destructors.
+//!
+//! This is run with `cargo test --features no-drop-tracking`,
+//! which skips the rest of the tests that allocate values that need to be dropped.
+#![cfg(feature = "no-drop-tracking")]
+use dynamic_arena::{CollectIn, DynamicArena};
+
+/// A type that doesn't implement `Copy`, but still doesn't need to be dropped
+#[derive(Debug, PartialEq)]
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+#[test]
+fn allocates_without_tracking() {
+    let mut arena = DynamicArena::new();
+    let point = arena.alloc(Point { x: 1, y: 2 });
+    point.y += 1;
+    assert_eq!(*point, Point { x: 1, y: 3 });
+    let numbers: &[u32] = (0..100).collect_in(&arena);
+    assert_eq!(numbers.iter().sum::<u32>(), 4950);
+    assert_eq!(arena.alloc_str("text"), "text");
+    // Values without destructors are never registered
+    unsafe { arena.dynamic_drop(arena.alloc_copy(7u64)) };
+    assert_eq!(arena.pending_drop_count(), 0);
+    let checkpoint = arena.checkpoint();
+    arena.alloc_copy([0u8; 64]);
+    arena.rollback_to(checkpoint);
+    arena.reset();
+    assert!(arena.is_empty());
+}
+#[test]
+fn capacity() {
+    let mut arena = DynamicArena::builder().item_capacity(16).build();
+    for index in 0..1000u32 {
+        arena.alloc(index);
+    }
+    assert_eq!(arena.pending_drop_count(), 0);
+    arena.verify().unwrap();
+    arena.reset();
+}
@@ -0,0 +1,101 @@
+#![cfg(feature = "tracing")]
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use dynamic_arena::DynamicArena;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// The fields of a captured event, formatted with `Debug`
+type Fields = Vec<(&'static str, String)>;
+
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<Fields>>>);
+impl Capture {
+    fn named(&self, name: &str) -> Vec<Fields> {
+        let events = self.0.lock().unwrap();
+        events
+            .iter()
+            .filter(|fields| field(fields, "message") == Some(name))
+            .cloned()
+            .collect()
+    }
+}
+impl Subscriber for Capture {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target() == "dynamic_arena"
+    }
+    fn new_span(&self, _span: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+    fn event(&self, event: &Event<'_>) {
+        struct Visitor(Fields);
+        impl Visit for Visitor {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.0.push((field.name(), format!("{:?}", value)));
+            }
+        }
+        let mut visitor = Visitor(Vec::new());
+        event.record(&mut visitor);
+        self.0.lock().unwrap().push(visitor.0);
+    }
+    fn enter(&self, _span: &Id) {}
+    fn exit(&self, _span: &Id) {}
+}
+
+fn field<'f>(fields: &'f Fields, name: &str) -> Option<&'f str> {
+    fields
+        .iter()
+        .find(|(field, _)| *field == name)
+        .map(|(_, value)| value.as_str())
+}
+
+#[test]
+fn events_fire() {
+    let capture = Capture::default();
+    tracing::subscriber::with_default(capture.clone(), || {
+        let mut arena: DynamicArena = DynamicArena::with_capacity(10, 1024);
+        for i in 0..10 {
+            arena.alloc(i.to_string());
+        }
+        let chunks = capture.named("chunk_allocated");
+        assert_eq!(chunks.len(), 1);
+        assert!(field(&chunks[0], "chunk_bytes").is_some());
+        arena.alloc_slice_fill_copy(100_000, 0u8);
+        assert_eq!(capture.named("chunk_allocated").len(), 2);
+        arena.reset();
+        let resets = capture.named("reset");
+        assert_eq!(resets.len(), 1);
+        assert_eq!(field(&resets[0], "dropped_items"), Some("10"));
+        let reclaimed: usize = field(&resets[0], "reclaimed_bytes")
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(reclaimed >= 100_000);
+        arena.alloc(String::from("dropped"));
+        drop(arena);
+        let dropped = capture.named("dropped");
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(field(&dropped[0], "dropped_items"), Some("1"));
+        assert!(field(&dropped[0], "drop_micros").is_some());
+    });
+}
+
+#[test]
+fn limit_exceeded() {
+    let capture = Capture::default();
+    tracing::subscriber::with_default(capture.clone(), || {
+        let arena = DynamicArena::new();
+        arena.as_bumpalo().set_allocation_limit(Some(1024));
+        arena.try_alloc_copy(1u8).unwrap();
+        assert!(capture.named("limit_exceeded").is_empty());
+        assert!(arena.try_alloc_copy([0u8; 4096]).is_err());
+        let exceeded = capture.named("limit_exceeded");
+        assert_eq!(exceeded.len(), 1);
+        assert_eq!(field(&exceeded[0], "requested_bytes"), Some("4096"));
+        assert_eq!(field(&exceeded[0], "limit_bytes"), Some("1024"));
+    });
+}